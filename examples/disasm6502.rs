@@ -18,7 +18,7 @@ fn main()
 
     // ...and print!
     for i in instructions.iter() {
-        println!("{}", format_strings(&i));
+        println!("{}", format_strings(i));
     }
 
     println!("${:04X}: .END", bytes.len());
@@ -27,7 +27,7 @@ fn main()
 
 // helper string formatter
 fn format_strings(instruction: &Instruction) -> String {
-    let op_illegal_str = format!("{}", if instruction.illegal { "???" } else { "   " });
+    let op_illegal_str = if instruction.illegal { "???" } else { "   " };
 
     // format cycle count
     let cc = if instruction.extra_cycle {
@@ -42,9 +42,9 @@ fn format_strings(instruction: &Instruction) -> String {
         for r in reg_read.iter() {
             r_str.push_str(format!("{}", r).as_str());
         }
-        r_str.push_str("]");
+        r_str.push(']');
         for _ in 0..(13 - r_str.len()) {
-            r_str.push_str(" ");
+            r_str.push(' ');
         }
         r_str.to_owned()
     } else {
@@ -57,9 +57,9 @@ fn format_strings(instruction: &Instruction) -> String {
         for r in reg_written.iter() {
             r_str.push_str(format!("{}", r).as_str());
         }
-        r_str.push_str("]");
+        r_str.push(']');
         for _ in 0..(14 - r_str.len()) {
-            r_str.push_str(" ");
+            r_str.push(' ');
         }
         r_str.to_owned()
     } else {
@@ -72,7 +72,7 @@ fn format_strings(instruction: &Instruction) -> String {
         for f in aff_flags.iter() {
             f_str.push_str(format!("{}", f).as_str());
         }
-        f_str.push_str("]");
+        f_str.push(']');
         f_str.to_owned()
     } else {
         String::from("")
@@ -81,7 +81,7 @@ fn format_strings(instruction: &Instruction) -> String {
     // add extra spacing for better looks!
     let mut spacing = String::new();
     for _ in 0..(30 - format!("{}", instruction).len()) {
-        spacing.push_str(" ");
+        spacing.push(' ');
     }
 
     format!("{}{}{}{}{}{}", instruction, spacing, cc, rr, rw, af)
@@ -0,0 +1,451 @@
+//! Code flow analysis separating code from data.
+//!
+//! Bytes are classified as code either because they were executed (listed in an execution trace)
+//! or because they are reachable from an entry point or a traced instruction by following
+//! branches, jumps and subroutine calls. Everything else is left as unknown and disassembled as
+//! data. Execution traces resolve indirect jumps (`JMP ($xxxx)`, `RTS` tricks) which static
+//! analysis cannot follow.
+//!
+//! Analysis works on a `MemoryImage`, so control flow between segments is followed. In banked
+//! images all addresses are bank-qualified and the bank of branch, jump and call targets is
+//! determined by a `BankLayout`.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use banking::{BankedAddress, BankLayout};
+use instruction::{AddrMode, Instruction};
+use instruction::OpCode::*;
+use memory_image::MemoryImage;
+use segment::Segment;
+use text::TextString;
+
+/// Classification of a memory region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Region {
+    /// code confirmed by an execution trace
+    Traced,
+    /// code reached by static analysis
+    Inferred,
+    /// not known to be code
+    Unknown
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Region::Traced => "traced",
+            Region::Inferred => "inferred",
+            Region::Unknown => "unknown"
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Classification of every byte of a memory image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeMap {
+    // keyed by address first, so all banks of an address can be looked up together
+    regions: BTreeMap<(u16, Option<u16>), Region>,
+    instructions: BTreeSet<(u16, Option<u16>)>,
+    entry_points: BTreeSet<BankedAddress>,
+    references: BTreeMap<BankedAddress, BTreeSet<BankedAddress>>,
+    unresolved: BTreeSet<(BankedAddress, u16)>
+}
+
+impl CodeMap {
+    /// Region containing the address in any bank (Unknown outside of the analyzed image).
+    pub fn region(&self, address: u16) -> Region {
+        self.regions.range(banks(address))
+            .map(|(_, r)| *r)
+            .find(|r| *r != Region::Unknown)
+            .unwrap_or(Region::Unknown)
+    }
+
+    /// Region containing the bank-qualified address (any bank if the bank is not given).
+    pub fn region_at(&self, at: BankedAddress) -> Region {
+        match at.bank {
+            Some(_) => self.regions.get(&key(at)).cloned().unwrap_or(Region::Unknown),
+            None => self.region(at.address)
+        }
+    }
+
+    /// Check if an instruction starts at the address in any bank.
+    pub fn is_instruction(&self, address: u16) -> bool {
+        self.instructions.range(banks(address)).next().is_some()
+    }
+
+    /// Check if an instruction starts at the bank-qualified address.
+    pub fn is_instruction_at(&self, at: BankedAddress) -> bool {
+        self.instructions.contains(&key(at))
+    }
+
+    /// Addresses of all instructions found, in address order.
+    pub fn instructions(&self) -> Vec<BankedAddress> {
+        self.instructions.iter().map(|&(address, bank)| BankedAddress { bank, address }).collect()
+    }
+
+    /// Entry points of the analysis, including executed targets of indirect jumps found in the trace.
+    pub fn entry_points(&self) -> &BTreeSet<BankedAddress> {
+        &self.entry_points
+    }
+
+    /// Instructions branching to, jumping to or calling the address (cross references).
+    pub fn references(&self, target: BankedAddress) -> Vec<BankedAddress> {
+        self.references.get(&target).map_or(Vec::new(), |r| r.iter().cloned().collect())
+    }
+
+    /// Branches, jumps and calls whose target bank could not be determined: (instruction, target address).
+    pub fn unresolved(&self) -> &BTreeSet<(BankedAddress, u16)> {
+        &self.unresolved
+    }
+
+    // addresses occupied by the instruction, None if any of them is outside of the image
+    fn span(&self, instruction: &Instruction) -> Option<Vec<(u16, Option<u16>)>> {
+        let span: Vec<(u16, Option<u16>)> = (0..instruction.size())
+            .map(|i| (instruction.address.wrapping_add(i), instruction.bank))
+            .collect();
+        if span.iter().all(|k| self.regions.contains_key(k)) { Some(span) } else { None }
+    }
+
+    // mark instruction bytes, returns false if they conflict with already known code
+    fn mark(&mut self, instruction: &Instruction, region: Region) -> bool {
+        let span = match self.span(instruction) {
+            Some(s) => s,
+            None => return false
+        };
+
+        if self.instructions.contains(&span[0]) {
+            if region == Region::Traced {
+                self.set(&span, region);
+            }
+            return false;
+        }
+
+        if region == Region::Inferred && span.iter().any(|k| self.regions[k] != Region::Unknown) {
+            return false;
+        }
+
+        self.set(&span, region);
+        self.instructions.insert(span[0]);
+        true
+    }
+
+    fn set(&mut self, span: &[(u16, Option<u16>)], region: Region) {
+        for k in span.iter() {
+            self.regions.insert(*k, region);
+        }
+    }
+}
+
+fn key(at: BankedAddress) -> (u16, Option<u16>) {
+    (at.address, at.bank)
+}
+
+// key range covering all banks of an address
+fn banks(address: u16) -> ::std::ops::RangeInclusive<(u16, Option<u16>)> {
+    (address, None)..=(address, Some(u16::MAX))
+}
+
+/// Single line of disassembly output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// decoded instruction with the region it belongs to (Traced or Inferred)
+    Code(Instruction, Region),
+    /// instruction starting within the bytes of the preceding `Code` line, as executed in
+    /// `BIT` skip tricks
+    Overlapping(Instruction, Region),
+    /// byte in unknown region
+    Data(BankedAddress, u8),
+    /// text string found in data (see `text::find_strings`)
+    Text(TextString)
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Line::Code(ref instruction, _) => write!(f, "{}", instruction),
+            Line::Overlapping(ref instruction, _) => write!(f, "{} ; overlapping", instruction),
+            Line::Data(address, value) => write!(f, "{}: {:02X}       .BYTE ${:02X}", address, value, value),
+            Line::Text(ref text) => write!(f, "{}", text)
+        }
+    }
+}
+
+/// Extract program counters from an execution trace log, one per line.
+///
+/// Lines must start with a 4 digit hex address followed by the first instruction byte
+/// (`C000  4C F5 C5 ...` as written by Nintendulator/Mesen and this crate, `.C:0810  A9 00 ...`
+/// as written by VICE monitor). Other lines, such as headers and column titles, are skipped.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// let log = "ADDR BYTES\n.C:0810  A9 00     LDA #$00\n.C:0812  8D 20 D0  STA $D020\nBEEF CAFE";
+/// assert_eq!(disasm6502::analysis::parse_trace(log), vec![0x0810, 0x0812]);
+/// ```
+pub fn parse_trace(log: &str) -> Vec<u16> {
+    let hex = |w: &str, digits: usize| w.len() == digits && w.chars().all(|c| c.is_ascii_hexdigit());
+
+    log.lines()
+        .filter_map(|l| {
+            let mut words = l.split_whitespace();
+            let address = words.next()?.trim_start_matches('.');
+            // VICE prefixes the address with the memory space (`C:`)
+            let address = address.rsplit(':').find(|s| !s.is_empty()).unwrap_or(address);
+            let byte = words.next()?;
+            if hex(address, 4) && hex(byte, 2) { u16::from_str_radix(address, 16).ok() } else { None }
+        })
+        .collect()
+}
+
+/// Find code in the buffer starting from entry points and executed addresses.
+///
+/// Addresses listed in `trace` (in execution order) are marked as traced code; code reachable
+/// from them and from `entry_points` is marked as inferred. Traced instructions which are not
+/// a static successor of the previous one (targets of indirect jumps, `RTS` to an address not
+/// pushed by `JSR`, interrupt handlers) become additional entry points.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::analysis::Region;
+///
+/// // $0800: JMP ($0806) / $0803: RTS / $0804: INX / RTS / $0806: .WORD $0804
+/// let bytes = vec![0x6C, 0x06, 0x08, 0x60, 0xE8, 0x60, 0x04, 0x08];
+/// let map = disasm6502::analysis::analyze(&bytes, 0x0800, &[0x0800], &[0x0800, 0x0804, 0x0805]);
+///
+/// assert_eq!(map.region(0x0800), Region::Traced);
+/// assert_eq!(map.region(0x0803), Region::Unknown);
+/// assert_eq!(map.region(0x0804), Region::Traced);
+/// assert_eq!(map.region(0x0806), Region::Unknown);
+/// ```
+pub fn analyze(bytes: &[u8], start_address: u16, entry_points: &[u16], trace: &[u16]) -> CodeMap {
+    analyze_image(&single_segment(bytes, start_address), entry_points, trace)
+}
+
+/// Find code in a memory image starting from entry points and executed addresses.
+///
+/// Works like `analyze`, following branches, jumps and calls across segments.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::analysis::{self, Region};
+/// use disasm6502::memory_image::MemoryImage;
+/// use disasm6502::segment::Segment;
+///
+/// // $2000: JSR $3000 / RTS, $3000: RTS
+/// let image = MemoryImage::from(vec![Segment::new(0x2000, vec![0x20, 0x00, 0x30, 0x60]),
+///                                    Segment::new(0x3000, vec![0x60])]);
+/// let map = analysis::analyze_image(&image, &[0x2000], &[]);
+///
+/// assert_eq!(map.region(0x3000), Region::Inferred);
+/// ```
+pub fn analyze_image(image: &MemoryImage, entry_points: &[u16], trace: &[u16]) -> CodeMap {
+    let entry_points: Vec<BankedAddress> = entry_points.iter().map(|&a| BankedAddress::unbanked(a)).collect();
+    let trace: Vec<BankedAddress> = trace.iter().map(|&a| BankedAddress::unbanked(a)).collect();
+    analyze_banked(image, &BankLayout::new(), &entry_points, &trace)
+}
+
+/// Find code in a banked memory image starting from bank-qualified entry points and executed
+/// addresses.
+///
+/// Works like `analyze_image`; the bank of each branch, jump and call target is resolved with
+/// the layout (see `BankLayout::resolve`). Targets whose bank cannot be resolved are not
+/// followed and are reported by `CodeMap::unresolved`. Addresses without a bank refer to
+/// whichever segment contains them.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::analysis::{self, Region};
+/// use disasm6502::banking::{BankedAddress, BankLayout};
+/// use disasm6502::memory_image::MemoryImage;
+/// use disasm6502::segment::Segment;
+///
+/// // two banks at $8000: bank 0 starts with JSR $8000 in bank 1 (declared by an annotation)
+/// let image = MemoryImage::from(vec![Segment::banked(0, 0x8000, vec![0x20, 0x00, 0x80, 0x60]),
+///                                    Segment::banked(1, 0x8000, vec![0xE8, 0x60])]);
+/// let mut layout = BankLayout::new();
+/// layout.add_window(0x8000, 0x9FFF, &[0, 1]);
+/// layout.annotate(BankedAddress::new(0, 0x8000), 1);
+///
+/// let map = analysis::analyze_banked(&image, &layout, &[BankedAddress::new(0, 0x8000)], &[]);
+/// assert_eq!(map.region_at(BankedAddress::new(1, 0x8001)), Region::Inferred);
+/// assert_eq!(map.references(BankedAddress::new(1, 0x8000)), vec![BankedAddress::new(0, 0x8000)]);
+/// ```
+pub fn analyze_banked(image: &MemoryImage, layout: &BankLayout, entry_points: &[BankedAddress], trace: &[BankedAddress]) -> CodeMap {
+    analyze_filtered(image, layout, entry_points, trace, |_| true)
+}
+
+// analysis which does not infer code from instructions rejected by the filter
+// (executed instructions are always accepted)
+pub(crate) fn analyze_filtered<F>(image: &MemoryImage, layout: &BankLayout, entry_points: &[BankedAddress], trace: &[BankedAddress],
+                                  accept: F) -> CodeMap where F: Fn(&Instruction) -> bool {
+    let mut regions = BTreeMap::new();
+    image.walk(|_, at| {
+        regions.insert(key(at), Region::Unknown);
+        1
+    });
+
+    // addresses without a bank refer to the segment containing them
+    let locate = |at: BankedAddress| image.segment_in(at).map_or(at, |s| BankedAddress { bank: s.bank, address: at.address });
+
+    let mut map = CodeMap {
+        regions,
+        instructions: BTreeSet::new(),
+        entry_points: entry_points.iter().map(|&e| locate(e)).collect(),
+        references: BTreeMap::new(),
+        unresolved: BTreeSet::new()
+    };
+
+    // executed instructions, returns from subroutines called within the trace are expected
+    let mut previous: Option<Instruction> = None;
+    let mut calls = Vec::<u16>::new();
+    for &at in trace.iter() {
+        let at = locate(at);
+        let pc = at.address;
+        let instruction = match image.decode_banked(at) {
+            Some(i) => i,
+            None => { previous = None; continue; }
+        };
+
+        map.mark(&instruction, Region::Traced);
+        if let Some(ref p) = previous {
+            let expected = match p.opcode {
+                JSR(_) => { calls.push(p.address.wrapping_add(p.size())); pc == p.operand.unwrap_or(0) },
+                RTS(_) => calls.pop() == Some(pc),
+                RTI(_) => true,
+                _ => successors(p).contains(&pc)
+            };
+
+            if !expected {
+                map.entry_points.insert(at);
+            }
+        }
+        previous = Some(instruction);
+    }
+
+    // code reachable from entry points and executed instructions
+    let mut pending: Vec<BankedAddress> = map.entry_points.iter().cloned().chain(map.instructions()).collect();
+    let mut visited = BTreeSet::<BankedAddress>::new();
+    while let Some(at) = pending.pop() {
+        if !visited.insert(at) {
+            continue;
+        }
+
+        let instruction = match image.decode_banked(at) {
+            Some(i) => i,
+            None => continue
+        };
+
+        if map.region_at(at) != Region::Traced && (!accept(&instruction) || !map.mark(&instruction, Region::Inferred)) {
+            continue;
+        }
+
+        // sequential flow stays in the bank, targets are resolved with the layout
+        let from = instruction.banked_address();
+        let target = jump_target(&instruction);
+        for address in successors(&instruction) {
+            if Some(address) != target {
+                pending.push(BankedAddress { bank: from.bank, address });
+                continue;
+            }
+
+            let resolved = match layout.resolve(from, address) {
+                Some(BankedAddress { bank: None, address }) => place(image, from, address),
+                resolved => resolved.map(&locate)
+            };
+
+            match resolved {
+                Some(resolved) => {
+                    map.references.entry(resolved).or_default().insert(from);
+                    pending.push(resolved);
+                },
+                None => { map.unresolved.insert((from, address)); }
+            }
+        }
+    }
+
+    map
+}
+
+// bank of a target outside of all bank windows: the bank of the caller if it covers the target,
+// otherwise the only bank covering it (None if several do)
+fn place(image: &MemoryImage, from: BankedAddress, address: u16) -> Option<BankedAddress> {
+    let banks: BTreeSet<Option<u16>> = image.segments_containing(address).iter().map(|s| s.bank).collect();
+    let bank = match banks.len() {
+        0 => None,
+        1 => banks.into_iter().next().unwrap_or(None),
+        _ if banks.contains(&from.bank) => from.bank,
+        _ => return None
+    };
+    Some(BankedAddress { bank, address })
+}
+
+/// Disassemble the buffer according to the code map: instructions in code regions, data bytes elsewhere.
+pub fn disassemble(bytes: &[u8], start_address: u16, map: &CodeMap) -> Vec<Line> {
+    disassemble_image(&single_segment(bytes, start_address), map)
+}
+
+/// Disassemble all segments of a memory image according to the code map. Instructions starting
+/// within another instruction follow it as `Overlapping` lines.
+pub fn disassemble_image(image: &MemoryImage, map: &CodeMap) -> Vec<Line> {
+    let mut lines = Vec::new();
+
+    image.walk(|segment, at| {
+        if map.is_instruction_at(at) {
+            if let Some(instruction) = image.decode_in(segment, at.address) {
+                let size = instruction.size();
+                lines.push(Line::Code(instruction, map.region_at(at)));
+
+                for offset in 1..size {
+                    let inner = BankedAddress { address: at.address.wrapping_add(offset), ..at };
+                    if let Some(instruction) = image.decode_in(segment, inner.address).filter(|_| map.is_instruction_at(inner)) {
+                        lines.push(Line::Overlapping(instruction, map.region_at(inner)));
+                    }
+                }
+                return size;
+            }
+        }
+
+        lines.push(Line::Data(at, segment.read(at.address).unwrap_or(0)));
+        1
+    });
+
+    lines
+}
+
+/// Target of a branch, jump or subroutine call with an absolute address.
+pub fn jump_target(instruction: &Instruction) -> Option<u16> {
+    match (&instruction.opcode, &instruction.addr_mode) {
+        (_, &AddrMode::Relative) => {
+            let next = instruction.address.wrapping_add(instruction.size());
+            Some(next.wrapping_add(instruction.operand.unwrap_or(0) as i8 as u16))
+        },
+        (&JMP(_), &AddrMode::Absolute) | (&JSR(_), _) => instruction.operand,
+        _ => None
+    }
+}
+
+/// Addresses which can be executed after the instruction, as far as known statically.
+pub fn successors(instruction: &Instruction) -> Vec<u16> {
+    let next = instruction.address.wrapping_add(instruction.size());
+    let mut result: Vec<u16> = jump_target(instruction).into_iter().collect();
+
+    match instruction.opcode {
+        JMP(_) | RTS(_) | RTI(_) | BRK(_) | HLT(_) => {},
+        _ => result.push(next)
+    }
+
+    result
+}
+
+fn single_segment(bytes: &[u8], start_address: u16) -> MemoryImage {
+    MemoryImage::from(vec![Segment::new(start_address, bytes.to_vec())])
+}
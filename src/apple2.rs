@@ -0,0 +1,433 @@
+//! Apple II 5.25" disk image (.dsk/.do/.po) reader for DOS 3.3 and ProDOS.
+//!
+//! Images hold 35 tracks of 16 sectors, stored either in DOS 3.3 logical sector order (.do and
+//! most .dsk files) or in ProDOS block order (.po). DOS 3.3 files are listed from the catalog
+//! chain pointed to by the VTOC and read through their track/sector lists. ProDOS files are
+//! listed from the volume directory (and its subdirectories) and read through their index
+//! blocks (seedling, sapling and tree files).
+use std::collections::HashSet;
+use std::fmt;
+use error::{Disasm6502Error, Result};
+use fields::{ascii_text, word};
+use segment::Segment;
+
+/// Size of a sector in bytes.
+pub const SECTOR_SIZE: usize = 256;
+
+/// Size of a ProDOS block in bytes.
+pub const BLOCK_SIZE: usize = 512;
+
+/// Number of tracks on a disk.
+pub const TRACKS: u8 = 35;
+
+/// Number of sectors on a track.
+pub const SECTORS_PER_TRACK: u8 = 16;
+
+/// Track holding the DOS 3.3 VTOC.
+pub const VTOC_TRACK: u8 = 17;
+
+// DOS 3.3 logical sectors making up the two halves of each ProDOS block on a track
+const PRODOS_TO_DOS: [u8; 16] = [0, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 15];
+
+// DOS 3.3 catalog entries and track/sector list pairs
+const DOS_ENTRY_SIZE: usize = 35;
+const DOS_ENTRIES: usize = 7;
+const TS_PAIRS: usize = 122;
+
+// ProDOS volume directory key block and storage types
+const VOLUME_DIRECTORY_BLOCK: u16 = 2;
+const SEEDLING: u8 = 1;
+const SAPLING: u8 = 2;
+const TREE: u8 = 3;
+const SUBDIRECTORY: u8 = 0xD;
+const VOLUME_HEADER: u8 = 0xF;
+
+// ProDOS file type of binary files and of system programs (always loaded at $2000)
+const PRODOS_BIN: u8 = 0x06;
+const PRODOS_SYS: u8 = 0xFF;
+const SYS_LOAD_ADDRESS: u16 = 0x2000;
+
+/// Order of sectors in the image file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SectorOrder {
+    /// DOS 3.3 logical sector order (.do, most .dsk)
+    Dos,
+    /// ProDOS block order (.po)
+    ProDos
+}
+
+/// File system found on the disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileSystem {
+    Dos33,
+    ProDos
+}
+
+// location of file contents
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Key {
+    // first track/sector list
+    TsList(u8, u8),
+    // storage type, key block, file length
+    ProDos(u8, u16, usize)
+}
+
+/// Catalog entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogEntry {
+    /// file name (ProDOS names in subdirectories are prefixed with the directory path)
+    pub name: String,
+    /// file type ("T", "I", "A", "B", "S", "R" for DOS 3.3, "BIN", "SYS", "TXT"... or "$XX" for ProDOS)
+    pub file_type: String,
+    /// file is write protected
+    pub locked: bool,
+    /// file size in sectors (DOS 3.3) or blocks (ProDOS), as stored in the catalog
+    pub size: u16,
+    /// ProDOS auxiliary type (load address of binary files), 0 for DOS 3.3
+    pub aux_type: u16,
+    key: Key
+}
+
+impl fmt::Display for CatalogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{:<3} {:03} {}", if self.locked { "*" } else { " " }, self.file_type, self.size, self.name)
+    }
+}
+
+/// File extracted from disk image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppleFile {
+    /// load address of binary files (None for other file types)
+    pub load_address: Option<u16>,
+    /// file contents (without the load address and length header of DOS 3.3 binary files)
+    pub data: Vec<u8>
+}
+
+impl AppleFile {
+    /// Contents of a binary file placed at its load address.
+    pub fn segment(&self) -> Option<Segment> {
+        self.load_address.map(|address| Segment::new(address, self.data.clone()))
+    }
+}
+
+/// Apple II disk image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiskImage {
+    data: Vec<u8>,
+    order: SectorOrder,
+    file_system: FileSystem
+}
+
+impl DiskImage {
+    /// Create disk image from file contents. Sector order and file system are detected unless
+    /// the order is given (e.g. based on the file extension).
+    pub fn parse(bytes: &[u8], order: Option<SectorOrder>) -> Result<DiskImage> {
+        let size = TRACKS as usize * SECTORS_PER_TRACK as usize * SECTOR_SIZE;
+        if bytes.len() != size {
+            return Err(Disasm6502Error::InvalidFormat(format!("unexpected Apple II disk image size {}", bytes.len())));
+        }
+
+        let orders = match order {
+            Some(o) => vec![o],
+            None => vec![SectorOrder::Dos, SectorOrder::ProDos]
+        };
+
+        // DOS 3.3 first as ProDOS volumes cannot be mistaken for a VTOC
+        for &file_system in [FileSystem::Dos33, FileSystem::ProDos].iter() {
+            for &order in orders.iter() {
+                let image = DiskImage { data: bytes.to_vec(), order, file_system };
+                if image.has_file_system() {
+                    return Ok(image);
+                }
+            }
+        }
+
+        Err(Disasm6502Error::InvalidFormat(String::from("no DOS 3.3 or ProDOS file system found")))
+    }
+
+    /// Sector order of the image.
+    pub fn order(&self) -> SectorOrder {
+        self.order
+    }
+
+    /// File system of the disk.
+    pub fn file_system(&self) -> FileSystem {
+        self.file_system
+    }
+
+    /// Contents of a DOS 3.3 logical sector (None if track/sector is invalid).
+    pub fn sector(&self, track: u8, sector: u8) -> Option<&[u8]> {
+        if track >= TRACKS || sector >= SECTORS_PER_TRACK {
+            return None;
+        }
+
+        let position = match self.order {
+            SectorOrder::Dos => sector,
+            SectorOrder::ProDos => PRODOS_TO_DOS.iter().position(|&s| s == sector).unwrap_or(0) as u8
+        };
+
+        let offset = (track as usize * SECTORS_PER_TRACK as usize + position as usize) * SECTOR_SIZE;
+        Some(&self.data[offset..offset + SECTOR_SIZE])
+    }
+
+    /// Contents of a ProDOS block (None if the block is outside of the disk).
+    pub fn block(&self, block: u16) -> Option<Vec<u8>> {
+        let track = (block / 8) as u8;
+        let first = (block % 8) as usize * 2;
+
+        let mut data = self.sector(track, PRODOS_TO_DOS[first])?.to_vec();
+        data.extend_from_slice(self.sector(track, PRODOS_TO_DOS[first + 1])?);
+        Some(data)
+    }
+
+    /// List catalog entries (deleted DOS 3.3 entries are skipped).
+    pub fn catalog(&self) -> Result<Vec<CatalogEntry>> {
+        match self.file_system {
+            FileSystem::Dos33 => self.dos_catalog(),
+            FileSystem::ProDos => {
+                let mut entries = Vec::new();
+                self.prodos_directory(VOLUME_DIRECTORY_BLOCK, "", &mut HashSet::new(), &mut entries)?;
+                Ok(entries)
+            }
+        }
+    }
+
+    /// Find catalog entry by file name.
+    pub fn find(&self, name: &str) -> Result<Option<CatalogEntry>> {
+        Ok(self.catalog()?.into_iter().find(|e| e.name == name))
+    }
+
+    /// Read file contents. Binary files get their load address from the DOS 3.3 file header or
+    /// the ProDOS auxiliary type.
+    pub fn read_file(&self, entry: &CatalogEntry) -> Result<AppleFile> {
+        match entry.key {
+            Key::TsList(track, sector) => {
+                let data = self.dos_file(track, sector)?;
+                if entry.file_type != "B" {
+                    return Ok(AppleFile { load_address: None, data });
+                }
+
+                if data.len() < 4 {
+                    return Err(Disasm6502Error::InvalidFormat(format!("binary file \"{}\" has no header", entry.name)));
+                }
+                let length = (data[2] as usize | (data[3] as usize) << 8).min(data.len() - 4);
+                Ok(AppleFile { load_address: Some(word(&data, 0)), data: data[4..4 + length].to_vec() })
+            },
+            Key::ProDos(storage_type, block, length) => {
+                let mut data = Vec::new();
+                self.prodos_file(storage_type, block, length, &mut data)?;
+                data.resize(length, 0);
+
+                let load_address = match entry.file_type.as_str() {
+                    "BIN" => Some(entry.aux_type),
+                    "SYS" => Some(SYS_LOAD_ADDRESS),
+                    _ => None
+                };
+                Ok(AppleFile { load_address, data })
+            }
+        }
+    }
+
+    /// Read contents of a file by name.
+    pub fn read_file_by_name(&self, name: &str) -> Result<AppleFile> {
+        match self.find(name)? {
+            Some(entry) => self.read_file(&entry),
+            None => Err(Disasm6502Error::FileNotFound(name.to_string()))
+        }
+    }
+
+    fn has_file_system(&self) -> bool {
+        match self.file_system {
+            FileSystem::Dos33 => {
+                let vtoc = self.sector(VTOC_TRACK, 0).unwrap_or(&[]);
+                vtoc[0x01] > 0 && vtoc[0x01] < TRACKS && vtoc[0x02] < SECTORS_PER_TRACK &&
+                    vtoc[0x27] as usize == TS_PAIRS && vtoc[0x35] == SECTORS_PER_TRACK
+            },
+            FileSystem::ProDos => {
+                let block = self.block(VOLUME_DIRECTORY_BLOCK).unwrap_or_default();
+                block[0] == 0 && block[1] == 0 && block[0x04] >> 4 == VOLUME_HEADER && block[0x23] == 0x27
+            }
+        }
+    }
+
+    fn dos_catalog(&self) -> Result<Vec<CatalogEntry>> {
+        let vtoc = self.sector(VTOC_TRACK, 0).unwrap_or(&[]);
+        let mut entries = Vec::new();
+
+        for (t, s) in self.dos_chain(vtoc[0x01], vtoc[0x02])? {
+            let data = self.sector(t, s).unwrap_or(&[]);
+            for e in data[0x0B..].chunks(DOS_ENTRY_SIZE).take(DOS_ENTRIES) {
+                // unused and deleted entries
+                if e[0] == 0x00 || e[0] == 0xFF {
+                    continue;
+                }
+
+                let file_type = match e[2] & 0x7F {
+                    0x00 => "T",
+                    0x01 => "I",
+                    0x02 => "A",
+                    0x04 => "B",
+                    0x08 => "S",
+                    0x10 => "R",
+                    _ => "?"
+                };
+
+                entries.push(CatalogEntry {
+                    name: ascii_text(&e[3..0x21]),
+                    file_type: file_type.to_string(),
+                    locked: e[2] & 0x80 != 0,
+                    size: word(e, 0x21),
+                    aux_type: 0,
+                    key: Key::TsList(e[0], e[1])
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // list track/sector pairs of a chain linked through bytes 1 and 2 (catalog and track/sector lists)
+    fn dos_chain(&self, track: u8, sector: u8) -> Result<Vec<(u8, u8)>> {
+        let mut result = Vec::new();
+        let mut visited = HashSet::new();
+        let (mut t, mut s) = (track, sector);
+
+        while t != 0 {
+            let data = match self.sector(t, s) {
+                Some(d) => d,
+                None => return Err(Disasm6502Error::InvalidFormat(format!("invalid sector {}/{} in chain", t, s)))
+            };
+
+            if !visited.insert((t, s)) {
+                return Err(Disasm6502Error::InvalidFormat(format!("sector chain loops at {}/{}", t, s)));
+            }
+
+            result.push((t, s));
+            t = data[0x01];
+            s = data[0x02];
+        }
+
+        Ok(result)
+    }
+
+    fn dos_file(&self, track: u8, sector: u8) -> Result<Vec<u8>> {
+        // data sectors indexed by their position in the file
+        let mut sectors: Vec<Option<&[u8]>> = Vec::new();
+
+        for (t, s) in self.dos_chain(track, sector)? {
+            let list = self.sector(t, s).unwrap_or(&[]);
+            let offset = list[0x05] as usize | (list[0x06] as usize) << 8;
+            for (i, pair) in list[0x0C..].chunks(2).take(TS_PAIRS).enumerate() {
+                if pair[0] == 0 {
+                    continue;
+                }
+
+                let data = self.sector(pair[0], pair[1])
+                    .ok_or_else(|| Disasm6502Error::InvalidFormat(format!("invalid data sector {}/{}", pair[0], pair[1])))?;
+                if sectors.len() <= offset + i {
+                    sectors.resize(offset + i + 1, None);
+                }
+                sectors[offset + i] = Some(data);
+            }
+        }
+
+        // sparse sectors within the file are read as zeros
+        let mut data = Vec::new();
+        for sector in sectors {
+            data.extend_from_slice(sector.unwrap_or(&[0; SECTOR_SIZE]));
+        }
+        Ok(data)
+    }
+
+    // append entries of a directory (and its subdirectories) starting at the key block
+    fn prodos_directory(&self, key_block: u16, path: &str, visited: &mut HashSet<u16>, entries: &mut Vec<CatalogEntry>) -> Result<()> {
+        let mut block = key_block;
+
+        while block != 0 {
+            if !visited.insert(block) {
+                return Err(Disasm6502Error::InvalidFormat(format!("directory chain loops at block {}", block)));
+            }
+
+            let data = self.block(block)
+                .ok_or_else(|| Disasm6502Error::InvalidFormat(format!("invalid directory block {}", block)))?;
+
+            for e in data[0x04..].chunks(0x27).take(0x0D) {
+                let storage_type = e[0] >> 4;
+                if storage_type == 0 || storage_type >= 0xE {
+                    continue;
+                }
+
+                let name_length = (e[0] & 0x0F) as usize;
+                let name = format!("{}{}", path, String::from_utf8_lossy(&e[1..1 + name_length]));
+                let key = word(e, 0x11);
+
+                if storage_type == SUBDIRECTORY {
+                    self.prodos_directory(key, &format!("{}/", name), visited, entries)?;
+                    continue;
+                }
+
+                entries.push(CatalogEntry {
+                    name,
+                    file_type: prodos_type(e[0x10]),
+                    locked: e[0x1E] & 0x02 == 0,
+                    size: word(e, 0x13),
+                    aux_type: word(e, 0x1F),
+                    key: Key::ProDos(storage_type, key, e[0x15] as usize | (e[0x16] as usize) << 8 | (e[0x17] as usize) << 16)
+                });
+            }
+
+            block = word(&data, 0x02);
+        }
+
+        Ok(())
+    }
+
+    // append contents of a file stored in the key block (up to the given length), recursively
+    // following index blocks
+    fn prodos_file(&self, storage_type: u8, block: u16, length: usize, data: &mut Vec<u8>) -> Result<()> {
+        let span = match storage_type {
+            SEEDLING => BLOCK_SIZE,
+            SAPLING => BLOCK_SIZE * 256,
+            TREE => BLOCK_SIZE * 256 * 256,
+            t => return Err(Disasm6502Error::InvalidFormat(format!("unsupported ProDOS storage type {}", t)))
+        };
+
+        // sparse blocks (and sparse index blocks) are read as zeros
+        if block == 0 {
+            let end = (data.len() + span).min(length);
+            data.resize(end.max(data.len()), 0);
+            return Ok(());
+        }
+
+        let contents = self.block(block).ok_or_else(|| Disasm6502Error::InvalidFormat(format!("invalid block {}", block)))?;
+        if storage_type == SEEDLING {
+            data.extend_from_slice(&contents);
+            return Ok(());
+        }
+
+        // index blocks hold low bytes of block numbers in the first half, high bytes in the second
+        let nested = if storage_type == TREE { SAPLING } else { SEEDLING };
+        for i in 0..BLOCK_SIZE / 2 {
+            if data.len() >= length {
+                break;
+            }
+            self.prodos_file(nested, contents[i] as u16 | (contents[i + 256] as u16) << 8, length, data)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn prodos_type(file_type: u8) -> String {
+    match file_type {
+        0x04 => String::from("TXT"),
+        PRODOS_BIN => String::from("BIN"),
+        0x0F => String::from("DIR"),
+        0xFA => String::from("INT"),
+        0xFC => String::from("BAS"),
+        0xFD => String::from("VAR"),
+        0xFE => String::from("REL"),
+        PRODOS_SYS => String::from("SYS"),
+        t => format!("${:02X}", t)
+    }
+}
@@ -0,0 +1,171 @@
+//! Bank-switched address spaces.
+//!
+//! Cartridges and NES mappers place different ROM banks at the same CPU address, so an address
+//! alone does not identify a byte. A `BankedAddress` qualifies the address with a bank number.
+//! A `BankLayout` declares the address windows in which banks are switched and which banks can
+//! appear there; it is used to decide which bank the target of a branch, jump or call lies in.
+//! Where this cannot be decided statically (a call into a switchable window from outside of
+//! it), the bank can be given by an annotation on the calling instruction.
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Address qualified with a bank number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BankedAddress {
+    /// bank number (None for memory which is not banked)
+    pub bank: Option<u16>,
+    /// CPU address
+    pub address: u16
+}
+
+impl BankedAddress {
+    /// Create an address in a specific bank.
+    pub fn new(bank: u16, address: u16) -> BankedAddress {
+        BankedAddress { bank: Some(bank), address }
+    }
+
+    /// Create an address which is not banked.
+    pub fn unbanked(address: u16) -> BankedAddress {
+        BankedAddress { bank: None, address }
+    }
+
+    /// Parse `bank:address` (e.g. `03:$8000` or `3:8000`) or a plain address, all hexadecimal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::banking::BankedAddress;
+    ///
+    /// assert_eq!(BankedAddress::parse("03:$8000"), Some(BankedAddress::new(3, 0x8000)));
+    /// assert_eq!(BankedAddress::parse("$FFFC"), Some(BankedAddress::unbanked(0xFFFC)));
+    /// assert_eq!(format!("{}", BankedAddress::new(0x1F, 0xA000)), "1F:$A000");
+    /// ```
+    pub fn parse(text: &str) -> Option<BankedAddress> {
+        let number = |s: &str| {
+            let s = s.trim();
+            let s = s.trim_start_matches('$').trim_start_matches("0x");
+            u16::from_str_radix(s, 16).ok()
+        };
+
+        match text.find(':') {
+            Some(i) => Some(BankedAddress::new(number(&text[..i])?, number(&text[i + 1..])?)),
+            None => Some(BankedAddress::unbanked(number(text)?))
+        }
+    }
+}
+
+impl fmt::Display for BankedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(bank) = self.bank {
+            write!(f, "{:02X}:", bank)?;
+        }
+        write!(f, "${:04X}", self.address)
+    }
+}
+
+/// Address range in which banks are switched.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Window {
+    /// first address of the window
+    pub start: u16,
+    /// last address of the window
+    pub end: u16,
+    /// banks which can be visible in the window (a single bank if it is fixed)
+    pub banks: Vec<u16>
+}
+
+impl Window {
+    /// Check if the address falls within the window.
+    pub fn contains(&self, address: u16) -> bool {
+        (self.start..=self.end).contains(&address)
+    }
+
+    /// Check if the window always shows the same bank.
+    pub fn is_fixed(&self) -> bool {
+        self.banks.len() == 1
+    }
+}
+
+/// Declaration of bank windows and annotations of bank switching jumps.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BankLayout {
+    windows: Vec<Window>,
+    annotations: BTreeMap<BankedAddress, u16>
+}
+
+impl BankLayout {
+    /// Create a layout without banked windows.
+    pub fn new() -> BankLayout {
+        BankLayout { windows: Vec::new(), annotations: BTreeMap::new() }
+    }
+
+    /// Declare a window with the banks that can be visible in it.
+    pub fn add_window(&mut self, start: u16, end: u16, banks: &[u16]) {
+        self.windows.push(Window { start, end, banks: banks.to_vec() });
+    }
+
+    /// Declared windows.
+    pub fn windows(&self) -> &[Window] {
+        &self.windows
+    }
+
+    /// Window containing the address.
+    pub fn window(&self, address: u16) -> Option<&Window> {
+        self.windows.iter().find(|w| w.contains(address))
+    }
+
+    /// Declare that the target of the branch, jump or call at `instruction` lies in `bank`.
+    pub fn annotate(&mut self, instruction: BankedAddress, bank: u16) {
+        self.annotations.insert(instruction, bank);
+    }
+
+    /// Bank annotated for the instruction.
+    pub fn annotation(&self, instruction: BankedAddress) -> Option<u16> {
+        self.annotations.get(&instruction).cloned()
+    }
+
+    /// Resolve the target of a branch, jump or call made by the instruction at `from`.
+    ///
+    /// In order: the annotation of the instruction, the bank of the instruction itself if the
+    /// target is in the same window, the only bank of a fixed window. Targets outside of all
+    /// windows are returned without a bank; the analysis places them in the bank of the caller
+    /// if it covers the target, or in the only bank covering it. Returns None if the bank cannot
+    /// be determined.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::banking::{BankedAddress, BankLayout};
+    ///
+    /// // UxROM: banks 0-6 switchable at $8000, bank 7 fixed at $C000
+    /// let mut layout = BankLayout::new();
+    /// layout.add_window(0x8000, 0xBFFF, &[0, 1, 2, 3, 4, 5, 6]);
+    /// layout.add_window(0xC000, 0xFFFF, &[7]);
+    ///
+    /// let call = BankedAddress::new(7, 0xC010);
+    /// assert_eq!(layout.resolve(call, 0xC100), Some(BankedAddress::new(7, 0xC100)));
+    /// assert_eq!(layout.resolve(call, 0x8000), None);
+    /// assert_eq!(layout.resolve(call, 0x0300), Some(BankedAddress::unbanked(0x0300)));
+    ///
+    /// layout.annotate(call, 2);
+    /// assert_eq!(layout.resolve(call, 0x8000), Some(BankedAddress::new(2, 0x8000)));
+    /// ```
+    pub fn resolve(&self, from: BankedAddress, target: u16) -> Option<BankedAddress> {
+        if let Some(bank) = self.annotation(from) {
+            return Some(BankedAddress::new(bank, target));
+        }
+
+        let window = match self.window(target) {
+            Some(w) => w,
+            None => return Some(BankedAddress::unbanked(target))
+        };
+
+        match from.bank {
+            Some(bank) if window.contains(from.address) && window.banks.contains(&bank) => Some(BankedAddress::new(bank, target)),
+            _ if window.is_fixed() => Some(BankedAddress::new(window.banks[0], target)),
+            _ => None
+        }
+    }
+}
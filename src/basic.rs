@@ -0,0 +1,191 @@
+//! Commodore BASIC V2 detokenizer.
+//!
+//! Tokenized BASIC programs (as found at the start of most C64/VIC-20 PRG files) are stored as
+//! a chain of lines: each line starts with a pointer to the next one and the line number,
+//! followed by tokenized text and a terminating zero byte. A null pointer ends the program;
+//! anything that follows is usually machine code.
+use std::fmt;
+
+/// BASIC V2 keywords for tokens $80-$CB.
+pub const KEYWORDS: [&str; 76] = [
+    "END", "FOR", "NEXT", "DATA", "INPUT#", "INPUT", "DIM", "READ",
+    "LET", "GOTO", "RUN", "IF", "RESTORE", "GOSUB", "RETURN", "REM",
+    "STOP", "ON", "WAIT", "LOAD", "SAVE", "VERIFY", "DEF", "POKE",
+    "PRINT#", "PRINT", "CONT", "LIST", "CLR", "CMD", "SYS", "OPEN",
+    "CLOSE", "GET", "NEW", "TAB(", "TO", "FN", "SPC(", "THEN",
+    "NOT", "STEP", "+", "-", "*", "/", "^", "AND",
+    "OR", ">", "=", "<", "SGN", "INT", "ABS", "USR",
+    "FRE", "POS", "SQR", "RND", "LOG", "EXP", "COS", "SIN",
+    "TAN", "ATN", "PEEK", "LEN", "STR$", "VAL", "ASC", "CHR$",
+    "LEFT$", "RIGHT$", "MID$", "GO"
+];
+
+// token of the first keyword
+const FIRST_TOKEN: u8 = 0x80;
+
+// token for the pi constant
+const PI_TOKEN: u8 = 0xFF;
+
+/// Single line of a BASIC program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicLine {
+    /// address of the line in memory
+    pub address: u16,
+    /// line number
+    pub number: u16,
+    /// detokenized text of the line
+    pub text: String
+}
+
+impl fmt::Display for BasicLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.number, self.text)
+    }
+}
+
+/// Detokenized BASIC program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    /// program lines
+    pub lines: Vec<BasicLine>,
+    /// address following the end-of-program marker (load address if there is no program)
+    pub end_address: u16
+}
+
+/// Detokenize BASIC program loaded at the given address.
+///
+/// Lines are followed through their link pointers until the end-of-program marker.
+/// An inconsistent link (pointing backwards or outside of the data) is treated as the end of
+/// the program.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// // 10 SYS 2061
+/// let bytes = vec![0x0B, 0x08, 0x0A, 0x00, 0x9E, 0x32, 0x30, 0x36, 0x31, 0x00, 0x00, 0x00];
+/// let program = disasm6502::basic::detokenize(0x0801, &bytes);
+///
+/// assert_eq!(format!("{}", program.lines[0]), "10 SYS2061");
+/// assert_eq!(program.end_address, 0x080D);
+/// ```
+pub fn detokenize(load_address: u16, data: &[u8]) -> Program {
+    let mut iter = lines(load_address, data);
+    let lines = iter.by_ref()
+        .map(|line| BasicLine { address: line.address, number: line.number, text: detokenize_line(line.text) })
+        .collect();
+
+    Program { lines, end_address: iter.end_address() }
+}
+
+/// Tokenized line of a BASIC program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenizedLine<'a> {
+    /// address of the line in memory
+    pub address: u16,
+    /// line number
+    pub number: u16,
+    /// tokenized text (without the terminating zero)
+    pub text: &'a [u8]
+}
+
+/// Iterator over the tokenized lines of a program, returned by `lines`.
+#[derive(Clone, Debug)]
+pub struct Lines<'a> {
+    load_address: u16,
+    data: &'a [u8],
+    offset: usize,
+    terminated: bool
+}
+
+impl<'a> Lines<'a> {
+    /// Address following the end-of-program marker, or following the last valid line if the
+    /// program has no marker (load address if there are no lines). Only final once the iterator
+    /// is exhausted.
+    pub fn end_address(&self) -> u16 {
+        let end = self.load_address.wrapping_add(self.offset as u16);
+        if self.terminated { end.wrapping_add(2) } else { end }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = TokenizedLine<'a>;
+
+    fn next(&mut self) -> Option<TokenizedLine<'a>> {
+        let (offset, data) = (self.offset, self.data);
+        if self.terminated || offset + 2 > data.len() {
+            return None;
+        }
+
+        let address = self.load_address.wrapping_add(offset as u16);
+        let link = data[offset] as u16 | (data[offset + 1] as u16) << 8;
+        if link == 0 {
+            self.terminated = true;
+            return None;
+        }
+
+        let next = link.wrapping_sub(self.load_address) as usize;
+        if link <= address || next > data.len() || offset + 4 > next {
+            return None;
+        }
+
+        let number = data[offset + 2] as u16 | (data[offset + 3] as u16) << 8;
+        let text = &data[offset + 4..next];
+        let end = text.iter().position(|&b| b == 0x00).unwrap_or(text.len());
+        self.offset = next;
+        Some(TokenizedLine { address, number, text: &text[..end] })
+    }
+}
+
+/// Follow the link pointers of a BASIC program loaded at the given address, with the same
+/// rules as `detokenize`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// // 10 SYS 2061
+/// let bytes = vec![0x0B, 0x08, 0x0A, 0x00, 0x9E, 0x32, 0x30, 0x36, 0x31, 0x00, 0x00, 0x00];
+/// let mut lines = disasm6502::basic::lines(0x0801, &bytes);
+/// let line = lines.next().unwrap();
+///
+/// assert_eq!((line.address, line.number, line.text), (0x0801, 10, &bytes[4..9]));
+/// assert_eq!(lines.next(), None);
+/// assert_eq!(lines.end_address(), 0x080D);
+/// ```
+pub fn lines<'a>(load_address: u16, data: &'a [u8]) -> Lines<'a> {
+    Lines { load_address, data, offset: 0, terminated: false }
+}
+
+/// Detokenize text of a single line (without link, line number and terminating zero).
+pub fn detokenize_line(text: &[u8]) -> String {
+    let mut result = String::new();
+    let mut quoted = false;
+
+    for &b in text.iter() {
+        if b == b'"' {
+            quoted = !quoted;
+        }
+
+        match b {
+            FIRST_TOKEN..=0xCB if !quoted => result.push_str(KEYWORDS[(b - FIRST_TOKEN) as usize]),
+            PI_TOKEN if !quoted => result.push('π'),
+            _ => push_petscii(&mut result, b)
+        }
+    }
+
+    result
+}
+
+// printable PETSCII characters are shown as their ASCII/Unicode equivalents, others as {$XX}
+pub(crate) fn push_petscii(s: &mut String, b: u8) {
+    match b {
+        0x5C => s.push('£'),
+        0x5E => s.push('↑'),
+        0x5F => s.push('←'),
+        0x20..=0x5D => s.push(b as char),
+        _ => s.push_str(&format!("{{${:02X}}}", b))
+    }
+}
@@ -0,0 +1,222 @@
+//! Per-cycle bus activity of decoded instructions.
+//!
+//! Describes what the NMOS 6502 puts on the address bus during every cycle of an instruction,
+//! including the dummy reads and writes which are invisible to the program but observable by
+//! memory mapped hardware (and on a logic analyzer).
+use instruction::{AddrMode, Instruction};
+use instruction::OpCode::*;
+use emulator::IRQ_VECTOR;
+use self::BusAddress::*;
+use self::BusOperation::*;
+use self::CycleCondition::*;
+
+/// Source of the address put on the bus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BusAddress {
+    /// program counter (opcode and operand fetches)
+    ProgramCounter,
+    /// top of the stack ($0100 + S)
+    Stack,
+    /// zero page address or pointer taken from the operand, before indexing
+    Operand,
+    /// low byte of the pointer (zero page pointer indexed with X for indexed indirect mode)
+    PointerLow,
+    /// high byte of the pointer (pointer + 1, without carry into the high byte)
+    PointerHigh,
+    /// effective address with index added to the low byte only (before page fix-up)
+    Unfixed,
+    /// effective address of the operand
+    Effective,
+    /// fixed address (interrupt vectors)
+    Fixed(u16)
+}
+
+/// Direction and purpose of the bus access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BusOperation {
+    /// read of a value used by the instruction
+    Read,
+    /// write of a value
+    Write,
+    /// read of a value which is discarded
+    DummyRead,
+    /// write of the unmodified value by read-modify-write instructions
+    DummyWrite
+}
+
+/// Condition under which the cycle takes place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CycleCondition {
+    /// cycle always takes place
+    Always,
+    /// cycle takes place only if indexing (or the branch) crosses a page boundary
+    PageCrossed,
+    /// cycle takes place only if the branch is taken
+    BranchTaken
+}
+
+/// Bus activity during a single cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BusCycle {
+    /// address source
+    pub address: BusAddress,
+    /// bus operation
+    pub operation: BusOperation,
+    /// condition for the cycle to happen
+    pub condition: CycleCondition
+}
+
+// how the instruction accesses its operand
+#[derive(PartialEq)]
+enum Access {
+    Read,
+    Write,
+    Modify
+}
+
+fn cycle(address: BusAddress, operation: BusOperation) -> BusCycle {
+    BusCycle { address, operation, condition: Always }
+}
+
+fn conditional(address: BusAddress, operation: BusOperation, condition: CycleCondition) -> BusCycle {
+    BusCycle { address, operation, condition }
+}
+
+impl Instruction {
+    /// Bus activity of the instruction, one entry per cycle (including conditional cycles).
+    ///
+    /// Reads of indexed operands at the `Unfixed` address are discarded when the page boundary
+    /// is crossed; the read is then repeated at the `Effective` address in the following
+    /// conditional cycle.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::bus_cycle::BusOperation;
+    ///
+    /// let memory = vec![0xEE, 0x20, 0xD0];
+    ///
+    /// // INC $D020
+    /// let instruction = disasm6502::instruction::decode(0x0800, &mut 0, &memory);
+    /// let cycles = instruction.bus_cycles();
+    ///
+    /// assert_eq!(cycles.len(), 6);
+    /// // read-modify-write instructions write the original value first
+    /// assert_eq!(cycles[4].operation, BusOperation::DummyWrite);
+    /// ```
+    pub fn bus_cycles(&self) -> Vec<BusCycle> {
+        let mut cycles = vec![cycle(ProgramCounter, Read)];
+
+        // instructions with dedicated sequences
+        match self.opcode {
+            BRK(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, DummyRead),
+                                           cycle(Stack, Write), cycle(Stack, Write), cycle(Stack, Write),
+                                           cycle(Fixed(IRQ_VECTOR), Read), cycle(Fixed(IRQ_VECTOR + 1), Read)]);
+                return cycles;
+            },
+            RTI(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, DummyRead), cycle(Stack, DummyRead),
+                                           cycle(Stack, Read), cycle(Stack, Read), cycle(Stack, Read)]);
+                return cycles;
+            },
+            RTS(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, DummyRead), cycle(Stack, DummyRead),
+                                           cycle(Stack, Read), cycle(Stack, Read), cycle(ProgramCounter, DummyRead)]);
+                return cycles;
+            },
+            PHA(_) | PHP(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, DummyRead), cycle(Stack, Write)]);
+                return cycles;
+            },
+            PLA(_) | PLP(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, DummyRead), cycle(Stack, DummyRead), cycle(Stack, Read)]);
+                return cycles;
+            },
+            JSR(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(Stack, DummyRead),
+                                           cycle(Stack, Write), cycle(Stack, Write), cycle(ProgramCounter, Read)]);
+                return cycles;
+            },
+            JMP(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(ProgramCounter, Read)]);
+                if let AddrMode::Indirect = self.addr_mode {
+                    cycles.extend_from_slice(&[cycle(PointerLow, Read), cycle(PointerHigh, Read)]);
+                }
+                return cycles;
+            },
+            // the CPU locks up after fetching the opcode
+            HLT(_) => return cycles,
+            _ => {}
+        }
+
+        let access = match self.opcode {
+            STA(_) | STX(_) | STY(_) | SAX(_) | AHX(_) | SHX(_) | SHY(_) | TAS(_) => Access::Write,
+            ASL(_) | LSR(_) | ROL(_) | ROR(_) | INC(_) | DEC(_) |
+            SLO(_) | RLA(_) | SRE(_) | RRA(_) | DCP(_) | ISC(_) => Access::Modify,
+            _ => Access::Read
+        };
+
+        // access to the effective address
+        let operand = |cycles: &mut Vec<BusCycle>| {
+            match access {
+                Access::Read => cycles.push(cycle(Effective, Read)),
+                Access::Write => cycles.push(cycle(Effective, Write)),
+                Access::Modify => cycles.extend_from_slice(&[cycle(Effective, Read), cycle(Effective, DummyWrite),
+                                                             cycle(Effective, Write)])
+            }
+        };
+
+        // indexed access which may cross a page boundary
+        let indexed = |cycles: &mut Vec<BusCycle>| {
+            if access == Access::Read {
+                cycles.extend_from_slice(&[cycle(Unfixed, Read), conditional(Effective, Read, PageCrossed)]);
+            } else {
+                cycles.push(cycle(Unfixed, DummyRead));
+                operand(cycles);
+            }
+        };
+
+        match self.addr_mode {
+            AddrMode::Implied | AddrMode::Accumulator => cycles.push(cycle(ProgramCounter, DummyRead)),
+            AddrMode::Immediate => cycles.push(cycle(ProgramCounter, Read)),
+            AddrMode::Zeropage => {
+                cycles.push(cycle(ProgramCounter, Read));
+                operand(&mut cycles);
+            },
+            AddrMode::ZeropageIndexedX | AddrMode::ZeropageIndexedY => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(Operand, DummyRead)]);
+                operand(&mut cycles);
+            },
+            AddrMode::Absolute => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(ProgramCounter, Read)]);
+                operand(&mut cycles);
+            },
+            AddrMode::AbsoluteIndexedX(_) | AddrMode::AbsoluteIndexedY(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(ProgramCounter, Read)]);
+                indexed(&mut cycles);
+            },
+            AddrMode::Relative => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read),
+                                           conditional(ProgramCounter, DummyRead, BranchTaken),
+                                           conditional(Unfixed, DummyRead, PageCrossed)]);
+            },
+            AddrMode::Indirect => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(ProgramCounter, Read),
+                                           cycle(PointerLow, Read), cycle(PointerHigh, Read)]);
+            },
+            AddrMode::IndexedIndirectX => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(Operand, DummyRead),
+                                           cycle(PointerLow, Read), cycle(PointerHigh, Read)]);
+                operand(&mut cycles);
+            },
+            AddrMode::IndirectIndexedY(_) => {
+                cycles.extend_from_slice(&[cycle(ProgramCounter, Read), cycle(PointerLow, Read), cycle(PointerHigh, Read)]);
+                indexed(&mut cycles);
+            }
+        }
+
+        cycles
+    }
+}
@@ -0,0 +1,168 @@
+//! C64 cartridge image (CRT) reader.
+//!
+//! CRT files start with a header describing the cartridge hardware, followed by CHIP packets
+//! holding ROM/RAM/flash contents together with their bank number and load address.
+//! Cartridges mapped at $8000 start through the cold-start vector stored in their first two
+//! bytes; Ultimax cartridges use the CPU reset vector at $FFFC.
+use std::fmt;
+use emulator::RESET_VECTOR;
+use error::{Disasm6502Error, Result};
+use fields::{long_be, text, word_be};
+use segment::Segment;
+
+/// Address of the cold-start vector of cartridges mapped at $8000.
+pub const COLD_START_VECTOR: u16 = 0x8000;
+
+const SIGNATURE: &[u8] = b"C64 CARTRIDGE   ";
+const CHIP_SIGNATURE: &[u8] = b"CHIP";
+
+// size of the CHIP packet header
+const CHIP_HEADER_SIZE: usize = 0x10;
+
+/// Hardware type names, indexed by type number.
+pub const HARDWARE_TYPES: [&str; 37] = [
+    "Normal cartridge", "Action Replay", "KCS Power Cartridge", "Final Cartridge III",
+    "Simons' BASIC", "Ocean type 1", "Expert Cartridge", "Fun Play, Power Play",
+    "Super Games", "Atomic Power", "Epyx Fastload", "Westermann Learning",
+    "Rex Utility", "Final Cartridge I", "Magic Formel", "C64 Game System, System 3",
+    "Warp Speed", "Dinamic", "Zaxxon, Super Zaxxon", "Magic Desk, Domark, HES Australia",
+    "Super Snapshot V5", "Comal-80", "Structured BASIC", "Ross",
+    "Dela EP64", "Dela EP7x8", "Dela EP256", "Rex EP256",
+    "Mikro Assembler", "Final Cartridge Plus", "Action Replay 4", "Stardos",
+    "EasyFlash", "EasyFlash Xbank", "Capture", "Action Replay 3",
+    "Retro Replay"
+];
+
+/// Type of memory held by a CHIP packet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChipType {
+    /// read-only memory
+    Rom,
+    /// RAM
+    Ram,
+    /// flash memory
+    Flash,
+    /// EEPROM
+    Eeprom,
+    /// unknown chip type
+    Unknown(u16)
+}
+
+impl From<u16> for ChipType {
+    fn from(value: u16) -> ChipType {
+        match value {
+            0 => ChipType::Rom,
+            1 => ChipType::Ram,
+            2 => ChipType::Flash,
+            3 => ChipType::Eeprom,
+            _ => ChipType::Unknown(value)
+        }
+    }
+}
+
+impl fmt::Display for ChipType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChipType::Rom => write!(f, "ROM"),
+            ChipType::Ram => write!(f, "RAM"),
+            ChipType::Flash => write!(f, "Flash"),
+            ChipType::Eeprom => write!(f, "EEPROM"),
+            ChipType::Unknown(value) => write!(f, "unknown ({})", value)
+        }
+    }
+}
+
+/// Single CHIP packet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chip {
+    /// memory type
+    pub chip_type: ChipType,
+    /// chip contents placed at the load address in its bank
+    pub segment: Segment
+}
+
+/// Cartridge image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Crt {
+    /// cartridge name
+    pub name: String,
+    /// hardware type number (see `HARDWARE_TYPES`)
+    pub hardware_type: u16,
+    /// state of the EXROM line (true if inactive/high)
+    pub exrom: bool,
+    /// state of the GAME line (true if inactive/high)
+    pub game: bool,
+    /// CHIP packets in file order
+    pub chips: Vec<Chip>
+}
+
+impl Crt {
+    /// Parse CRT file contents.
+    pub fn parse(bytes: &[u8]) -> Result<Crt> {
+        if !bytes.starts_with(SIGNATURE) || bytes.len() < 0x40 {
+            return Err(Disasm6502Error::InvalidFormat(String::from("missing CRT signature")));
+        }
+
+        let header_size = long_be(bytes, 0x10) as usize;
+        let hardware_type = word_be(bytes, 0x16);
+        let exrom = bytes[0x18] != 0;
+        let game = bytes[0x19] != 0;
+        let name = text(&bytes[0x20..0x40]);
+
+        let mut chips = Vec::new();
+        let mut offset = header_size.max(0x40);
+        while offset < bytes.len() {
+            let packet = &bytes[offset..];
+            if !packet.starts_with(CHIP_SIGNATURE) {
+                return Err(Disasm6502Error::InvalidFormat(format!("missing CHIP signature at offset ${:X}", offset)));
+            }
+
+            if packet.len() < CHIP_HEADER_SIZE {
+                return Err(Disasm6502Error::InvalidFormat(format!("truncated CHIP packet at offset ${:X}", offset)));
+            }
+
+            let length = long_be(packet, 0x04) as usize;
+            let size = word_be(packet, 0x0E) as usize;
+            if length < CHIP_HEADER_SIZE || CHIP_HEADER_SIZE + size > packet.len() {
+                return Err(Disasm6502Error::InvalidFormat(format!("truncated CHIP packet at offset ${:X}", offset)));
+            }
+
+            let data = packet[CHIP_HEADER_SIZE..CHIP_HEADER_SIZE + size].to_vec();
+            chips.push(Chip {
+                chip_type: ChipType::from(word_be(packet, 0x08)),
+                segment: Segment::banked(word_be(packet, 0x0A), word_be(packet, 0x0C), data)
+            });
+
+            offset += length;
+        }
+
+        Ok(Crt { name, hardware_type, exrom, game, chips })
+    }
+
+    /// Name of the hardware type (None if unknown).
+    pub fn hardware_name(&self) -> Option<&'static str> {
+        HARDWARE_TYPES.get(self.hardware_type as usize).cloned()
+    }
+
+    /// Check if the cartridge runs in Ultimax mode (GAME active, EXROM inactive).
+    pub fn is_ultimax(&self) -> bool {
+        self.exrom && !self.game
+    }
+
+    /// Segments of all CHIP packets.
+    pub fn segments(&self) -> Vec<Segment> {
+        self.chips.iter().map(|c| c.segment.clone()).collect()
+    }
+
+    /// Entry points of the cartridge: the cold-start vector at $8000 (or the reset vector for
+    /// Ultimax cartridges) read from bank 0.
+    pub fn entry_points(&self) -> Vec<u16> {
+        let vector = if self.is_ultimax() { RESET_VECTOR } else { COLD_START_VECTOR };
+
+        self.chips.iter()
+            .filter(|c| c.segment.bank.unwrap_or(0) == 0)
+            .filter_map(|c| c.segment.read_word(vector))
+            .take(1)
+            .collect()
+    }
+}
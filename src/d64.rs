@@ -0,0 +1,273 @@
+//! Commodore 1541 disk image (D64) reader.
+//!
+//! Supports 35 and 40 track images, with or without the trailing error info bytes. Files are
+//! listed from the directory on track 18 and extracted by following their track/sector chains.
+use std::collections::HashSet;
+use std::fmt;
+use error::{Disasm6502Error, Result};
+use fields::{petscii_name, word};
+
+/// Size of a single sector in bytes.
+pub const SECTOR_SIZE: usize = 256;
+
+/// Track holding the BAM and directory.
+pub const DIRECTORY_TRACK: u8 = 18;
+
+// sectors in a 35 and 40 track image
+const SECTORS_35: usize = 683;
+const SECTORS_40: usize = 768;
+
+// directory entry size and number of entries per sector
+const ENTRY_SIZE: usize = 32;
+
+// filename padding character
+const PADDING: u8 = 0xA0;
+
+/// Number of sectors on a track (0 for tracks outside of the 40 track range).
+pub fn sectors_per_track(track: u8) -> u8 {
+    match track {
+        1..=17 => 21,
+        18..=24 => 19,
+        25..=30 => 18,
+        31..=40 => 17,
+        _ => 0
+    }
+}
+
+/// Type of a file stored on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileType {
+    Del,
+    Seq,
+    Prg,
+    Usr,
+    Rel,
+    /// unknown file type code
+    Unknown(u8)
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FileType::Del => write!(f, "DEL"),
+            FileType::Seq => write!(f, "SEQ"),
+            FileType::Prg => write!(f, "PRG"),
+            FileType::Usr => write!(f, "USR"),
+            FileType::Rel => write!(f, "REL"),
+            FileType::Unknown(t) => write!(f, "?{:X}?", t)
+        }
+    }
+}
+
+/// Directory entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    /// file name (PETSCII converted for display)
+    pub name: String,
+    /// file type
+    pub file_type: FileType,
+    /// file was properly closed
+    pub closed: bool,
+    /// file is write protected
+    pub locked: bool,
+    /// track of the first sector
+    pub track: u8,
+    /// first sector
+    pub sector: u8,
+    /// file size in blocks, as stored in the directory
+    pub blocks: u16
+}
+
+impl fmt::Display for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<5} \"{}\" {}{}{}", self.blocks, self.name,
+               if self.closed { " " } else { "*" }, self.file_type, if self.locked { "<" } else { "" })
+    }
+}
+
+/// File extracted from disk image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct File {
+    /// file contents
+    pub data: Vec<u8>,
+    /// sectors of the file marked as erroneous in error info: (track, sector, error code)
+    pub errors: Vec<(u8, u8, u8)>
+}
+
+/// 1541 disk image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct D64 {
+    data: Vec<u8>,
+    tracks: u8,
+    errors: Option<Vec<u8>>
+}
+
+impl D64 {
+    /// Create disk image from file contents. Image size determines track count and presence of error info.
+    pub fn parse(bytes: &[u8]) -> Result<D64> {
+        let (sectors, tracks) = match bytes.len() {
+            l if l == SECTORS_35 * SECTOR_SIZE || l == SECTORS_35 * (SECTOR_SIZE + 1) => (SECTORS_35, 35),
+            l if l == SECTORS_40 * SECTOR_SIZE || l == SECTORS_40 * (SECTOR_SIZE + 1) => (SECTORS_40, 40),
+            l => return Err(Disasm6502Error::InvalidFormat(format!("unexpected D64 image size {}", l)))
+        };
+
+        let size = sectors * SECTOR_SIZE;
+        let errors = if bytes.len() > size { Some(bytes[size..].to_vec()) } else { None };
+
+        Ok(D64 { data: bytes[..size].to_vec(), tracks, errors })
+    }
+
+    /// Number of tracks (35 or 40).
+    pub fn tracks(&self) -> u8 {
+        self.tracks
+    }
+
+    /// Contents of a sector (None if track/sector is invalid).
+    pub fn sector(&self, track: u8, sector: u8) -> Option<&[u8]> {
+        let index = self.sector_index(track, sector)?;
+        Some(&self.data[index * SECTOR_SIZE..(index + 1) * SECTOR_SIZE])
+    }
+
+    /// Error code of a sector from error info, if the image has one and the sector is not OK.
+    pub fn error(&self, track: u8, sector: u8) -> Option<u8> {
+        let index = self.sector_index(track, sector)?;
+        let code = *self.errors.as_ref()?.get(index)?;
+
+        // 0 (unused) and 1 both mean no error
+        if code > 1 { Some(code) } else { None }
+    }
+
+    /// Disk name from the BAM sector.
+    pub fn disk_name(&self) -> String {
+        petscii_name(&self.bam()[0x90..0xA0], PADDING)
+    }
+
+    /// Disk ID from the BAM sector.
+    pub fn disk_id(&self) -> String {
+        petscii_name(&self.bam()[0xA2..0xA4], PADDING)
+    }
+
+    /// Check in the BAM if a sector is free. Only the standard 35 tracks are covered by the BAM.
+    pub fn is_free(&self, track: u8, sector: u8) -> Option<bool> {
+        if !(1..=35).contains(&track) || sector >= sectors_per_track(track) {
+            return None;
+        }
+
+        let entry = 4 * track as usize;
+        let bits = self.bam()[entry + 1 + sector as usize / 8];
+        Some(bits & (1 << (sector % 8)) != 0)
+    }
+
+    /// Number of free blocks according to the BAM (directory track excluded).
+    pub fn free_blocks(&self) -> u16 {
+        (1..36u8).filter(|&t| t != DIRECTORY_TRACK)
+                 .map(|t| self.bam()[4 * t as usize] as u16)
+                 .sum()
+    }
+
+    /// List directory entries (deleted entries with no type are skipped).
+    pub fn directory(&self) -> Result<Vec<DirEntry>> {
+        let mut entries = Vec::new();
+        let (track, sector) = (self.bam()[0], self.bam()[1]);
+
+        for (t, s) in self.chain(track, sector)? {
+            let data = self.sector(t, s).unwrap_or(&[]);
+            for e in data.chunks(ENTRY_SIZE) {
+                let file_type = match e[2] & 0x0F {
+                    0 => FileType::Del,
+                    1 => FileType::Seq,
+                    2 => FileType::Prg,
+                    3 => FileType::Usr,
+                    4 => FileType::Rel,
+                    t => FileType::Unknown(t)
+                };
+
+                if e[2] == 0x00 {
+                    continue;
+                }
+
+                entries.push(DirEntry {
+                    name: petscii_name(&e[5..0x15], PADDING),
+                    file_type,
+                    closed: e[2] & 0x80 != 0,
+                    locked: e[2] & 0x40 != 0,
+                    track: e[3],
+                    sector: e[4],
+                    blocks: word(e, 0x1E)
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Find directory entry by file name.
+    pub fn find(&self, name: &str) -> Result<Option<DirEntry>> {
+        Ok(self.directory()?.into_iter().find(|e| e.name == name))
+    }
+
+    /// Read file contents by following its track/sector chain.
+    pub fn read_file(&self, entry: &DirEntry) -> Result<File> {
+        let chain = self.chain(entry.track, entry.sector)?;
+        let mut file = File { data: Vec::new(), errors: Vec::new() };
+
+        for (i, &(t, s)) in chain.iter().enumerate() {
+            let data = self.sector(t, s).unwrap_or(&[]);
+            // last sector stores index of its last used byte instead of next sector
+            let end = if i + 1 == chain.len() { (data[1] as usize + 1).clamp(2, SECTOR_SIZE) } else { SECTOR_SIZE };
+            file.data.extend_from_slice(&data[2..end]);
+
+            if let Some(code) = self.error(t, s) {
+                file.errors.push((t, s, code));
+            }
+        }
+
+        Ok(file)
+    }
+
+    /// Read contents of a file by name.
+    pub fn read_file_by_name(&self, name: &str) -> Result<File> {
+        match self.find(name)? {
+            Some(entry) => self.read_file(&entry),
+            None => Err(Disasm6502Error::FileNotFound(name.to_string()))
+        }
+    }
+
+    // list track/sector pairs of a sector chain
+    fn chain(&self, track: u8, sector: u8) -> Result<Vec<(u8, u8)>> {
+        let mut result = Vec::new();
+        let mut visited = HashSet::new();
+        let (mut t, mut s) = (track, sector);
+
+        loop {
+            let data = match self.sector(t, s) {
+                Some(d) => d,
+                None => return Err(Disasm6502Error::InvalidFormat(format!("invalid sector {}/{} in chain", t, s)))
+            };
+
+            if !visited.insert((t, s)) {
+                return Err(Disasm6502Error::InvalidFormat(format!("sector chain loops at {}/{}", t, s)));
+            }
+
+            result.push((t, s));
+            if data[0] == 0 {
+                return Ok(result);
+            }
+            t = data[0];
+            s = data[1];
+        }
+    }
+
+    fn bam(&self) -> &[u8] {
+        self.sector(DIRECTORY_TRACK, 0).unwrap_or(&[])
+    }
+
+    fn sector_index(&self, track: u8, sector: u8) -> Option<usize> {
+        if track < 1 || track > self.tracks || sector >= sectors_per_track(track) {
+            return None;
+        }
+
+        let preceding: usize = (1..track).map(|t| sectors_per_track(t) as usize).sum();
+        Some(preceding + sector as usize)
+    }
+}
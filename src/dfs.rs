@@ -0,0 +1,176 @@
+//! Acorn DFS disk image (.ssd/.dsd) reader for the BBC Micro.
+//!
+//! Each disk side has 40 or 80 tracks of 10 sectors. The catalog occupies sectors 0 and 1 of
+//! track 0: sector 0 holds file names, sector 1 holds load and execution addresses, lengths and
+//! start sectors. Double-sided images interleave the sides track by track. Addresses are
+//! 18 bits wide; addresses with the top bits set refer to the I/O processor.
+use std::fmt;
+use analysis;
+use error::{Disasm6502Error, Result};
+use fields::ascii_text;
+use listing;
+use platform::Platform;
+use segment::Segment;
+
+/// Size of a sector in bytes.
+pub const SECTOR_SIZE: usize = 256;
+
+/// Number of sectors on a track.
+pub const SECTORS_PER_TRACK: usize = 10;
+
+// catalog entries are 8 bytes long, the first one follows the 8 byte header
+const ENTRY_SIZE: usize = 8;
+const MAX_ENTRIES: usize = 31;
+
+/// Catalog entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DfsEntry {
+    /// file name with directory (e.g. `$.GAME`)
+    pub name: String,
+    /// file is locked
+    pub locked: bool,
+    /// load address (18 bits)
+    pub load_address: u32,
+    /// execution address (18 bits)
+    pub exec_address: u32,
+    /// file length in bytes
+    pub length: u32,
+    /// first sector of the file on its side
+    pub start_sector: u16,
+    /// disk side (0 or 1)
+    pub side: u8
+}
+
+impl fmt::Display for DfsEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:<9} {} {:06X} {:06X} {:06X} {:03X}", self.name, if self.locked { "L" } else { " " },
+               self.load_address, self.exec_address, self.length, self.start_sector)
+    }
+}
+
+/// DFS disk image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dfs {
+    data: Vec<u8>,
+    sides: u8
+}
+
+impl Dfs {
+    /// Create disk image from file contents of a single-sided (.ssd) or interleaved double-sided
+    /// (.dsd) image. Images may be truncated after the last used sector.
+    pub fn parse(bytes: &[u8], double_sided: bool) -> Result<Dfs> {
+        let sides = if double_sided { 2 } else { 1 };
+        let catalog_end = (sides as usize - 1) * SECTORS_PER_TRACK * SECTOR_SIZE + 2 * SECTOR_SIZE;
+        if bytes.len() < catalog_end {
+            return Err(Disasm6502Error::InvalidFormat(String::from("DFS image too short for the catalog")));
+        }
+
+        let dfs = Dfs { data: bytes.to_vec(), sides };
+        for side in 0..sides {
+            let entries = dfs.sector(side, 1).unwrap_or(&[])[5] as usize;
+            if entries % ENTRY_SIZE != 0 || entries / ENTRY_SIZE > MAX_ENTRIES {
+                return Err(Disasm6502Error::InvalidFormat(format!("invalid catalog on side {}", side)));
+            }
+        }
+
+        Ok(dfs)
+    }
+
+    /// Number of disk sides in the image.
+    pub fn sides(&self) -> u8 {
+        self.sides
+    }
+
+    /// Contents of a logical sector of a side (None if outside of the image).
+    pub fn sector(&self, side: u8, sector: u16) -> Option<&[u8]> {
+        if side >= self.sides {
+            return None;
+        }
+
+        let track = sector as usize / SECTORS_PER_TRACK;
+        let offset = ((track * self.sides as usize + side as usize) * SECTORS_PER_TRACK
+                      + sector as usize % SECTORS_PER_TRACK) * SECTOR_SIZE;
+        self.data.get(offset..offset + SECTOR_SIZE)
+    }
+
+    /// Disk title of a side.
+    pub fn title(&self, side: u8) -> String {
+        let mut title = self.sector(side, 0).map_or(Vec::new(), |s| s[0..8].to_vec());
+        title.extend_from_slice(self.sector(side, 1).map_or(&[][..], |s| &s[0..4]));
+        ascii_text(&title)
+    }
+
+    /// Boot option (*OPT 4) of a side.
+    pub fn boot_option(&self, side: u8) -> u8 {
+        self.sector(side, 1).map_or(0, |s| (s[6] >> 4) & 0x03)
+    }
+
+    /// List catalog entries of a side.
+    pub fn catalog(&self, side: u8) -> Vec<DfsEntry> {
+        let (names, info) = match (self.sector(side, 0), self.sector(side, 1)) {
+            (Some(n), Some(i)) => (n, i),
+            _ => return Vec::new()
+        };
+
+        (0..info[5] as usize / ENTRY_SIZE).map(|i| {
+            let n = &names[ENTRY_SIZE * (i + 1)..ENTRY_SIZE * (i + 2)];
+            let e = &info[ENTRY_SIZE * (i + 1)..ENTRY_SIZE * (i + 2)];
+            let high = |shift: u8| ((e[6] >> shift) & 0x03) as u32;
+            // addresses with both top bits set are sign extended to $FFxxxx
+            let address = |low: u32, bits: u32| if bits == 0x03 { 0xFF0000 | low } else { bits << 16 | low };
+
+            DfsEntry {
+                name: format!("{}.{}", (n[7] & 0x7F) as char, ascii_text(&n[0..7])),
+                locked: n[7] & 0x80 != 0,
+                load_address: address(e[0] as u32 | (e[1] as u32) << 8, high(2)),
+                exec_address: address(e[2] as u32 | (e[3] as u32) << 8, high(6)),
+                length: e[4] as u32 | (e[5] as u32) << 8 | high(4) << 16,
+                start_sector: e[7] as u16 | (high(0) as u16) << 8,
+                side
+            }
+        }).collect()
+    }
+
+    /// Find catalog entry by name on any side. Names without a directory are looked up in `$`.
+    pub fn find(&self, name: &str) -> Option<DfsEntry> {
+        let name = if name.chars().nth(1) == Some('.') { name.to_string() } else { format!("$.{}", name) };
+        (0..self.sides).flat_map(|side| self.catalog(side)).find(|e| e.name.eq_ignore_ascii_case(&name))
+    }
+
+    /// Read file contents.
+    pub fn read_file(&self, entry: &DfsEntry) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut sector = entry.start_sector;
+
+        while data.len() < entry.length as usize {
+            let contents = self.sector(entry.side, sector)
+                .ok_or_else(|| Disasm6502Error::InvalidFormat(format!("file \"{}\" extends past the end of the image", entry.name)))?;
+            data.extend_from_slice(contents);
+            sector += 1;
+        }
+
+        data.truncate(entry.length as usize);
+        Ok(data)
+    }
+
+    /// Read contents of a file by name.
+    pub fn read_file_by_name(&self, name: &str) -> Result<Vec<u8>> {
+        match self.find(name) {
+            Some(entry) => self.read_file(&entry),
+            None => Err(Disasm6502Error::FileNotFound(name.to_string()))
+        }
+    }
+
+    /// File contents placed at the load address.
+    pub fn segment(&self, entry: &DfsEntry) -> Result<Segment> {
+        Ok(Segment::new(entry.load_address as u16, self.read_file(entry)?))
+    }
+
+    /// Disassemble the file at its load address starting from the execution address, with MOS
+    /// entry points, vectors and SHEILA registers as labels.
+    pub fn listing(&self, entry: &DfsEntry) -> Result<Vec<String>> {
+        let segment = self.segment(entry)?;
+        let map = analysis::analyze(&segment.data, segment.address, &[entry.exec_address as u16], &[]);
+        Ok(listing::format(&analysis::disassemble(&segment.data, segment.address, &map), &Platform::Bbc.symbols()))
+    }
+}
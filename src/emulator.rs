@@ -0,0 +1,561 @@
+//! NMOS 6502 emulator executing instructions produced by the decoder.
+//!
+//! The emulator works on instruction level: each step fetches and decodes a single instruction
+//! through `instruction::decode` and executes it against a `Bus`, accumulating the cycle count
+//! reported by the decoder (plus page crossing and branch penalties). Decimal mode arithmetic,
+//! interrupts and the stable undocumented opcodes follow NMOS 6502 behavior.
+use instruction::{self, AddrMode, CPUStatusFlag, Instruction};
+use instruction::CPUStatusFlag::*;
+use instruction::OpCode::*;
+
+/// NMI vector address.
+pub const NMI_VECTOR: u16 = 0xFFFA;
+
+/// RESET vector address.
+pub const RESET_VECTOR: u16 = 0xFFFC;
+
+/// IRQ/BRK vector address.
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+
+// unused status register bit, always set when pushed
+const UNUSED: u8 = 0x20;
+
+// base address of the stack page
+const STACK: u16 = 0x0100;
+
+/// Memory and I/O as seen by the CPU.
+pub trait Bus {
+    /// Read a byte from the given address.
+    fn read(&mut self, address: u16) -> u8;
+    /// Write a byte to the given address.
+    fn write(&mut self, address: u16, value: u8);
+
+    /// Read a byte without side effects on I/O registers (used for trace output). Defaults to `read`.
+    fn peek(&mut self, address: u16) -> u8 {
+        self.read(address)
+    }
+}
+
+/// Plain memory: reads outside of the vector return 0, writes outside of it are ignored.
+impl Bus for Vec<u8> {
+    fn read(&mut self, address: u16) -> u8 {
+        self.get(address as usize).cloned().unwrap_or(0)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if let Some(b) = self.get_mut(address as usize) {
+            *b = value;
+        }
+    }
+}
+
+/// 6502 CPU state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cpu {
+    /// accumulator
+    pub a: u8,
+    /// X index register
+    pub x: u8,
+    /// Y index register
+    pub y: u8,
+    /// stack pointer
+    pub sp: u8,
+    /// program counter
+    pub pc: u16,
+    /// processor status register
+    pub p: u8,
+    /// number of cycles executed so far
+    pub cycles: u64,
+    /// CPU executed a HLT instruction and stopped
+    pub halted: bool
+}
+
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new()
+    }
+}
+
+impl Cpu {
+    /// Create a CPU in power-on state. Call `reset` to start execution from the RESET vector.
+    pub fn new() -> Cpu {
+        Cpu {
+            a: 0,
+            x: 0,
+            y: 0,
+            sp: 0,
+            pc: 0,
+            p: UNUSED | I.mask(),
+            cycles: 0,
+            halted: false
+        }
+    }
+
+    /// Check if status flag is set.
+    pub fn flag(&self, flag: CPUStatusFlag) -> bool {
+        self.p & flag.mask() != 0
+    }
+
+    /// Set or clear status flag.
+    pub fn set_flag(&mut self, flag: CPUStatusFlag, value: bool) {
+        if value {
+            self.p |= flag.mask();
+        } else {
+            self.p &= !flag.mask();
+        }
+    }
+
+    /// Perform RESET sequence: load program counter from RESET vector.
+    pub fn reset<B: Bus>(&mut self, bus: &mut B) {
+        self.sp = self.sp.wrapping_sub(3);
+        self.set_flag(I, true);
+        self.pc = read_word(bus, RESET_VECTOR);
+        self.cycles += 7;
+        self.halted = false;
+    }
+
+    /// Perform non-maskable interrupt sequence.
+    pub fn nmi<B: Bus>(&mut self, bus: &mut B) {
+        self.interrupt(bus, NMI_VECTOR, false);
+        self.cycles += 7;
+    }
+
+    /// Perform interrupt request sequence. Returns false if interrupts are disabled.
+    pub fn irq<B: Bus>(&mut self, bus: &mut B) -> bool {
+        if self.flag(I) {
+            return false;
+        }
+
+        self.interrupt(bus, IRQ_VECTOR, false);
+        self.cycles += 7;
+        true
+    }
+
+    /// Decode instruction at current program counter without executing it.
+    ///
+    /// Only the bytes belonging to the instruction are read from the bus.
+    pub fn fetch<B: Bus>(&self, bus: &mut B) -> Instruction {
+        let mut bytes = vec![bus.read(self.pc)];
+        let size = instruction::decode(self.pc, &mut 0, &bytes).size();
+        for i in 1..size {
+            bytes.push(bus.read(self.pc.wrapping_add(i)));
+        }
+
+        instruction::decode(self.pc, &mut 0, &bytes)
+    }
+
+    /// Execute a single instruction and return it. A halted CPU executes nothing and does not
+    /// access the bus; it returns a HLT instruction at the program counter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::emulator::Cpu;
+    ///
+    /// let mut memory = vec![0; 0x10000];
+    /// // LDA #$01 / ADC #$02
+    /// memory[0x0200..0x0204].copy_from_slice(&[0xA9, 0x01, 0x69, 0x02]);
+    ///
+    /// let mut cpu = Cpu::new();
+    /// cpu.pc = 0x0200;
+    /// cpu.step(&mut memory);
+    /// cpu.step(&mut memory);
+    ///
+    /// assert_eq!(cpu.a, 0x03);
+    /// ```
+    pub fn step<B: Bus>(&mut self, bus: &mut B) -> Instruction {
+        if self.halted {
+            return instruction::decode(self.pc, &mut 0, &[0x02]);
+        }
+
+        let instruction = self.fetch(bus);
+        self.execute(bus, &instruction);
+        instruction
+    }
+
+    fn execute<B: Bus>(&mut self, bus: &mut B, instruction: &Instruction) {
+        self.pc = self.pc.wrapping_add(instruction.size());
+        self.cycles += match instruction.addr_mode {
+            AddrMode::Relative => instruction.cycles as u64 - 2,
+            _ if instruction.extra_cycle => instruction.cycles as u64 - 1,
+            _ => instruction.cycles as u64
+        };
+
+        match instruction.opcode {
+            // Load/store
+            LDA(_) => { let v = self.load(bus, instruction); self.a = self.nz(v); },
+            LDX(_) => { let v = self.load(bus, instruction); self.x = self.nz(v); },
+            LDY(_) => { let v = self.load(bus, instruction); self.y = self.nz(v); },
+            STA(_) => { let v = self.a; self.store(bus, instruction, v); },
+            STX(_) => { let v = self.x; self.store(bus, instruction, v); },
+            STY(_) => { let v = self.y; self.store(bus, instruction, v); },
+            // Register transfers
+            TAX(_) => { let v = self.a; self.x = self.nz(v); },
+            TAY(_) => { let v = self.a; self.y = self.nz(v); },
+            TXA(_) => { let v = self.x; self.a = self.nz(v); },
+            TYA(_) => { let v = self.y; self.a = self.nz(v); },
+            // Stack operations
+            TSX(_) => { let v = self.sp; self.x = self.nz(v); },
+            TXS(_) => self.sp = self.x,
+            PHA(_) => { let v = self.a; self.push(bus, v); },
+            PHP(_) => { let v = self.p | B.mask() | UNUSED; self.push(bus, v); },
+            PLA(_) => { let v = self.pull(bus); self.a = self.nz(v); },
+            PLP(_) => self.p = (self.pull(bus) & !B.mask()) | UNUSED,
+            // Logical
+            AND(_) => { let v = self.load(bus, instruction) & self.a; self.a = self.nz(v); },
+            EOR(_) => { let v = self.load(bus, instruction) ^ self.a; self.a = self.nz(v); },
+            ORA(_) => { let v = self.load(bus, instruction) | self.a; self.a = self.nz(v); },
+            BIT(_) => {
+                let v = self.load(bus, instruction);
+                self.set_flag(N, v & 0x80 != 0);
+                self.set_flag(V, v & 0x40 != 0);
+                self.set_flag(Z, v & self.a == 0);
+            },
+            // Arithmetic
+            ADC(_) => { let v = self.load(bus, instruction); self.adc(v); },
+            SBC(_) => { let v = self.load(bus, instruction); self.sbc(v); },
+            CMP(_) => { let (r, v) = (self.a, self.load(bus, instruction)); self.compare(r, v); },
+            CPX(_) => { let (r, v) = (self.x, self.load(bus, instruction)); self.compare(r, v); },
+            CPY(_) => { let (r, v) = (self.y, self.load(bus, instruction)); self.compare(r, v); },
+            // Inc/Dec
+            INC(_) => { self.modify(bus, instruction, |cpu, v| cpu.nz(v.wrapping_add(1))); },
+            DEC(_) => { self.modify(bus, instruction, |cpu, v| cpu.nz(v.wrapping_sub(1))); },
+            INX(_) => { let v = self.x.wrapping_add(1); self.x = self.nz(v); },
+            INY(_) => { let v = self.y.wrapping_add(1); self.y = self.nz(v); },
+            DEX(_) => { let v = self.x.wrapping_sub(1); self.x = self.nz(v); },
+            DEY(_) => { let v = self.y.wrapping_sub(1); self.y = self.nz(v); },
+            // Shifts
+            ASL(_) => { self.modify(bus, instruction, Cpu::asl); },
+            LSR(_) => { self.modify(bus, instruction, Cpu::lsr); },
+            ROL(_) => { self.modify(bus, instruction, Cpu::rol); },
+            ROR(_) => { self.modify(bus, instruction, Cpu::ror); },
+            // Jump calls
+            JMP(_) => self.pc = self.address(bus, instruction),
+            JSR(_) => {
+                let pc = self.pc.wrapping_sub(1);
+                self.push_word(bus, pc);
+                self.pc = instruction.operand.unwrap_or(0);
+            },
+            RTS(_) => self.pc = self.pull_word(bus).wrapping_add(1),
+            // Branches
+            BCC(_) => { let c = !self.flag(C); self.branch(instruction, c); },
+            BCS(_) => { let c = self.flag(C); self.branch(instruction, c); },
+            BEQ(_) => { let c = self.flag(Z); self.branch(instruction, c); },
+            BMI(_) => { let c = self.flag(N); self.branch(instruction, c); },
+            BNE(_) => { let c = !self.flag(Z); self.branch(instruction, c); },
+            BPL(_) => { let c = !self.flag(N); self.branch(instruction, c); },
+            BVC(_) => { let c = !self.flag(V); self.branch(instruction, c); },
+            BVS(_) => { let c = self.flag(V); self.branch(instruction, c); },
+            // Status flag changes
+            CLC(_) => self.set_flag(C, false),
+            CLD(_) => self.set_flag(D, false),
+            CLI(_) => self.set_flag(I, false),
+            CLV(_) => self.set_flag(V, false),
+            SEC(_) => self.set_flag(C, true),
+            SED(_) => self.set_flag(D, true),
+            SEI(_) => self.set_flag(I, true),
+            // System functions
+            BRK(_) => {
+                // BRK skips the byte following the opcode
+                self.pc = self.pc.wrapping_add(1);
+                self.interrupt(bus, IRQ_VECTOR, true);
+            },
+            NOP(_) => {
+                if instruction.operand.is_some() {
+                    self.load(bus, instruction);
+                }
+            },
+            RTI(_) => {
+                self.p = (self.pull(bus) & !B.mask()) | UNUSED;
+                self.pc = self.pull_word(bus);
+            },
+            // illegal/undocumented
+            HLT(_) => {
+                self.pc = instruction.address;
+                self.halted = true;
+            },
+            SLO(_) => { let v = self.modify(bus, instruction, Cpu::asl) | self.a; self.a = self.nz(v); },
+            RLA(_) => { let v = self.modify(bus, instruction, Cpu::rol) & self.a; self.a = self.nz(v); },
+            SRE(_) => { let v = self.modify(bus, instruction, Cpu::lsr) ^ self.a; self.a = self.nz(v); },
+            RRA(_) => { let v = self.modify(bus, instruction, Cpu::ror); self.adc(v); },
+            DCP(_) => { let v = self.modify(bus, instruction, |_, v| v.wrapping_sub(1)); let r = self.a; self.compare(r, v); },
+            ISC(_) => { let v = self.modify(bus, instruction, |_, v| v.wrapping_add(1)); self.sbc(v); },
+            SAX(_) => { let v = self.a & self.x; self.store(bus, instruction, v); },
+            LAX(_) => { let v = self.load(bus, instruction); self.a = self.nz(v); self.x = v; },
+            LAS(_) => {
+                let v = self.load(bus, instruction) & self.sp;
+                self.sp = v;
+                self.x = v;
+                self.a = self.nz(v);
+            },
+            ANC(_) => {
+                let v = self.load(bus, instruction) & self.a;
+                self.a = self.nz(v);
+                self.set_flag(C, v & 0x80 != 0);
+            },
+            ALR(_) => {
+                let v = self.load(bus, instruction) & self.a;
+                self.a = self.lsr(v);
+            },
+            ARR(_) => { let v = self.load(bus, instruction); self.arr(v); },
+            AXS(_) => {
+                let v = self.load(bus, instruction);
+                let ax = self.a & self.x;
+                self.set_flag(C, ax >= v);
+                self.x = self.nz(ax.wrapping_sub(v));
+            },
+            XAA(_) => {
+                // unstable, uses the most common value of the "magic" constant
+                let v = (self.a | 0xEE) & self.x & self.load(bus, instruction);
+                self.a = self.nz(v);
+            },
+            // unstable stores, value is ANDed with high byte of base address + 1
+            AHX(_) => { let v = self.a & self.x; self.store_high(bus, instruction, v); },
+            SHX(_) => { let v = self.x; self.store_high(bus, instruction, v); },
+            SHY(_) => { let v = self.y; self.store_high(bus, instruction, v); },
+            TAS(_) => {
+                self.sp = self.a & self.x;
+                let v = self.sp;
+                self.store_high(bus, instruction, v);
+            }
+        }
+    }
+
+    // push return address and status, then jump through the vector
+    fn interrupt<B: Bus>(&mut self, bus: &mut B, vector: u16, brk: bool) {
+        let pc = self.pc;
+        self.push_word(bus, pc);
+        let p = if brk { self.p | B.mask() } else { self.p & !B.mask() };
+        self.push(bus, p | UNUSED);
+        self.set_flag(I, true);
+        self.pc = read_word(bus, vector);
+    }
+
+    // effective address of the operand, adds page crossing penalty if applicable
+    fn address<B: Bus>(&mut self, bus: &mut B, instruction: &Instruction) -> u16 {
+        let operand = instruction.operand.unwrap_or(0);
+        let (base, index) = match instruction.addr_mode {
+            AddrMode::Zeropage | AddrMode::Absolute => return operand,
+            AddrMode::ZeropageIndexedX => return operand.wrapping_add(self.x as u16) & 0x00FF,
+            AddrMode::ZeropageIndexedY => return operand.wrapping_add(self.y as u16) & 0x00FF,
+            AddrMode::Indirect => {
+                // indirect jump does not carry into the high byte of the pointer
+                let hi = (operand & 0xFF00) | (operand.wrapping_add(1) & 0x00FF);
+                return bus.read(operand) as u16 | (bus.read(hi) as u16) << 8;
+            },
+            AddrMode::IndexedIndirectX => {
+                let ptr = operand.wrapping_add(self.x as u16) & 0x00FF;
+                return read_zeropage_word(bus, ptr);
+            },
+            AddrMode::AbsoluteIndexedX(_) => (operand, self.x),
+            AddrMode::AbsoluteIndexedY(_) => (operand, self.y),
+            AddrMode::IndirectIndexedY(_) => (read_zeropage_word(bus, operand), self.y),
+            _ => return 0
+        };
+
+        let address = base.wrapping_add(index as u16);
+        if instruction.extra_cycle && (address & 0xFF00) != (base & 0xFF00) {
+            self.cycles += 1;
+        }
+
+        address
+    }
+
+    fn load<B: Bus>(&mut self, bus: &mut B, instruction: &Instruction) -> u8 {
+        match instruction.addr_mode {
+            AddrMode::Immediate => instruction.operand.unwrap_or(0) as u8,
+            _ => {
+                let address = self.address(bus, instruction);
+                bus.read(address)
+            }
+        }
+    }
+
+    fn store<B: Bus>(&mut self, bus: &mut B, instruction: &Instruction, value: u8) {
+        let address = self.address(bus, instruction);
+        bus.write(address, value);
+    }
+
+    fn store_high<B: Bus>(&mut self, bus: &mut B, instruction: &Instruction, value: u8) {
+        let address = self.address(bus, instruction);
+        let index = match instruction.addr_mode {
+            AddrMode::AbsoluteIndexedX(_) => self.x,
+            _ => self.y
+        };
+        let high = (address.wrapping_sub(index as u16) >> 8) as u8;
+        bus.write(address, value & high.wrapping_add(1));
+    }
+
+    // read-modify-write: NMOS 6502 writes the unmodified value back before the result
+    fn modify<B: Bus, F: FnOnce(&mut Cpu, u8) -> u8>(&mut self, bus: &mut B, instruction: &Instruction, f: F) -> u8 {
+        if let AddrMode::Accumulator = instruction.addr_mode {
+            let v = self.a;
+            self.a = f(self, v);
+            return self.a;
+        }
+
+        let address = self.address(bus, instruction);
+        let v = bus.read(address);
+        bus.write(address, v);
+        let result = f(self, v);
+        bus.write(address, result);
+        result
+    }
+
+    fn branch(&mut self, instruction: &Instruction, condition: bool) {
+        if !condition {
+            return;
+        }
+
+        let target = self.pc.wrapping_add(instruction.operand.unwrap_or(0) as i8 as u16);
+        self.cycles += if target & 0xFF00 != self.pc & 0xFF00 { 2 } else { 1 };
+        self.pc = target;
+    }
+
+    fn push<B: Bus>(&mut self, bus: &mut B, value: u8) {
+        bus.write(STACK | self.sp as u16, value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    fn pull<B: Bus>(&mut self, bus: &mut B) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        bus.read(STACK | self.sp as u16)
+    }
+
+    fn push_word<B: Bus>(&mut self, bus: &mut B, value: u16) {
+        self.push(bus, (value >> 8) as u8);
+        self.push(bus, value as u8);
+    }
+
+    fn pull_word<B: Bus>(&mut self, bus: &mut B) -> u16 {
+        let lo = self.pull(bus) as u16;
+        let hi = self.pull(bus) as u16;
+        hi << 8 | lo
+    }
+
+    // set N and Z flags according to the value and pass it through
+    fn nz(&mut self, value: u8) -> u8 {
+        self.set_flag(N, value & 0x80 != 0);
+        self.set_flag(Z, value == 0);
+        value
+    }
+
+    fn compare(&mut self, register: u8, value: u8) {
+        self.set_flag(C, register >= value);
+        self.nz(register.wrapping_sub(value));
+    }
+
+    fn asl(&mut self, value: u8) -> u8 {
+        self.set_flag(C, value & 0x80 != 0);
+        self.nz(value << 1)
+    }
+
+    fn lsr(&mut self, value: u8) -> u8 {
+        self.set_flag(C, value & 0x01 != 0);
+        self.nz(value >> 1)
+    }
+
+    fn rol(&mut self, value: u8) -> u8 {
+        let carry = self.flag(C) as u8;
+        self.set_flag(C, value & 0x80 != 0);
+        self.nz(value << 1 | carry)
+    }
+
+    fn ror(&mut self, value: u8) -> u8 {
+        let carry = self.flag(C) as u8;
+        self.set_flag(C, value & 0x01 != 0);
+        self.nz(value >> 1 | carry << 7)
+    }
+
+    fn adc(&mut self, value: u8) {
+        let a = self.a as u16;
+        let m = value as u16;
+        let carry = self.flag(C) as u16;
+        let binary = a + m + carry;
+
+        if !self.flag(D) {
+            self.set_flag(C, binary > 0xFF);
+            self.set_flag(V, (!(a ^ m) & (a ^ binary) & 0x80) != 0);
+            self.a = self.nz(binary as u8);
+            return;
+        }
+
+        // NMOS: Z reflects the binary result, N and V are taken before the high nibble is adjusted
+        let mut lo = (a & 0x0F) + (m & 0x0F) + carry;
+        if lo > 0x09 {
+            lo += 0x06;
+        }
+        let mut hi = (a >> 4) + (m >> 4) + (lo > 0x0F) as u16;
+
+        self.set_flag(Z, binary & 0xFF == 0);
+        self.set_flag(N, hi & 0x08 != 0);
+        self.set_flag(V, (((hi << 4) ^ a) & 0x80) != 0 && ((a ^ m) & 0x80) == 0);
+
+        if hi > 0x09 {
+            hi += 0x06;
+        }
+        self.set_flag(C, hi > 0x0F);
+        self.a = ((hi << 4) | (lo & 0x0F)) as u8;
+    }
+
+    fn sbc(&mut self, value: u8) {
+        let a = self.a as i16;
+        let m = value as i16;
+        let borrow = !self.flag(C) as i16;
+        let binary = a - m - borrow;
+
+        // NMOS: flags always reflect the binary result
+        self.set_flag(C, binary >= 0);
+        self.set_flag(V, ((a ^ binary) & (a ^ m) & 0x80) != 0);
+        let result = self.nz(binary as u8);
+
+        if !self.flag(D) {
+            self.a = result;
+            return;
+        }
+
+        let mut lo = (a & 0x0F) - (m & 0x0F) - borrow;
+        if lo < 0 {
+            lo = ((lo - 0x06) & 0x0F) - 0x10;
+        }
+        let mut r = (a & 0xF0) - (m & 0xF0) + lo;
+        if r < 0 {
+            r -= 0x60;
+        }
+        self.a = r as u8;
+    }
+
+    fn arr(&mut self, value: u8) {
+        let t = self.a & value;
+        let carry = self.flag(C) as u8;
+        let mut r = t >> 1 | carry << 7;
+        self.nz(r);
+
+        if !self.flag(D) {
+            self.set_flag(C, r & 0x40 != 0);
+            self.set_flag(V, ((r >> 6) ^ (r >> 5)) & 0x01 != 0);
+            self.a = r;
+            return;
+        }
+
+        self.set_flag(V, (t ^ r) & 0x40 != 0);
+        if (t & 0x0F) + (t & 0x01) > 0x05 {
+            r = (r & 0xF0) | (r.wrapping_add(0x06) & 0x0F);
+        }
+        let carry = (t as u16 & 0xF0) + (t as u16 & 0x10) > 0x50;
+        self.set_flag(C, carry);
+        if carry {
+            r = r.wrapping_add(0x60);
+        }
+        self.a = r;
+    }
+}
+
+fn read_word<B: Bus>(bus: &mut B, address: u16) -> u16 {
+    bus.read(address) as u16 | (bus.read(address.wrapping_add(1)) as u16) << 8
+}
+
+fn read_zeropage_word<B: Bus>(bus: &mut B, address: u16) -> u16 {
+    bus.read(address & 0x00FF) as u16 | (bus.read(address.wrapping_add(1) & 0x00FF) as u16) << 8
+}
@@ -10,13 +10,31 @@ pub type Result<T> = result::Result<T, Disasm6502Error>;
 #[derive(Debug)]
 pub enum Disasm6502Error {
     /// I/O error
-    Io(io::Error)
+    Io(io::Error),
+    /// input data does not match the expected file format
+    InvalidFormat(String),
+    /// file with the given name is not on the disk image
+    FileNotFound(String),
+    /// record checksum does not match its contents
+    Checksum {
+        /// line number of the record (starting at 1)
+        line: usize,
+        /// checksum computed from the record contents
+        expected: u8,
+        /// checksum stored in the record
+        actual: u8
+    }
 }
 
 impl fmt::Display for Disasm6502Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Disasm6502Error::Io(ref err) => err.fmt(f)
+            Disasm6502Error::Io(ref err) => err.fmt(f),
+            Disasm6502Error::InvalidFormat(ref msg) => write!(f, "invalid format: {}", msg),
+            Disasm6502Error::FileNotFound(ref name) => write!(f, "file \"{}\" not found on disk image", name),
+            Disasm6502Error::Checksum { line, expected, actual } => {
+                write!(f, "checksum mismatch on line {}: expected ${:02X}, found ${:02X}", line, expected, actual)
+            }
         }
     }
 }
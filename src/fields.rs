@@ -0,0 +1,42 @@
+//! Readers for header and directory fields shared by the file format loaders.
+use basic;
+
+/// Little-endian 16-bit value at the offset.
+pub fn word(bytes: &[u8], offset: usize) -> u16 {
+    bytes[offset] as u16 | (bytes[offset + 1] as u16) << 8
+}
+
+/// Big-endian 16-bit value at the offset (CRT and SID headers).
+pub fn word_be(bytes: &[u8], offset: usize) -> u16 {
+    (bytes[offset] as u16) << 8 | bytes[offset + 1] as u16
+}
+
+/// Big-endian 32-bit value at the offset.
+pub fn long_be(bytes: &[u8], offset: usize) -> u32 {
+    (word_be(bytes, offset) as u32) << 16 | word_be(bytes, offset + 2) as u32
+}
+
+/// Zero terminated string with one ISO-8859-1 character per byte.
+pub fn text(bytes: &[u8]) -> String {
+    bytes.iter().take_while(|&&b| b != 0x00).map(|&b| b as char).collect()
+}
+
+/// Zero terminated string of 7-bit ASCII characters with the high bit ignored (Acorn and Apple
+/// names), without trailing spaces.
+pub fn ascii_text(bytes: &[u8]) -> String {
+    bytes.iter()
+        .take_while(|&&b| b != 0x00)
+        .map(|&b| (b & 0x7F) as char)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// PETSCII name ending at the first padding byte.
+pub fn petscii_name(bytes: &[u8], padding: u8) -> String {
+    let mut name = String::new();
+    for &b in bytes.iter().take_while(|&&b| b != padding) {
+        basic::push_petscii(&mut name, b);
+    }
+    name
+}
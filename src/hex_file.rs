@@ -0,0 +1,187 @@
+//! Intel HEX and Motorola S-record loaders.
+//!
+//! Both formats are text files with one record per line, each carrying an address, data bytes
+//! and a checksum. Data records are collected into contiguous segments; gaps between records
+//! start a new segment. Addresses above $FFFF are split into 64K banks, with the bank number
+//! taken from the upper address bits. Start address records become the entry point.
+use std::collections::BTreeMap;
+use error::{Disasm6502Error, Result};
+use segment::Segment;
+
+/// Contents of a hex file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HexFile {
+    /// data in address order
+    pub segments: Vec<Segment>,
+    /// start address from the start address record (if any)
+    pub entry_point: Option<u32>
+}
+
+impl HexFile {
+    /// Parse Intel HEX or Motorola S-record text, depending on the first record.
+    pub fn parse(text: &str) -> Result<HexFile> {
+        match text.trim_start().chars().next() {
+            Some(':') => HexFile::parse_intel(text),
+            Some('S') | Some('s') => HexFile::parse_srecord(text),
+            _ => Err(Disasm6502Error::InvalidFormat(String::from("neither Intel HEX nor S-record data")))
+        }
+    }
+
+    /// Parse Intel HEX text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::hex_file::HexFile;
+    ///
+    /// let hex = HexFile::parse_intel(":03800000A9016073\n:00000001FF").unwrap();
+    ///
+    /// assert_eq!(hex.segments[0].address, 0x8000);
+    /// assert_eq!(hex.segments[0].data, vec![0xA9, 0x01, 0x60]);
+    /// ```
+    pub fn parse_intel(text: &str) -> Result<HexFile> {
+        let mut memory = BTreeMap::new();
+        let mut entry_point = None;
+        let mut base: u32 = 0;
+
+        for (index, line) in records(text) {
+            let record = parse_record(line, index, ':', |sum| 0u8.wrapping_sub(sum))?;
+            if record.len() < 4 || record[0] as usize + 4 != record.len() {
+                return Err(invalid(index, "record length does not match its contents"));
+            }
+
+            let address = (record[1] as u32) << 8 | record[2] as u32;
+            let data = &record[4..];
+            match record[3] {
+                0x00 => store(&mut memory, base.wrapping_add(address), data),
+                0x01 => break,
+                0x02 if data.len() == 2 => base = ((data[0] as u32) << 8 | data[1] as u32) << 4,
+                0x03 if data.len() == 4 => {
+                    let segment = (data[0] as u32) << 8 | data[1] as u32;
+                    entry_point = Some((segment << 4).wrapping_add((data[2] as u32) << 8 | data[3] as u32));
+                },
+                0x04 if data.len() == 2 => base = ((data[0] as u32) << 8 | data[1] as u32) << 16,
+                0x05 if data.len() == 4 => entry_point = Some(big_endian(data)),
+                t => return Err(invalid(index, &format!("unsupported record type {:02X}", t)))
+            }
+        }
+
+        Ok(HexFile { segments: segments(&memory), entry_point })
+    }
+
+    /// Parse Motorola S-record (S19/S28/S37) text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::hex_file::HexFile;
+    ///
+    /// let srec = HexFile::parse_srecord("S1068000A901606F\nS90380007C").unwrap();
+    ///
+    /// assert_eq!(srec.segments[0].data, vec![0xA9, 0x01, 0x60]);
+    /// assert_eq!(srec.entry_point, Some(0x8000));
+    /// ```
+    pub fn parse_srecord(text: &str) -> Result<HexFile> {
+        let mut memory = BTreeMap::new();
+        let mut entry_point = None;
+
+        for (index, line) in records(text) {
+            if !line.starts_with('S') && !line.starts_with('s') {
+                return Err(invalid(index, "malformed record"));
+            }
+
+            let kind = line[1..].chars().next().unwrap_or(' ');
+            let record = parse_record(&line[1..], index, kind, |sum| !sum)?;
+            // the byte count includes the checksum
+            if record.is_empty() || record[0] as usize != record.len() {
+                return Err(invalid(index, "record length does not match its contents"));
+            }
+
+            let address_size = match kind {
+                '0' | '1' | '5' | '9' => 2,
+                '2' | '6' | '8' => 3,
+                '3' | '7' => 4,
+                _ => return Err(invalid(index, &format!("unsupported record type S{}", kind)))
+            };
+            if record.len() < 1 + address_size {
+                return Err(invalid(index, "record too short for its address"));
+            }
+
+            let address = big_endian(&record[1..1 + address_size]);
+            match kind {
+                '1' | '2' | '3' => store(&mut memory, address, &record[1 + address_size..]),
+                '7' | '8' | '9' => entry_point = Some(address),
+                // header and record counts
+                _ => {}
+            }
+        }
+
+        Ok(HexFile { segments: segments(&memory), entry_point })
+    }
+}
+
+// non-empty lines with their line numbers (starting at 1)
+fn records(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|&(_, l)| !l.is_empty())
+}
+
+// decode hex digits following the start character and verify the checksum in the last byte,
+// returns the bytes without the checksum
+fn parse_record<F: Fn(u8) -> u8>(line: &str, index: usize, start: char, checksum: F) -> Result<Vec<u8>> {
+    if !line.starts_with(start) || line.len() % 2 == 0 {
+        return Err(invalid(index, "malformed record"));
+    }
+
+    let mut bytes = (1..line.len()).step_by(2)
+        .map(|i| line.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| invalid(index, "invalid hex digits"))?;
+
+    let actual = bytes.pop().ok_or_else(|| invalid(index, "missing checksum"))?;
+    let expected = checksum(bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+    if actual != expected {
+        return Err(Disasm6502Error::Checksum { line: index, expected, actual });
+    }
+
+    Ok(bytes)
+}
+
+fn invalid(index: usize, message: &str) -> Disasm6502Error {
+    Disasm6502Error::InvalidFormat(format!("line {}: {}", index, message))
+}
+
+fn big_endian(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |value, &b| value << 8 | b as u32)
+}
+
+// later records overwrite earlier ones
+fn store(memory: &mut BTreeMap<u32, u8>, address: u32, data: &[u8]) {
+    for (i, &b) in data.iter().enumerate() {
+        memory.insert(address.wrapping_add(i as u32), b);
+    }
+}
+
+// split memory into contiguous runs, not crossing 64K bank boundaries
+fn segments(memory: &BTreeMap<u32, u8>) -> Vec<Segment> {
+    let mut result: Vec<Segment> = Vec::new();
+    let mut next: Option<u32> = None;
+
+    for (&address, &b) in memory.iter() {
+        if next == Some(address) && address & 0xFFFF != 0 {
+            if let Some(segment) = result.last_mut() {
+                segment.data.push(b);
+            }
+        } else {
+            let bank = (address >> 16) as u16;
+            result.push(Segment { bank: if bank == 0 { None } else { Some(bank) }, address: address as u16, data: vec![b] });
+        }
+        next = address.checked_add(1);
+    }
+
+    result
+}
@@ -0,0 +1,216 @@
+//! iNES / NES 2.0 ROM image loader.
+//!
+//! The 16 byte header describes PRG-ROM and CHR-ROM sizes, the mapper and an optional 512 byte
+//! trainer. PRG-ROM is split into banks and the banks visible at power-on are mapped into
+//! $8000-$FFFF so the interrupt vectors can be read. CHR-ROM holds graphics only and is not
+//! exposed for disassembly.
+use analysis;
+use banking::{BankedAddress, BankLayout};
+use emulator::{IRQ_VECTOR, NMI_VECTOR, RESET_VECTOR};
+use error::{Disasm6502Error, Result};
+use listing;
+use memory_image::MemoryImage;
+use segment::Segment;
+use symbols::SymbolTable;
+
+/// Size of a 16K PRG-ROM bank.
+pub const PRG_BANK_SIZE: usize = 0x4000;
+
+/// Size of a CHR-ROM bank.
+pub const CHR_BANK_SIZE: usize = 0x2000;
+
+/// Address at which PRG-ROM starts in CPU memory.
+pub const PRG_ROM_START: u16 = 0x8000;
+
+const SIGNATURE: &[u8] = b"NES\x1A";
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+
+/// Contents of an iNES or NES 2.0 file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Nes {
+    /// header uses the NES 2.0 format
+    pub nes2: bool,
+    /// mapper number
+    pub mapper: u16,
+    /// submapper number (NES 2.0 only, 0 otherwise)
+    pub submapper: u8,
+    /// 512 byte trainer loaded at $7000 (if present)
+    pub trainer: Option<Vec<u8>>,
+    /// PRG-ROM contents
+    pub prg_rom: Vec<u8>,
+    /// CHR-ROM size in bytes (contents are skipped)
+    pub chr_rom_size: usize
+}
+
+impl Nes {
+    /// Parse contents of an iNES or NES 2.0 file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::ines::Nes;
+    ///
+    /// // NROM with a single 16K bank mirrored at $8000 and $C000, reset vector at $C000
+    /// let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    /// rom.extend_from_slice(&vec![0xEA; 0x4000]);
+    /// rom[16 + 0x3FFC] = 0x00;
+    /// rom[16 + 0x3FFD] = 0xC0;
+    ///
+    /// let nes = Nes::parse(&rom).unwrap();
+    /// assert_eq!(nes.mapper, 0);
+    /// assert_eq!(nes.reset_vector(), Some(0xC000));
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Nes> {
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(SIGNATURE) {
+            return Err(Disasm6502Error::InvalidFormat(String::from("missing iNES signature")));
+        }
+
+        let nes2 = bytes[7] & 0x0C == 0x08;
+        let mut mapper = (bytes[6] >> 4) as u16 | (bytes[7] & 0xF0) as u16;
+        let mut submapper = 0;
+        let (prg_size, chr_size) = if nes2 {
+            mapper |= ((bytes[8] & 0x0F) as u16) << 8;
+            submapper = bytes[8] >> 4;
+            (rom_size(bytes[4], bytes[9] & 0x0F, PRG_BANK_SIZE), rom_size(bytes[5], bytes[9] >> 4, CHR_BANK_SIZE))
+        } else {
+            (bytes[4] as usize * PRG_BANK_SIZE, bytes[5] as usize * CHR_BANK_SIZE)
+        };
+
+        let mut offset = HEADER_SIZE;
+        let trainer = if bytes[6] & 0x04 != 0 {
+            offset += TRAINER_SIZE;
+            Some(bytes.get(HEADER_SIZE..offset)
+                .ok_or_else(|| Disasm6502Error::InvalidFormat(String::from("truncated trainer")))?
+                .to_vec())
+        } else {
+            None
+        };
+
+        let prg_rom = bytes.get(offset..offset.saturating_add(prg_size))
+            .ok_or_else(|| Disasm6502Error::InvalidFormat(format!("PRG-ROM shorter than {} bytes", prg_size)))?
+            .to_vec();
+        if prg_rom.is_empty() {
+            return Err(Disasm6502Error::InvalidFormat(String::from("no PRG-ROM")));
+        }
+
+        Ok(Nes { nes2, mapper, submapper, trainer, prg_rom, chr_rom_size: chr_size })
+    }
+
+    /// Split PRG-ROM into banks of the given size, each placed at $8000.
+    pub fn prg_banks(&self, bank_size: usize) -> Vec<Segment> {
+        self.prg_rom.chunks(bank_size)
+            .enumerate()
+            .map(|(bank, data)| Segment::banked(bank as u16, PRG_ROM_START, data.to_vec()))
+            .collect()
+    }
+
+    /// PRG-ROM banks mapped into $8000-$FFFF at power-on.
+    ///
+    /// NROM-style mappers see the whole PRG-ROM (16K images mirrored at $C000), mappers
+    /// switching 32K at once start with the last 32K, MMC3-style mappers switching 8K banks fix
+    /// the last two banks at $C000 and $E000, and all other mappers are assumed to fix the last
+    /// 16K bank at $C000 with the first bank at $8000.
+    ///
+    /// Whatever the mapper switches, segments are numbered in 16K PRG-ROM banks (the bank
+    /// holding the first byte), so a bank and an address always give the same PRG-ROM offset.
+    /// An image without PRG-ROM has no segments.
+    pub fn mapped_segments(&self) -> Vec<Segment> {
+        if self.prg_rom.is_empty() {
+            return Vec::new();
+        }
+
+        // `size` bytes of PRG-ROM from `offset` seen at `address`
+        let at = |offset: usize, address: u16, size: usize| {
+            let data = self.prg_rom[offset..(offset + size).min(self.prg_rom.len())].to_vec();
+            Segment::banked((offset / PRG_BANK_SIZE) as u16, address, data)
+        };
+        let last = (self.prg_rom.len() - 1) / PRG_BANK_SIZE * PRG_BANK_SIZE;
+
+        match self.mapper {
+            0 | 3 | 13 | 87 | 185 if self.prg_rom.len() <= 2 * PRG_BANK_SIZE =>
+                vec![at(0, 0x8000, PRG_BANK_SIZE), at(last, 0xC000, PRG_BANK_SIZE)],
+            7 | 11 | 34 | 66 => {
+                let start = last.saturating_sub(PRG_BANK_SIZE);
+                vec![at(start, 0x8000, PRG_BANK_SIZE), at(last, 0xC000, PRG_BANK_SIZE)]
+            },
+            4 | 118 | 119 | 206 => {
+                let size = PRG_BANK_SIZE / 2;
+                let last = (self.prg_rom.len() - 1) / size * size;
+                vec![at(0, 0x8000, size), at(size.min(last), 0xA000, size),
+                     at(last.saturating_sub(size), 0xC000, size), at(last, 0xE000, size)]
+            },
+            _ => vec![at(0, 0x8000, PRG_BANK_SIZE), at(last, 0xC000, PRG_BANK_SIZE)]
+        }
+    }
+
+    /// Windows of the power-on mapping, each with the bank mapped into it.
+    pub fn layout(&self) -> BankLayout {
+        let mut layout = BankLayout::new();
+        for segment in self.mapped_segments().iter() {
+            let end = segment.address.wrapping_add(segment.data.len().saturating_sub(1) as u16);
+            layout.add_window(segment.address, end, &segment.bank.into_iter().collect::<Vec<u16>>());
+        }
+        layout
+    }
+
+    /// NMI vector read from the power-on mapping.
+    pub fn nmi_vector(&self) -> Option<u16> {
+        self.vector(NMI_VECTOR)
+    }
+
+    /// Reset vector read from the power-on mapping.
+    pub fn reset_vector(&self) -> Option<u16> {
+        self.vector(RESET_VECTOR)
+    }
+
+    /// IRQ/BRK vector read from the power-on mapping.
+    pub fn irq_vector(&self) -> Option<u16> {
+        self.vector(IRQ_VECTOR)
+    }
+
+    /// Reset, NMI and IRQ handlers (duplicates removed).
+    pub fn entry_points(&self) -> Vec<u16> {
+        let mut result = Vec::new();
+        for address in [self.reset_vector(), self.nmi_vector(), self.irq_vector()].iter().filter_map(|&v| v) {
+            if !result.contains(&address) {
+                result.push(address);
+            }
+        }
+        result
+    }
+
+    /// Labels of the reset, NMI and IRQ handlers.
+    pub fn symbols(&self) -> SymbolTable {
+        let mut symbols = SymbolTable::new();
+        for &(vector, label) in [(self.irq_vector(), "IRQ"), (self.nmi_vector(), "NMI"), (self.reset_vector(), "RESET")].iter() {
+            if let Some(address) = vector {
+                symbols.insert(address, label);
+            }
+        }
+        symbols
+    }
+
+    /// Disassemble the power-on mapping starting from the reset, NMI and IRQ handlers, with code
+    /// not reachable from them listed as data.
+    pub fn listing(&self) -> Vec<String> {
+        let image = MemoryImage::from(self.mapped_segments());
+        let entry_points: Vec<BankedAddress> = self.entry_points().into_iter().map(BankedAddress::unbanked).collect();
+        let map = analysis::analyze_banked(&image, &self.layout(), &entry_points, &[]);
+        listing::format(&analysis::disassemble_image(&image, &map), &self.symbols())
+    }
+
+    fn vector(&self, address: u16) -> Option<u16> {
+        self.mapped_segments().iter().find(|s| s.contains(address)).and_then(|s| s.read_word(address))
+    }
+}
+
+// NES 2.0 sizes: 12 bit bank count, or exponent-multiplier notation if the MSB nibble is $F
+fn rom_size(lsb: u8, msb: u8, bank_size: usize) -> usize {
+    if msb == 0x0F {
+        2usize.saturating_pow((lsb >> 2) as u32).saturating_mul((lsb & 0x03) as usize * 2 + 1)
+    } else {
+        ((msb as usize) << 8 | lsb as usize) * bank_size
+    }
+}
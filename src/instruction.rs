@@ -13,6 +13,7 @@
 // rel = $0000                // relative to PC/IP
 
 use std::fmt;
+use banking::BankedAddress;
 use self::CPURegister::*;
 use self::CPUStatusFlag::*;
 use self::OpCode::*;
@@ -23,14 +24,13 @@ pub type FlagVec = Option<Vec<CPUStatusFlag>>;
 
 // Some() vector
 macro_rules! sv {
-    ( $( $x:expr ),* ) => {{
-        let mut temp_vec = Vec::new();
-        $(temp_vec.push($x);)*
-            Some(temp_vec)
-    }};
+    ( $( $x:expr ),* ) => {
+        Some(vec![$($x),*])
+    };
 }
 
 /// 6502 addressing modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum AddrMode {
     Implied,
     Accumulator,
@@ -51,6 +51,7 @@ pub enum AddrMode {
 }
 
 /// 6502 CPU registers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum CPURegister {
     A, X, Y
 }
@@ -66,6 +67,7 @@ impl fmt::Display for CPURegister {
 }
 
 /// 6502 CPU status flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum CPUStatusFlag {
     N, V, B, D, I, Z, C
 }
@@ -81,7 +83,18 @@ impl fmt::Display for CPUStatusFlag {
     }
 }
 
+impl CPUStatusFlag {
+    /// Bit mask of the flag in processor status register.
+    pub fn mask(&self) -> u8 {
+        match *self {
+            N => 0x80, V => 0x40, B => 0x10, D => 0x08,
+            I => 0x04, Z => 0x02, C => 0x01
+        }
+    }
+}
+
 /// 6502 opcodes (with associated hex value).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum OpCode {
     // Load/store
     LDA(u8), LDX(u8), LDY(u8), STA(u8), STX(u8), STY(u8),
@@ -180,6 +193,7 @@ impl fmt::Display for OpCode {
 }
 
 /// Decoded 6502 instruction.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Instruction {
     /// instruction opcode
     pub opcode: OpCode,
@@ -195,6 +209,8 @@ pub struct Instruction {
     pub extra_cycle: bool,
     /// instruction is illegal/undocumented
     pub illegal: bool,
+    /// bank the instruction was decoded from (None if not banked)
+    pub bank: Option<u16>,
     /// registers read by this instruction (optional)
     pub registers_read: RegVec,
     /// registers written by this instruction (optional)
@@ -205,26 +221,32 @@ pub struct Instruction {
 
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "${:04X}: {} {}", self.address, self.as_hex_str(), self.as_str())
+        write!(f, "{}: {} {}", self.banked_address(), self.as_hex_str(), self.as_str())
     }
 }
 
 impl Instruction {
     fn new(opcode: OpCode, address: u16, cycles: u8, addr_mode: AddrMode) -> Instruction {
         Instruction {
-            opcode: opcode,
-            cycles: cycles,
-            addr_mode: addr_mode,
-            address: address,
+            opcode,
+            cycles,
+            addr_mode,
+            address,
             extra_cycle: false,
             illegal: false,
             operand: None,
+            bank: None,
             registers_read: None,
             registers_written: None,
             affected_flags: None
         }
     }
 
+    /// Address of the instruction qualified with its bank.
+    pub fn banked_address(&self) -> BankedAddress {
+        BankedAddress { bank: self.bank, address: self.address }
+    }
+
     /// Convert instruction to fixed length string of hex values (opcode + operand, if applicable).
     ///
     /// # Examples
@@ -251,8 +273,8 @@ impl Instruction {
         };
         
         let operand_hex = match self.addr_mode {
-            Implied     => format!("      "),
-            Accumulator => format!("      "),
+            Implied     => String::from("      "),
+            Accumulator => String::from("      "),
             Immediate   => format!(" {:02X}   ", oper_lo),
             Absolute    => format!(" {:02X} {:02X}", oper_lo, oper_hi),
             AbsoluteIndexedX(_) => format!(" {:02X} {:02X}", oper_lo, oper_hi),
@@ -288,33 +310,94 @@ impl Instruction {
     /// println!("{}", instruction.as_str());
     /// ```
     pub fn as_str(&self) -> String {
-        let operand = if let Some(v) = self.operand { v } else { 0 };
-        
+        self.format(None)
+    }
+
+    /// Convert instruction to assembler mnemonic with the operand replaced by a label or any other
+    /// text (immediate operands keep the `#` prefix, indexed modes keep the index).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    ///
+    /// let memory = vec![0x20, 0x03, 0x10];
+    /// let instruction = disasm6502::instruction::decode(0x1000, &mut 0, &memory);
+    ///
+    /// assert_eq!(instruction.as_str_with_label("PLAY"), "JSR PLAY");
+    /// ```
+    pub fn as_str_with_label(&self, label: &str) -> String {
+        self.format(Some(label))
+    }
+
+    /// Address referenced by the operand (branch target for relative mode), None for implied,
+    /// accumulator and immediate modes.
+    pub fn operand_address(&self) -> Option<u16> {
+        match self.addr_mode {
+            Implied | Accumulator | Immediate => None,
+            Relative => Some(self.address
+                // Add 2 for the next PC value
+                .wrapping_add(2)
+                // Add the sign-extended offset
+                .wrapping_add(self.operand.unwrap_or(0) as i8 as u16)),
+            _ => Some(self.operand.unwrap_or(0))
+        }
+    }
+
+    fn format(&self, label: Option<&str>) -> String {
+        let operand = self.operand.unwrap_or(0);
+        let address = |width: usize| match label {
+            Some(l) => l.to_string(),
+            None => format!("${:01$X}", self.operand_address().unwrap_or(0), width)
+        };
+
         let operand_str = match self.addr_mode {
-            Implied     => format!(""),
-            Accumulator => format!("A"),
-            Immediate   => format!("#${:02X}", operand),
-            Absolute    => format!("${:04X}", operand),
-            AbsoluteIndexedX(_) => format!("${:04X},X", operand),
-            AbsoluteIndexedY(_) => format!("${:04X},Y", operand),
-            Zeropage => format!("${:02X}", operand),
-            ZeropageIndexedX => format!("${:02X},X", operand),
-            ZeropageIndexedY => format!("${:02X},Y", operand),
-            Relative => format!(
-                "${:04X}",
-                self.address
-                    // Add 2 for the next PC value
-                    .wrapping_add(2)
-                    // Add the sign-extended offset
-                    .wrapping_add(operand as i8 as u16)
-            ),
-            Indirect => format!("(${:04X})", operand),
-            IndexedIndirectX    => format!("(${:02X},X)", operand),
-            IndirectIndexedY(_) => format!("(${:02X}),Y", operand)
+            Implied     => String::new(),
+            Accumulator => String::from("A"),
+            Immediate   => match label {
+                Some(l) => format!("#{}", l),
+                None => format!("#${:02X}", operand)
+            },
+            Absolute    => address(4),
+            AbsoluteIndexedX(_) => format!("{},X", address(4)),
+            AbsoluteIndexedY(_) => format!("{},Y", address(4)),
+            Zeropage => address(2),
+            ZeropageIndexedX => format!("{},X", address(2)),
+            ZeropageIndexedY => format!("{},Y", address(2)),
+            Relative => address(4),
+            Indirect => format!("({})", address(4)),
+            IndexedIndirectX    => format!("({},X)", address(2)),
+            IndirectIndexedY(_) => format!("({}),Y", address(2))
         };
 
         format!("{} {}", self.opcode, operand_str)
     }
+
+    /// Size of the instruction in bytes (opcode + operand, if applicable).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    ///
+    /// let memory = vec![0x05, 0x0B, 0x6C, 0x01, 0x02];
+    ///
+    /// // set program counter to 0 - will decode first instruction
+    /// let mut pc: usize = 0;
+    ///
+    /// // interprets 0x05 as an instruction, places it at $0800
+    /// let instruction = disasm6502::instruction::decode(0x0800, &mut pc, &memory);
+    ///
+    /// // ORA $0B occupies two bytes
+    /// assert_eq!(instruction.size(), 2);
+    /// ```
+    pub fn size(&self) -> u16 {
+        match self.addr_mode {
+            Implied | Accumulator => 1,
+            Absolute | AbsoluteIndexedX(_) | AbsoluteIndexedY(_) | Indirect => 3,
+            _ => 2
+        }
+    }
 }
 
 // read a byte and return it as u16 for convenience (0x0000 if can't fetch)
@@ -329,7 +412,7 @@ fn read_byte(index: usize, buffer: &[u8]) -> u16 {
 
 // read word: Little Endian (0x0000 if can't fetch)
 fn read_word_le(index: &mut usize, buffer: &[u8]) -> u16 {
-    let value_be = (read_byte(*index, buffer) << 8 & 0xFF00) | (read_byte((*index + 0x0001), buffer) & 0x00FF);
+    let value_be = (read_byte(*index, buffer) << 8 & 0xFF00) | (read_byte(*index + 0x0001, buffer) & 0x00FF);
     *index += 1;
 
     ((value_be << 8) & 0xFF00) | ((value_be >> 8) & 0x00FF)
@@ -355,7 +438,7 @@ fn fetch_operand(addr_mode: &AddrMode, index: &mut usize, buffer: &[u8]) -> (Opt
     };
 
     // move the buffer index past fetched operand (if it exists!)
-    if let Some(_) = operand {
+    if operand.is_some() {
         *index += 1;
     }
 
@@ -395,7 +478,7 @@ fn fetch(opcode: OpCode, num_cycles: u8, addr_mode: AddrMode, data: (u16, &mut u
     instruction.registers_written = reg_written;
     instruction.affected_flags = affected_flags;
 
-    if let Some(_) = ILLEGAL_OPS.into_iter().filter(|&&illegal| op_hex == illegal).next() {
+    if ILLEGAL_OPS.contains(&op_hex) {
         instruction.illegal = true;
     }
 
@@ -606,7 +689,7 @@ pub fn decode(address: u16, index: &mut usize, memory: &[u8]) -> Instruction {
         /* RLA_abx */ 0x3F => fetch(RLA(op), 7, AbsoluteIndexedX(false), data, sv![A,X], sv![A]),
         /* HLT     */ 0x42 => fetch(HLT(op), 1, Implied, data, None, None),
         /* SRE_izx */ 0x43 => fetch(SRE(op), 8, IndexedIndirectX, data, sv![A,X], sv![A]),
-        /* NOP     */ 0x44 => fetch(NOP(op), 3, Implied, data, None, None),
+        /* NOP_zp  */ 0x44 => fetch(NOP(op), 3, Zeropage, data, None, None),
         /* SRE_zp  */ 0x47 => fetch(SRE(op), 5, Zeropage, data, sv![A], sv![A]),
         /* ALR_imm */ 0x4B => fetch(ALR(op), 2, Immediate, data, None, None),
         /* SRE_abs */ 0x4F => fetch(SRE(op), 6, Absolute, data, sv![A], sv![A]),
@@ -622,7 +705,7 @@ pub fn decode(address: u16, index: &mut usize, memory: &[u8]) -> Instruction {
         /* RRA_izx */ 0x63 => fetch(RRA(op), 8, IndexedIndirectX, data, sv![A,X], sv![A]),
         /* NOP_zp  */ 0x64 => fetch(NOP(op), 3, Zeropage, data, None, None),
         /* RRA_zp  */ 0x67 => fetch(RRA(op), 5, Zeropage, data, sv![A], sv![A]),
-        /* ARR     */ 0x6B => fetch(ARR(op), 2, Implied, data, None, None),
+        /* ARR_imm */ 0x6B => fetch(ARR(op), 2, Immediate, data, sv![A], sv![A]),
         /* RRA_abs */ 0x6F => fetch(RRA(op), 6, Absolute, data, sv![A], sv![A]),
         /* HLT     */ 0x72 => fetch(HLT(op), 1, Implied, data, None, None),
         /* RRA_izy */ 0x73 => fetch(RRA(op), 8, IndirectIndexedY(false), data, sv![A,Y], sv![A]),
@@ -680,7 +763,6 @@ pub fn decode(address: u16, index: &mut usize, memory: &[u8]) -> Instruction {
         /* NOP     */ 0xFA => fetch(NOP(op), 2, Implied, data, None, None),
         /* ISC_aby */ 0xFB => fetch(ISC(op), 7, AbsoluteIndexedY(false), data, sv![A,Y], sv![A]),
         /* NOP_abx */ 0xFC => fetch(NOP(op), 5, AbsoluteIndexedX(true), data, sv![X], None), // add 1 cycle if page boundary is crossed
-        /* ISC_abx */ 0xFF => fetch(ISC(op), 7, AbsoluteIndexedX(false), data, sv![A,X], sv![A]),
-                         _ => fetch(NOP(op), 0, Implied, data, None, None)
+        /* ISC_abx */ 0xFF => fetch(ISC(op), 7, AbsoluteIndexedX(false), data, sv![A,X], sv![A])
     }
 }
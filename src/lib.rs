@@ -1,6 +1,6 @@
 //! [Disasm6502](https://github.com/kondrak/disasm6502) - a 6502 disassembler crate.
 //!
-//! A crate providing functionality to disassemble 6502 binary code. Supports decoding of forbidden instructions, provides information about cycle count, which registers the instruction accesses and which status flags are affected. Acceptable data input can be either an array of bytes, a vector of bytes or a binary file.
+//! A crate providing functionality to disassemble 6502 binary code. Supports decoding of forbidden instructions, provides information about cycle count, which registers the instruction accesses and which status flags are affected. Acceptable data input can be either an array of bytes, a vector of bytes, a binary file, a Commodore PRG file or a memory image made of multiple segments (as loaded from tape, disk, cartridge, ROM and hex files).
 //!
 //!# Quick Start
 //!
@@ -25,11 +25,42 @@
 //!    }
 //!}
 //!```
+pub mod analysis;
+pub mod apple2;
+pub mod banking;
+pub mod basic;
+pub mod bus_cycle;
+pub mod crt;
+pub mod d64;
+pub mod dfs;
+pub mod emulator;
 pub mod error;
+mod fields;
+pub mod hex_file;
+pub mod ines;
 pub mod instruction;
+pub mod listing;
+pub mod memory_image;
+pub mod nsf;
+pub mod operand;
+pub mod platform;
+pub mod pointers;
+pub mod prg;
+pub mod project;
+pub mod scanline;
+pub mod segment;
+pub mod sid;
+pub mod symbol_file;
+pub mod symbols;
+pub mod t64;
+pub mod text;
+pub mod trace;
+pub mod xex;
 
 use error::Result;
+use d64::D64;
 use instruction::Instruction;
+use prg::Prg;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
@@ -60,13 +91,103 @@ pub fn from_file(filename: &str) -> Result<Vec<Instruction>> {
 /// ```
 pub fn from_addr_file(filename: &str, start_address: u16) -> Result<Vec<Instruction>> {
     let path = Path::new(&filename);
-    let mut file = try!(File::open(&path));
+    let mut file = File::open(path)?;
     let mut bytes = Vec::new();
-    try!(file.read_to_end(&mut bytes));
+    file.read_to_end(&mut bytes)?;
 
     from_addr_array(&bytes, start_address)
 }
 
+/// Disassembles Commodore PRG file using the load address from its header.
+///
+/// If the file starts with a BASIC stub (e.g. `10 SYS 2061`), disassembly starts at the `SYS` address.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// // file loads at $0801, first instruction will be located at $080D
+/// let instructions = disasm6502::from_prg_file("examples/data.prg").unwrap();
+/// ```
+pub fn from_prg_file(filename: &str) -> Result<Vec<Instruction>> {
+    let path = Path::new(&filename);
+    let mut file = File::open(path)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    from_prg_array(&bytes)
+}
+
+/// Disassembles Commodore PRG file contents using the load address from its header.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// let bytes = vec![0x00, 0xC0, 0xA9, 0x00, 0x60];
+///
+/// // first instruction will be located at $C000
+/// let instructions = disasm6502::from_prg_array(&bytes).unwrap();
+/// ```
+pub fn from_prg_array(bytes: &[u8]) -> Result<Vec<Instruction>> {
+    let prg = Prg::parse(bytes)?;
+    let (start_address, code) = prg.code();
+
+    from_addr_array(code, start_address)
+}
+
+/// Lists BASIC program at the start of PRG file contents and disassembles machine code following it.
+///
+/// Disassembly starts right after the end-of-program marker.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// let bytes = vec![0x01, 0x08, 0x0B, 0x08, 0x0A, 0x00, 0x9E, 0x32, 0x30, 0x36, 0x31, 0x00, 0x00, 0x00, 0x60];
+/// let (program, instructions) = disasm6502::from_basic_prg_array(&bytes).unwrap();
+///
+/// // prints "10 SYS2061" followed by "$080D: 60       RTS "
+/// for line in program.lines.iter() {
+///     println!("{}", line);
+/// }
+/// for i in instructions.iter() {
+///     println!("{}", i);
+/// }
+/// ```
+pub fn from_basic_prg_array(bytes: &[u8]) -> Result<(basic::Program, Vec<Instruction>)> {
+    let prg = Prg::parse(bytes)?;
+    let program = basic::detokenize(prg.load_address, &prg.data);
+    let offset = program.end_address.wrapping_sub(prg.load_address) as usize;
+    let instructions = from_addr_array(&prg.data[offset.min(prg.data.len())..], program.end_address)?;
+
+    Ok((program, instructions))
+}
+
+/// Disassembles PRG file stored on a 1541 disk image (D64).
+///
+/// The file is looked up by name in the directory and disassembled as by `from_prg_array`.
+///
+/// # Examples
+///
+/// ```no_run
+/// extern crate disasm6502;
+///
+/// let instructions = disasm6502::from_d64_file("game.d64", "GAME").unwrap();
+/// ```
+pub fn from_d64_file(filename: &str, name: &str) -> Result<Vec<Instruction>> {
+    let path = Path::new(&filename);
+    let mut file = File::open(path)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    let disk = D64::parse(&bytes)?;
+    from_prg_array(&disk.read_file_by_name(name)?.data)
+}
+
 /// Disassembles data from array of bytes using $0000 as start address.
 ///
 /// # Examples
@@ -101,10 +222,67 @@ pub fn from_addr_array(bytes: &[u8], start_address: u16) -> Result<Vec<Instructi
     let mut next_addr = start_address;
 
     while index < bytes.len() {
-        let instruction = instruction::decode(next_addr, &mut index, &bytes);
+        let instruction = instruction::decode(next_addr, &mut index, bytes);
         ret.push(instruction);
         next_addr = start_address.wrapping_add(index as u16);
     }
 
     Ok(ret)
 }
+
+/// Disassembles each segment at its own address, in order.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::segment::Segment;
+///
+/// let segments = vec![Segment::new(0x2000, vec![0xA9, 0x00]), Segment::new(0x3000, vec![0x60])];
+///
+/// let instructions = disasm6502::from_segments(&segments).unwrap();
+/// assert_eq!(instructions[1].address, 0x3000);
+/// ```
+pub fn from_segments(segments: &[segment::Segment]) -> Result<Vec<Instruction>> {
+    from_memory_image(&memory_image::MemoryImage::from(segments.to_vec()))
+}
+
+/// Disassembles all segments of a memory image, in order. Instructions at the end of a segment
+/// take their operands from the adjacent segment (if there is one).
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::memory_image::MemoryImage;
+/// use disasm6502::segment::Segment;
+///
+/// let mut image = MemoryImage::new();
+/// image.add_segment(Segment::new(0x2000, vec![0xA9]));
+/// image.add_segment(Segment::new(0x2001, vec![0x00, 0x60]));
+///
+/// let instructions = disasm6502::from_memory_image(&image).unwrap();
+/// assert_eq!(instructions[0].as_str(), "LDA #$00");
+/// assert_eq!(instructions[1].address, 0x2002);
+/// ```
+pub fn from_memory_image(image: &memory_image::MemoryImage) -> Result<Vec<Instruction>> {
+    let mut ret = Vec::<Instruction>::new();
+
+    image.walk(|segment, at| {
+        // incomplete instructions at the end of the image are decoded with missing bytes as zeros
+        let instruction = image.decode_in(segment, at.address).unwrap_or_else(|| {
+            let bytes: Vec<u8> = (0..3u16)
+                .map(|i| at.address.wrapping_add(i))
+                .map_while(|a| segment.read(a).or_else(|| image.read_banked(banking::BankedAddress { bank: at.bank, address: a })))
+                .collect();
+            let mut instruction = instruction::decode(at.address, &mut 0, &bytes);
+            instruction.bank = at.bank;
+            instruction
+        });
+        let size = instruction.size();
+        ret.push(instruction);
+        size
+    });
+
+    Ok(ret)
+}
@@ -0,0 +1,142 @@
+//! Assembler-style listing of disassembled lines with labels.
+//!
+//! Text strings are preceded by a 64tass `.ENC` line whenever the encoding changes. ASCII and
+//! screen codes use the built-in `none` and `screen` encodings; other encodings are defined with
+//! `.CDEF` lines the first time they are selected. Strings whose characters would not assemble
+//! back to the same bytes are listed as `.BYTE` lines with the string in a comment.
+use std::collections::BTreeMap;
+use analysis::Line;
+use banking::BankedAddress;
+use operand::OperandFormat;
+use symbols::SymbolTable;
+use text::Encoding;
+
+/// User annotations applied to a listing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotations {
+    /// comments appended to the line of an address
+    pub comments: BTreeMap<BankedAddress, String>,
+    /// comments placed before an address (and its label), may span multiple lines
+    pub block_comments: BTreeMap<BankedAddress, String>,
+    /// operand formats of instructions, replacing labels of operand addresses
+    pub operand_formats: BTreeMap<BankedAddress, OperandFormat>
+}
+
+/// Format disassembly lines, adding a label line before each labelled address and
+/// replacing operand addresses with labels. Addresses in banked memory are prefixed with the bank.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::analysis;
+/// use disasm6502::symbols::SymbolTable;
+///
+/// // $1000: JMP $1000
+/// let bytes = vec![0x4C, 0x00, 0x10];
+/// let map = analysis::analyze(&bytes, 0x1000, &[0x1000], &[]);
+///
+/// let mut symbols = SymbolTable::new();
+/// symbols.insert(0x1000, "loop");
+///
+/// let listing = disasm6502::listing::format(&analysis::disassemble(&bytes, 0x1000, &map), &symbols);
+/// assert_eq!(listing, vec!["loop:", "$1000: 4C 00 10 JMP loop"]);
+/// ```
+pub fn format(lines: &[Line], symbols: &SymbolTable) -> Vec<String> {
+    format_annotated(lines, symbols, &Annotations::default())
+}
+
+/// Format disassembly lines like `format`, adding comments and applying operand formats.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::analysis;
+/// use disasm6502::banking::BankedAddress;
+/// use disasm6502::listing::{self, Annotations};
+/// use disasm6502::operand::OperandFormat;
+/// use disasm6502::symbols::SymbolTable;
+///
+/// // $1000: LDA #$41 / RTS
+/// let bytes = vec![0xA9, 0x41, 0x60];
+/// let map = analysis::analyze(&bytes, 0x1000, &[0x1000], &[]);
+///
+/// let mut annotations = Annotations::default();
+/// annotations.block_comments.insert(BankedAddress::unbanked(0x1000), String::from("print A"));
+/// annotations.comments.insert(BankedAddress::unbanked(0x1000), String::from("letter"));
+/// annotations.operand_formats.insert(BankedAddress::unbanked(0x1000), OperandFormat::Char);
+///
+/// let lines = analysis::disassemble(&bytes, 0x1000, &map);
+/// assert_eq!(listing::format_annotated(&lines, &SymbolTable::new(), &annotations),
+///            vec!["; print A", "$1000: A9 41    LDA #'A' ; letter", "$1002: 60       RTS "]);
+/// ```
+pub fn format_annotated(lines: &[Line], symbols: &SymbolTable, annotations: &Annotations) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut encoding = Encoding::Ascii;
+    let mut defined = Vec::new();
+
+    for line in lines.iter() {
+        let address = match *line {
+            Line::Code(ref instruction, _) | Line::Overlapping(ref instruction, _) => instruction.banked_address(),
+            Line::Data(address, _) => address,
+            Line::Text(ref text) => text.start
+        };
+
+        if let Some(comment) = annotations.block_comments.get(&address) {
+            result.extend(comment.lines().map(|l| format!("; {}", l)));
+        }
+
+        if let Some(label) = symbols.get_banked(address) {
+            result.push(format!("{}:", label));
+        }
+
+        let text = match *line {
+            Line::Code(ref instruction, _) | Line::Overlapping(ref instruction, _) => {
+                let mnemonic = match annotations.operand_formats.get(&address) {
+                    Some(format) => format.format_instruction(instruction),
+                    None => symbols.format_instruction(instruction)
+                };
+                let overlapping = if let Line::Overlapping(..) = *line { " ; overlapping" } else { "" };
+                format!("{}: {} {}{}", address, instruction.as_hex_str(), mnemonic, overlapping)
+            },
+            Line::Text(ref text) if !text.round_trips() => {
+                let hex: Vec<String> = text.bytes.iter().take(3).map(|b| format!("{:02X}", b)).collect();
+                let bytes: Vec<String> = text.bytes.iter().map(|b| format!("${:02X}", b)).collect();
+                format!("{}: {:<8} .BYTE {} ; {}", text.start, hex.join(" "), bytes.join(", "), text.directive())
+            },
+            Line::Text(ref text) if text.encoding != encoding => {
+                encoding = text.encoding;
+                result.extend(select_encoding(encoding, &mut defined));
+                format!("{}", line)
+            },
+            Line::Data(..) | Line::Text(_) => format!("{}", line)
+        };
+
+        match annotations.comments.get(&address) {
+            Some(comment) => result.push(format!("{} ; {}", text, comment.replace('\n', " "))),
+            None => result.push(text)
+        }
+    }
+
+    result
+}
+
+// 64tass directives switching to an encoding, with its character table the first time
+fn select_encoding(encoding: Encoding, defined: &mut Vec<Encoding>) -> Vec<String> {
+    let name = match encoding {
+        Encoding::Ascii => "none",
+        Encoding::ScreenCode => "screen",
+        _ => encoding.name()
+    };
+    let mut result = vec![format!("        .ENC \"{}\"", name)];
+
+    if name == encoding.name() && !defined.contains(&encoding) {
+        defined.push(encoding);
+        let quote = |c: char| if c == '"' { String::from("\"\"") } else { c.to_string() };
+        result.extend(encoding.ranges().iter()
+            .map(|&(first, last, byte)| format!("        .CDEF \"{}{}\", ${:02X}", quote(first), quote(last), byte)));
+    }
+
+    result
+}
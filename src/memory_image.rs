@@ -0,0 +1,181 @@
+//! Memory image made of multiple, possibly non-contiguous segments.
+//!
+//! Most file formats place data at several places in memory (or in several banks). The image
+//! keeps segments in the order they were added and resolves addresses against all of them, so
+//! instructions and references crossing segment boundaries are decoded as they would be in
+//! memory. Segments loaded over each other (overlays) are all walked from their own start, but
+//! lookups by address find the first one added unless a bank is given, so overlays should be
+//! kept apart in banks of their own (see `MemoryImage::with_overlays`).
+use banking::BankedAddress;
+use instruction::{self, Instruction};
+use segment::Segment;
+
+/// Set of memory segments.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryImage {
+    segments: Vec<Segment>
+}
+
+impl MemoryImage {
+    /// Create an empty image.
+    pub fn new() -> MemoryImage {
+        MemoryImage { segments: Vec::new() }
+    }
+
+    /// Create an image of segments without banks in which segments overlapping another one are
+    /// kept apart: each of them is placed in a bank numbered with its index. Segments which do
+    /// not overlap any other stay unbanked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::banking::BankedAddress;
+    /// use disasm6502::memory_image::MemoryImage;
+    /// use disasm6502::segment::Segment;
+    ///
+    /// // LDA #$01 loaded at $2000, then LDX #$02 loaded over it
+    /// let image = MemoryImage::with_overlays(vec![Segment::new(0x2000, vec![0xA9, 0x01]),
+    ///                                             Segment::new(0x3000, vec![0x60]),
+    ///                                             Segment::new(0x2000, vec![0xA2, 0x02])]);
+    ///
+    /// assert_eq!(image.decode_banked(BankedAddress::new(0, 0x2000)).unwrap().as_str(), "LDA #$01");
+    /// assert_eq!(image.decode_banked(BankedAddress::new(2, 0x2000)).unwrap().as_str(), "LDX #$02");
+    /// assert_eq!(image.segments()[1].bank, None);
+    /// ```
+    pub fn with_overlays(segments: Vec<Segment>) -> MemoryImage {
+        let overlaps = |a: &Segment, b: &Segment| {
+            let (start_a, start_b) = (a.address as u32, b.address as u32);
+            start_a < start_b + b.data.len() as u32 && start_b < start_a + a.data.len() as u32
+        };
+
+        let banks: Vec<Option<u16>> = segments.iter().enumerate().map(|(i, s)| {
+            let overlay = segments.iter().enumerate().any(|(j, other)| i != j && other.bank.is_none() && overlaps(s, other));
+            if s.bank.is_none() && overlay { Some(i as u16) } else { s.bank }
+        }).collect();
+
+        MemoryImage::from(segments.into_iter().zip(banks).map(|(s, bank)| Segment { bank, ..s }).collect::<Vec<Segment>>())
+    }
+
+    /// Add a segment to the image.
+    pub fn add_segment(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// Segments in the order they were added.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Segment containing the address.
+    pub fn segment_at(&self, address: u16) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.contains(address))
+    }
+
+    /// All segments containing the address, in the order they were added.
+    pub fn segments_containing(&self, address: u16) -> Vec<&Segment> {
+        self.segments.iter().filter(|s| s.contains(address)).collect()
+    }
+
+    /// Segment containing the address in the given bank, or an unbanked segment containing it.
+    /// Addresses without a bank are looked up like in `segment_at`.
+    pub fn segment_in(&self, at: BankedAddress) -> Option<&Segment> {
+        match at.bank {
+            Some(bank) => self.segments.iter()
+                .find(|s| s.bank == Some(bank) && s.contains(at.address))
+                .or_else(|| self.segments.iter().find(|s| s.bank.is_none() && s.contains(at.address))),
+            None => self.segment_at(at.address)
+        }
+    }
+
+    /// Check if the address is backed by any segment.
+    pub fn contains(&self, address: u16) -> bool {
+        self.segment_at(address).is_some()
+    }
+
+    /// Byte at the address (None if not backed by any segment).
+    pub fn read(&self, address: u16) -> Option<u8> {
+        self.segment_at(address).and_then(|s| s.read(address))
+    }
+
+    /// Byte at the bank-qualified address.
+    pub fn read_banked(&self, at: BankedAddress) -> Option<u8> {
+        self.segment_in(at).and_then(|s| s.read(at.address))
+    }
+
+    /// Little-endian word at the address (None if any byte is not backed by a segment).
+    pub fn read_word(&self, address: u16) -> Option<u16> {
+        Some(self.read(address)? as u16 | (self.read(address.wrapping_add(1))? as u16) << 8)
+    }
+
+    /// Decode the instruction at the address, with operand bytes taken from whichever segment
+    /// holds them. None if the opcode or any operand byte is not backed by a segment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::memory_image::MemoryImage;
+    /// use disasm6502::segment::Segment;
+    ///
+    /// // JMP $1234 split across two segments
+    /// let image = MemoryImage::from(vec![Segment::new(0x1000, vec![0x4C, 0x34]), Segment::new(0x1002, vec![0x12])]);
+    ///
+    /// assert_eq!(image.decode(0x1000).unwrap().as_str(), "JMP $1234");
+    /// // nothing mapped at $1003
+    /// assert!(image.decode(0x1003).is_none());
+    /// ```
+    pub fn decode(&self, address: u16) -> Option<Instruction> {
+        self.decode_banked(BankedAddress::unbanked(address))
+    }
+
+    /// Decode the instruction at the bank-qualified address, with operand bytes taken from the
+    /// same bank (or unbanked memory). The instruction is tagged with the bank it was found in.
+    pub fn decode_banked(&self, at: BankedAddress) -> Option<Instruction> {
+        self.decode_in(self.segment_in(at)?, at.address)
+    }
+
+    /// Decode the instruction at the address from the bytes of the segment, with operand bytes
+    /// past its end taken from the same bank (or unbanked memory).
+    pub(crate) fn decode_in(&self, segment: &Segment, address: u16) -> Option<Instruction> {
+        let bytes: Vec<u8> = (0..3u16)
+            .map(|i| address.wrapping_add(i))
+            .map_while(|a| segment.read(a).or_else(|| self.read_banked(BankedAddress { bank: segment.bank, address: a })))
+            .collect();
+
+        let mut instruction = instruction::decode(address, &mut 0, &bytes);
+        instruction.bank = segment.bank;
+        if instruction.size() as usize > bytes.len() { None } else { Some(instruction) }
+    }
+
+    /// Walk all segments in order, calling the closure with each segment and (bank-qualified)
+    /// address in it not yet covered by a previously visited item. The closure returns the number of bytes the item at
+    /// the address occupies; items may extend into the following segment of the same bank if it
+    /// starts right after the previous one. Overlapping segments are walked from their own start.
+    pub(crate) fn walk<F: FnMut(&Segment, BankedAddress) -> u16>(&self, mut visit: F) {
+        // bank, end of the previous segment and end of its last item
+        let mut next: Option<(Option<u16>, u32, u32)> = None;
+
+        for segment in self.segments.iter() {
+            let start = segment.address as u32;
+            let end = start + segment.data.len() as u32;
+
+            // skip bytes already consumed by an item starting in the adjacent previous segment
+            let mut address = match next {
+                Some((bank, previous_end, n)) if bank == segment.bank && previous_end == start && n > start && n <= end => n,
+                _ => start
+            };
+
+            while address < end {
+                address += visit(segment, BankedAddress { bank: segment.bank, address: address as u16 }).max(1) as u32;
+            }
+            next = Some((segment.bank, end, address));
+        }
+    }
+}
+
+impl From<Vec<Segment>> for MemoryImage {
+    fn from(segments: Vec<Segment>) -> MemoryImage {
+        MemoryImage { segments }
+    }
+}
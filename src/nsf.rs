@@ -0,0 +1,148 @@
+//! NES Sound Format (NSF) music file loader.
+//!
+//! NSF files consist of a 128 byte header followed by the sound driver and music data. The
+//! player calls the INIT routine once per song and the PLAY routine at the playback rate.
+//! Bankswitched files are split into 4K banks; the header holds the banks initially mapped
+//! into $8000-$FFFF, with the first bank padded so that the data starts at the load address.
+use analysis;
+use banking::{BankedAddress, BankLayout};
+use error::{Disasm6502Error, Result};
+use fields::{text, word};
+use listing;
+use memory_image::MemoryImage;
+use segment::Segment;
+use symbols::SymbolTable;
+
+/// Size of a bankswitched bank.
+pub const BANK_SIZE: usize = 0x1000;
+
+/// Label of the INIT routine.
+pub const INIT_LABEL: &str = "INIT";
+
+/// Label of the PLAY routine.
+pub const PLAY_LABEL: &str = "PLAY";
+
+const SIGNATURE: &[u8] = b"NESM\x1A";
+const HEADER_SIZE: usize = 0x80;
+
+// banks are mapped into $8000-$FFFF
+const BANKED_START: u16 = 0x8000;
+
+/// Contents of an NSF file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Nsf {
+    /// format version
+    pub version: u8,
+    /// number of songs
+    pub songs: u8,
+    /// first song to play (starting at 1)
+    pub start_song: u8,
+    /// address the data is loaded to
+    pub load_address: u16,
+    /// address of the INIT routine
+    pub init_address: u16,
+    /// address of the PLAY routine
+    pub play_address: u16,
+    /// song name
+    pub name: String,
+    /// artist name
+    pub artist: String,
+    /// copyright holder
+    pub copyright: String,
+    /// NTSC play rate in microseconds per call
+    pub ntsc_speed: u16,
+    /// PAL play rate in microseconds per call
+    pub pal_speed: u16,
+    /// initial banks for $8000-$FFFF (None if the file is not bankswitched)
+    pub bankswitch: Option<[u8; 8]>,
+    /// expansion sound chips bit mask
+    pub sound_chips: u8,
+    /// program data following the header
+    pub data: Vec<u8>
+}
+
+impl Nsf {
+    /// Parse contents of an NSF file.
+    pub fn parse(bytes: &[u8]) -> Result<Nsf> {
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(SIGNATURE) {
+            return Err(Disasm6502Error::InvalidFormat(String::from("missing NSF signature")));
+        }
+
+        let mut bankswitch = [0; 8];
+        bankswitch.copy_from_slice(&bytes[0x70..0x78]);
+
+        // NSF2 files may be followed by metadata, program length of 0 means the rest of the file
+        let length = bytes[0x7D] as usize | (bytes[0x7E] as usize) << 8 | (bytes[0x7F] as usize) << 16;
+        let end = if bytes[0x05] >= 2 && length > 0 { (HEADER_SIZE + length).min(bytes.len()) } else { bytes.len() };
+
+        Ok(Nsf {
+            version: bytes[0x05],
+            songs: bytes[0x06],
+            start_song: bytes[0x07],
+            load_address: word(bytes, 0x08),
+            init_address: word(bytes, 0x0A),
+            play_address: word(bytes, 0x0C),
+            name: text(&bytes[0x0E..0x2E]),
+            artist: text(&bytes[0x2E..0x4E]),
+            copyright: text(&bytes[0x4E..0x6E]),
+            ntsc_speed: word(bytes, 0x6E),
+            pal_speed: word(bytes, 0x78),
+            bankswitch: if bankswitch.iter().any(|&b| b != 0) { Some(bankswitch) } else { None },
+            sound_chips: bytes[0x7B],
+            data: bytes[HEADER_SIZE..end].to_vec()
+        })
+    }
+
+    /// Memory contents after loading: the data at the load address, or the initial banks mapped
+    /// into $8000-$FFFF for bankswitched files.
+    pub fn segments(&self) -> Vec<Segment> {
+        let table = match self.bankswitch {
+            Some(table) => table,
+            None => return vec![Segment::new(self.load_address, self.data.clone())]
+        };
+
+        let mut padded = vec![0; self.load_address as usize & (BANK_SIZE - 1)];
+        padded.extend_from_slice(&self.data);
+
+        table.iter().enumerate().map(|(slot, &bank)| {
+            let mut data = padded.iter().cloned().skip(bank as usize * BANK_SIZE).take(BANK_SIZE).collect::<Vec<u8>>();
+            data.resize(BANK_SIZE, 0);
+            Segment::banked(bank as u16, BANKED_START + (slot * BANK_SIZE) as u16, data)
+        }).collect()
+    }
+
+    /// Windows of the 4K slots in $8000-$FFFF, each with the bank initially mapped into it
+    /// (no windows if the file is not bankswitched).
+    pub fn layout(&self) -> BankLayout {
+        let mut layout = BankLayout::new();
+        if let Some(table) = self.bankswitch {
+            for (slot, &bank) in table.iter().enumerate() {
+                let start = BANKED_START + (slot * BANK_SIZE) as u16;
+                layout.add_window(start, start + (BANK_SIZE - 1) as u16, &[bank as u16]);
+            }
+        }
+        layout
+    }
+
+    /// INIT and PLAY routine addresses.
+    pub fn entry_points(&self) -> Vec<u16> {
+        vec![self.init_address, self.play_address]
+    }
+
+    /// Labels of the INIT and PLAY routines.
+    pub fn symbols(&self) -> SymbolTable {
+        let mut symbols = SymbolTable::new();
+        symbols.insert(self.init_address, INIT_LABEL);
+        symbols.insert(self.play_address, PLAY_LABEL);
+        symbols
+    }
+
+    /// Disassemble the sound driver starting from the INIT and PLAY routines, with code not
+    /// reachable from them listed as data.
+    pub fn listing(&self) -> Vec<String> {
+        let image = MemoryImage::from(self.segments());
+        let entry_points: Vec<BankedAddress> = self.entry_points().into_iter().map(BankedAddress::unbanked).collect();
+        let map = analysis::analyze_banked(&image, &self.layout(), &entry_points, &[]);
+        listing::format(&analysis::disassemble_image(&image, &map), &self.symbols())
+    }
+}
@@ -0,0 +1,144 @@
+//! Display formats for instruction operands.
+//!
+//! By default operands are shown in hex (or as the label of the operand address). A format
+//! override shows the operand value of a single instruction in decimal, binary, as a character
+//! (in ASCII or a platform encoding), as a symbol name or as an expression instead. The low and high byte forms (`#<table`,
+//! `#>table+1`) mark immediate values that are halves of an address, so reassembled code keeps
+//! working when the address moves.
+use std::fmt;
+use instruction::{AddrMode, Instruction};
+use text::Encoding;
+
+/// How the operand of an instruction is shown.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OperandFormat {
+    /// hexadecimal (`LDA #$41`)
+    Hex,
+    /// decimal (`LDA #65`)
+    Decimal,
+    /// binary (`LDA #%01000001`)
+    Binary,
+    /// ASCII character, hex if not printable (`LDA #'A'`)
+    Char,
+    /// character in the encoding, hex if not printable (`LDA #'A'` for PETSCII $C1 in lower case)
+    EncodedChar(Encoding),
+    /// symbol name (`CMP #SCREEN_WIDTH`)
+    Symbol(String),
+    /// low byte of an expression (`LDA #<table`)
+    LowByte(String),
+    /// high byte of an expression (`LDA #>table+1`)
+    HighByte(String),
+    /// any other expression, written as given (`LDA #COLS*2`)
+    Expression(String)
+}
+
+impl OperandFormat {
+    /// Parse a format name: `hex`, `decimal`, `binary`, `char`, `char:<encoding>`, `symbol:<name>`,
+    /// `low:<expression>`, `high:<expression>` or `expr:<expression>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::operand::OperandFormat;
+    ///
+    /// assert_eq!(OperandFormat::parse("high:table+1"), Some(OperandFormat::HighByte(String::from("table+1"))));
+    /// assert_eq!(OperandFormat::parse("low:"), None);
+    /// ```
+    pub fn parse(text: &str) -> Option<OperandFormat> {
+        let text = text.trim();
+        let argument = |prefix: &str| text.strip_prefix(prefix).map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+
+        match text {
+            "hex" => Some(OperandFormat::Hex),
+            "decimal" => Some(OperandFormat::Decimal),
+            "binary" => Some(OperandFormat::Binary),
+            "char" => Some(OperandFormat::Char),
+            _ => argument("char:").and_then(|e| Encoding::parse(&e)).map(OperandFormat::EncodedChar)
+                .or_else(|| argument("symbol:").map(OperandFormat::Symbol))
+                .or_else(|| argument("low:").map(OperandFormat::LowByte))
+                .or_else(|| argument("high:").map(OperandFormat::HighByte))
+                .or_else(|| argument("expr:").map(OperandFormat::Expression))
+        }
+    }
+
+    /// Low byte of the address of a label with an offset, e.g. `#<table+1`.
+    pub fn low_byte(label: &str, offset: i32) -> OperandFormat {
+        OperandFormat::LowByte(with_offset(label, offset))
+    }
+
+    /// High byte of the address of a label with an offset, e.g. `#>table+1`.
+    pub fn high_byte(label: &str, offset: i32) -> OperandFormat {
+        OperandFormat::HighByte(with_offset(label, offset))
+    }
+
+    /// Convert instruction to assembler mnemonic with the operand in this format. Instructions
+    /// without an operand are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::operand::OperandFormat;
+    ///
+    /// // LDA #$B0
+    /// let instruction = disasm6502::instruction::decode(0x0800, &mut 0, &[0xA9, 0xB0]);
+    ///
+    /// assert_eq!(OperandFormat::Binary.format_instruction(&instruction), "LDA #%10110000");
+    /// assert_eq!(OperandFormat::Decimal.format_instruction(&instruction), "LDA #176");
+    /// assert_eq!(OperandFormat::Symbol(String::from("MASK")).format_instruction(&instruction), "LDA #MASK");
+    /// assert_eq!(OperandFormat::high_byte("table", 1).format_instruction(&instruction), "LDA #>table+1");
+    /// assert_eq!(OperandFormat::parse("char:apple").unwrap().format_instruction(&instruction), "LDA #'0'");
+    /// ```
+    pub fn format_instruction(&self, instruction: &Instruction) -> String {
+        let (value, word) = match instruction.addr_mode {
+            AddrMode::Implied | AddrMode::Accumulator => return instruction.as_str(),
+            AddrMode::Immediate => (instruction.operand.unwrap_or(0) & 0xFF, false),
+            AddrMode::Zeropage | AddrMode::ZeropageIndexedX | AddrMode::ZeropageIndexedY |
+            AddrMode::IndexedIndirectX | AddrMode::IndirectIndexedY(_) => (instruction.operand_address().unwrap_or(0), false),
+            _ => (instruction.operand_address().unwrap_or(0), true)
+        };
+
+        match *self {
+            OperandFormat::Hex => instruction.as_str(),
+            OperandFormat::Decimal => instruction.as_str_with_label(&value.to_string()),
+            OperandFormat::Binary if word => instruction.as_str_with_label(&format!("%{:016b}", value)),
+            OperandFormat::Binary => instruction.as_str_with_label(&format!("%{:08b}", value)),
+            OperandFormat::Char if !word && (0x20..0x7F).contains(&value) && value != 0x27 => {
+                instruction.as_str_with_label(&format!("'{}'", value as u8 as char))
+            },
+            OperandFormat::EncodedChar(encoding) if !word => match encoding.decode(value as u8) {
+                Some(c) if c != '\'' => instruction.as_str_with_label(&format!("'{}'", c)),
+                _ => instruction.as_str()
+            },
+            OperandFormat::Char | OperandFormat::EncodedChar(_) => instruction.as_str(),
+            OperandFormat::Symbol(ref name) | OperandFormat::Expression(ref name) => instruction.as_str_with_label(name),
+            OperandFormat::LowByte(ref expression) => instruction.as_str_with_label(&format!("<{}", expression)),
+            OperandFormat::HighByte(ref expression) => instruction.as_str_with_label(&format!(">{}", expression))
+        }
+    }
+}
+
+fn with_offset(label: &str, offset: i32) -> String {
+    match offset {
+        0 => label.to_string(),
+        o if o > 0 => format!("{}+{}", label, o),
+        o => format!("{}{}", label, o)
+    }
+}
+
+impl fmt::Display for OperandFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OperandFormat::Hex => write!(f, "hex"),
+            OperandFormat::Decimal => write!(f, "decimal"),
+            OperandFormat::Binary => write!(f, "binary"),
+            OperandFormat::Char => write!(f, "char"),
+            OperandFormat::EncodedChar(encoding) => write!(f, "char:{}", encoding),
+            OperandFormat::Symbol(ref name) => write!(f, "symbol:{}", name),
+            OperandFormat::LowByte(ref expression) => write!(f, "low:{}", expression),
+            OperandFormat::HighByte(ref expression) => write!(f, "high:{}", expression),
+            OperandFormat::Expression(ref expression) => write!(f, "expr:{}", expression)
+        }
+    }
+}
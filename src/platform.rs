@@ -0,0 +1,542 @@
+//! Hardware register and operating system symbols of common 6502 platforms.
+//!
+//! Each platform provides a symbol table naming its I/O registers, ROM entry points and system
+//! vectors, with a short description of each. Chip registers are prefixed with the chip name
+//! (e.g. `VIC_BORDER`, `CIA1_PRA`), ROM routines use their customary names (e.g. `CHROUT`,
+//! `COUT`, `OSWRCH`). Registers with different meanings on reads and writes are named after
+//! the write register; the read register is given in the description. The Atari 2600 TIA,
+//! whose read and write registers share all addresses, names them by the instruction accessing
+//! them.
+use std::fmt;
+use scanline::TIA_WRITE_REGISTERS;
+use sid::{SID_BASE, SID_REGISTERS};
+use symbols::SymbolTable;
+use text::Encoding;
+use xex::{INITAD, RUNAD};
+
+/// Platform with a known memory map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// Commodore 64
+    C64,
+    /// Commodore VIC-20
+    Vic20,
+    /// Nintendo Entertainment System
+    Nes,
+    /// Atari 400/800/XL/XE
+    Atari8Bit,
+    /// Atari 2600 (VCS)
+    Atari2600,
+    /// Apple II
+    Apple2,
+    /// Acorn BBC Micro
+    Bbc
+}
+
+/// All supported platforms.
+pub const PLATFORMS: [Platform; 7] = [
+    Platform::C64, Platform::Vic20, Platform::Nes, Platform::Atari8Bit,
+    Platform::Atari2600, Platform::Apple2, Platform::Bbc
+];
+
+impl Platform {
+    /// Short lowercase name of the platform (as accepted by `parse`).
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Platform::C64 => "c64",
+            Platform::Vic20 => "vic20",
+            Platform::Nes => "nes",
+            Platform::Atari8Bit => "atari8bit",
+            Platform::Atari2600 => "atari2600",
+            Platform::Apple2 => "apple2",
+            Platform::Bbc => "bbc"
+        }
+    }
+
+    /// Find platform by its short name (case insensitive).
+    pub fn parse(name: &str) -> Option<Platform> {
+        PLATFORMS.iter().find(|p| p.name().eq_ignore_ascii_case(name.trim())).cloned()
+    }
+
+    /// Default character encoding of text.
+    pub fn encoding(&self) -> Encoding {
+        match *self {
+            Platform::C64 | Platform::Vic20 => Encoding::PetsciiUpper,
+            Platform::Atari8Bit => Encoding::Atascii,
+            Platform::Apple2 => Encoding::AppleHighBit,
+            Platform::Nes | Platform::Atari2600 | Platform::Bbc => Encoding::Ascii
+        }
+    }
+
+    /// Symbol table with register, entry point and vector names and descriptions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::platform::Platform;
+    ///
+    /// let symbols = Platform::C64.symbols();
+    /// assert_eq!(symbols.description(0xD020), Some("border color"));
+    ///
+    /// // $0800: STA $D020
+    /// let instruction = disasm6502::instruction::decode(0x0800, &mut 0, &[0x8D, 0x20, 0xD0]);
+    /// assert_eq!(symbols.format_instruction(&instruction), "STA VIC_BORDER");
+    /// ```
+    pub fn symbols(&self) -> SymbolTable {
+        let mut symbols = SymbolTable::new();
+        match *self {
+            Platform::C64 => {
+                add(&mut symbols, 0xD000, "VIC_", &VIC_II_REGISTERS);
+                add(&mut symbols, SID_BASE, "SID_", &SID_REGISTERS);
+                add(&mut symbols, 0xDC00, "CIA1_", &CIA_REGISTERS);
+                add(&mut symbols, 0xDD00, "CIA2_", &CIA_REGISTERS);
+                add_list(&mut symbols, &CBM_VECTORS);
+                add_list(&mut symbols, &KERNAL);
+            },
+            Platform::Vic20 => {
+                add(&mut symbols, 0x9000, "VIC_", &VIC_REGISTERS);
+                add(&mut symbols, 0x9110, "VIA1_", &VIA_REGISTERS);
+                add(&mut symbols, 0x9120, "VIA2_", &VIA_REGISTERS);
+                add_list(&mut symbols, &CBM_VECTORS);
+                add_list(&mut symbols, &KERNAL);
+            },
+            Platform::Nes => {
+                add(&mut symbols, 0x2000, "", &PPU_REGISTERS);
+                add_list(&mut symbols, &NES_IO);
+            },
+            Platform::Atari8Bit => {
+                add(&mut symbols, 0xD000, "", &GTIA_REGISTERS);
+                add(&mut symbols, 0xD200, "", &POKEY_REGISTERS);
+                add(&mut symbols, 0xD300, "", &PIA_REGISTERS);
+                add(&mut symbols, 0xD400, "", &ANTIC_REGISTERS);
+                add_list(&mut symbols, &ATARI_OS);
+                symbols.insert(RUNAD, "RUNAD");
+                symbols.describe(RUNAD, "run address of a binary file");
+                symbols.insert(INITAD, "INITAD");
+                symbols.describe(INITAD, "init address of a binary file segment");
+            },
+            Platform::Atari2600 => {
+                add(&mut symbols, 0x0000, "", &TIA_WRITE_REGISTERS);
+                // loads, compares and BIT see the read registers, also addressed through their $30 mirror
+                for (offset, &(name, _)) in TIA_READ_REGISTERS.iter().enumerate() {
+                    symbols.insert_read(offset as u16, name);
+                }
+                add(&mut symbols, 0x0030, "", &TIA_READ_REGISTERS);
+                add_list(&mut symbols, &RIOT);
+            },
+            Platform::Apple2 => {
+                add_list(&mut symbols, &APPLE2_SOFT_SWITCHES);
+                add_list(&mut symbols, &APPLE2_MONITOR);
+            },
+            Platform::Bbc => {
+                add_list(&mut symbols, &MOS);
+                add_list(&mut symbols, &SHEILA);
+                add(&mut symbols, 0xFE40, "SYSVIA_", &VIA_REGISTERS);
+                add(&mut symbols, 0xFE60, "USRVIA_", &VIA_REGISTERS);
+            }
+        }
+        symbols
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// consecutive chip registers starting at the base address
+fn add(symbols: &mut SymbolTable, base: u16, prefix: &str, registers: &[(&str, &str)]) {
+    for (offset, &(name, description)) in registers.iter().enumerate() {
+        let address = base.wrapping_add(offset as u16);
+        if !name.is_empty() {
+            symbols.insert(address, &format!("{}{}", prefix, name));
+            symbols.describe(address, description);
+        }
+    }
+}
+
+fn add_list(symbols: &mut SymbolTable, list: &[(u16, &str, &str)]) {
+    for &(address, name, description) in list.iter() {
+        symbols.insert(address, name);
+        symbols.describe(address, description);
+    }
+}
+
+// unused registers have empty names
+
+const VIC_II_REGISTERS: [(&str, &str); 47] = [
+    ("SP0X", "sprite 0 x position"), ("SP0Y", "sprite 0 y position"),
+    ("SP1X", "sprite 1 x position"), ("SP1Y", "sprite 1 y position"),
+    ("SP2X", "sprite 2 x position"), ("SP2Y", "sprite 2 y position"),
+    ("SP3X", "sprite 3 x position"), ("SP3Y", "sprite 3 y position"),
+    ("SP4X", "sprite 4 x position"), ("SP4Y", "sprite 4 y position"),
+    ("SP5X", "sprite 5 x position"), ("SP5Y", "sprite 5 y position"),
+    ("SP6X", "sprite 6 x position"), ("SP6Y", "sprite 6 y position"),
+    ("SP7X", "sprite 7 x position"), ("SP7Y", "sprite 7 y position"),
+    ("MSIGX", "sprite x position bit 8"),
+    ("CR1", "control register 1: vertical scroll, screen height, display enable, bitmap mode, raster bit 8"),
+    ("RASTER", "raster line (read), raster interrupt line (write)"),
+    ("LPENX", "light pen x position"), ("LPENY", "light pen y position"),
+    ("SPENA", "sprite enable"),
+    ("CR2", "control register 2: horizontal scroll, screen width, multicolor mode"),
+    ("YXPAND", "sprite vertical expansion"),
+    ("VMCSB", "screen and character memory base"),
+    ("IRQ", "interrupt status"), ("IRQMASK", "interrupt enable"),
+    ("BGPRIO", "sprite to background priority"), ("SPMC", "sprite multicolor enable"),
+    ("XXPAND", "sprite horizontal expansion"),
+    ("SSCOL", "sprite to sprite collisions"), ("SBCOL", "sprite to background collisions"),
+    ("BORDER", "border color"), ("BGCOL0", "background color 0"),
+    ("BGCOL1", "background color 1"), ("BGCOL2", "background color 2"), ("BGCOL3", "background color 3"),
+    ("SPMC0", "sprite multicolor 0"), ("SPMC1", "sprite multicolor 1"),
+    ("SP0COL", "sprite 0 color"), ("SP1COL", "sprite 1 color"), ("SP2COL", "sprite 2 color"),
+    ("SP3COL", "sprite 3 color"), ("SP4COL", "sprite 4 color"), ("SP5COL", "sprite 5 color"),
+    ("SP6COL", "sprite 6 color"), ("SP7COL", "sprite 7 color")
+];
+
+const CIA_REGISTERS: [(&str, &str); 16] = [
+    ("PRA", "port A data"), ("PRB", "port B data"),
+    ("DDRA", "port A data direction"), ("DDRB", "port B data direction"),
+    ("TALO", "timer A low byte"), ("TAHI", "timer A high byte"),
+    ("TBLO", "timer B low byte"), ("TBHI", "timer B high byte"),
+    ("TOD10", "time of day tenths of seconds"), ("TODSEC", "time of day seconds"),
+    ("TODMIN", "time of day minutes"), ("TODHR", "time of day hours"),
+    ("SDR", "serial data"), ("ICR", "interrupt control and status"),
+    ("CRA", "timer A control"), ("CRB", "timer B control")
+];
+
+const VIA_REGISTERS: [(&str, &str); 16] = [
+    ("ORB", "port B data"), ("ORA", "port A data"),
+    ("DDRB", "port B data direction"), ("DDRA", "port A data direction"),
+    ("T1CL", "timer 1 counter low byte"), ("T1CH", "timer 1 counter high byte"),
+    ("T1LL", "timer 1 latch low byte"), ("T1LH", "timer 1 latch high byte"),
+    ("T2CL", "timer 2 counter low byte"), ("T2CH", "timer 2 counter high byte"),
+    ("SR", "shift register"), ("ACR", "auxiliary control"), ("PCR", "peripheral control"),
+    ("IFR", "interrupt flags"), ("IER", "interrupt enable"), ("ORA_NH", "port A data without handshake")
+];
+
+const CBM_VECTORS: [(u16, &str, &str); 3] = [
+    (0x0314, "CINV", "IRQ vector"),
+    (0x0316, "CBINV", "BRK vector"),
+    (0x0318, "NMINV", "NMI vector")
+];
+
+const KERNAL: [(u16, &str, &str); 39] = [
+    (0xFF81, "CINT", "initialize screen editor"),
+    (0xFF84, "IOINIT", "initialize I/O devices"),
+    (0xFF87, "RAMTAS", "test and initialize RAM"),
+    (0xFF8A, "RESTOR", "restore default I/O vectors"),
+    (0xFF8D, "VECTOR", "read or set I/O vectors"),
+    (0xFF90, "SETMSG", "control KERNAL messages"),
+    (0xFF93, "SECOND", "send secondary address after LISTEN"),
+    (0xFF96, "TKSA", "send secondary address after TALK"),
+    (0xFF99, "MEMTOP", "read or set top of memory"),
+    (0xFF9C, "MEMBOT", "read or set bottom of memory"),
+    (0xFF9F, "SCNKEY", "scan keyboard"),
+    (0xFFA2, "SETTMO", "set serial bus timeout"),
+    (0xFFA5, "ACPTR", "read byte from serial bus"),
+    (0xFFA8, "CIOUT", "write byte to serial bus"),
+    (0xFFAB, "UNTLK", "send UNTALK to serial bus"),
+    (0xFFAE, "UNLSN", "send UNLISTEN to serial bus"),
+    (0xFFB1, "LISTEN", "send LISTEN to serial bus"),
+    (0xFFB4, "TALK", "send TALK to serial bus"),
+    (0xFFB7, "READST", "read I/O status"),
+    (0xFFBA, "SETLFS", "set logical file, device and secondary address"),
+    (0xFFBD, "SETNAM", "set file name"),
+    (0xFFC0, "OPEN", "open logical file"),
+    (0xFFC3, "CLOSE", "close logical file"),
+    (0xFFC6, "CHKIN", "set input channel"),
+    (0xFFC9, "CHKOUT", "set output channel"),
+    (0xFFCC, "CLRCHN", "restore default channels"),
+    (0xFFCF, "CHRIN", "read character from input channel"),
+    (0xFFD2, "CHROUT", "write character to output channel"),
+    (0xFFD5, "LOAD", "load file to memory"),
+    (0xFFD8, "SAVE", "save memory to file"),
+    (0xFFDB, "SETTIM", "set jiffy clock"),
+    (0xFFDE, "RDTIM", "read jiffy clock"),
+    (0xFFE1, "STOP", "check STOP key"),
+    (0xFFE4, "GETIN", "get character from keyboard buffer"),
+    (0xFFE7, "CLALL", "close all files"),
+    (0xFFEA, "UDTIM", "update jiffy clock"),
+    (0xFFED, "SCREEN", "read screen size"),
+    (0xFFF0, "PLOT", "read or set cursor position"),
+    (0xFFF3, "IOBASE", "read I/O base address")
+];
+
+const VIC_REGISTERS: [(&str, &str); 16] = [
+    ("HORIZ", "interlace and left screen margin"), ("VERT", "top screen margin"),
+    ("COLUMNS", "screen memory bit 9 and number of columns"),
+    ("ROWS", "raster bit 0, number of rows and character size"),
+    ("RASTER", "raster line bits 1-8"), ("MEMPTR", "screen and character memory base"),
+    ("LPENX", "light pen x position"), ("LPENY", "light pen y position"),
+    ("POTX", "paddle x"), ("POTY", "paddle y"),
+    ("BASS", "bass voice frequency"), ("ALTO", "alto voice frequency"),
+    ("SOPRANO", "soprano voice frequency"), ("NOISE", "noise voice frequency"),
+    ("VOLUME", "auxiliary color and volume"), ("COLOR", "background and border color, reverse mode")
+];
+
+const PPU_REGISTERS: [(&str, &str); 8] = [
+    ("PPUCTRL", "PPU control: name table, increment, pattern tables, sprite size, NMI enable"),
+    ("PPUMASK", "PPU mask: rendering enable and color effects"),
+    ("PPUSTATUS", "PPU status: sprite overflow, sprite 0 hit, vertical blank"),
+    ("OAMADDR", "sprite memory address"), ("OAMDATA", "sprite memory data"),
+    ("PPUSCROLL", "scroll position (written twice)"), ("PPUADDR", "VRAM address (written twice)"),
+    ("PPUDATA", "VRAM data")
+];
+
+const NES_IO: [(u16, &str, &str); 22] = [
+    (0x4000, "SQ1_VOL", "pulse 1 duty and volume"),
+    (0x4001, "SQ1_SWEEP", "pulse 1 sweep"),
+    (0x4002, "SQ1_LO", "pulse 1 timer low byte"),
+    (0x4003, "SQ1_HI", "pulse 1 length and timer high bits"),
+    (0x4004, "SQ2_VOL", "pulse 2 duty and volume"),
+    (0x4005, "SQ2_SWEEP", "pulse 2 sweep"),
+    (0x4006, "SQ2_LO", "pulse 2 timer low byte"),
+    (0x4007, "SQ2_HI", "pulse 2 length and timer high bits"),
+    (0x4008, "TRI_LINEAR", "triangle linear counter"),
+    (0x400A, "TRI_LO", "triangle timer low byte"),
+    (0x400B, "TRI_HI", "triangle length and timer high bits"),
+    (0x400C, "NOISE_VOL", "noise volume"),
+    (0x400E, "NOISE_LO", "noise mode and period"),
+    (0x400F, "NOISE_HI", "noise length"),
+    (0x4010, "DMC_FREQ", "DMC IRQ, loop and frequency"),
+    (0x4011, "DMC_RAW", "DMC direct load"),
+    (0x4012, "DMC_START", "DMC sample address"),
+    (0x4013, "DMC_LEN", "DMC sample length"),
+    (0x4014, "OAMDMA", "sprite DMA page"),
+    (0x4015, "SND_CHN", "sound channel enable and status"),
+    (0x4016, "JOY1", "controller 1 data, controller strobe (write)"),
+    (0x4017, "JOY2", "controller 2 data, frame counter control (write)")
+];
+
+const GTIA_REGISTERS: [(&str, &str); 32] = [
+    ("HPOSP0", "player 0 horizontal position (read: M0PF)"), ("HPOSP1", "player 1 horizontal position (read: M1PF)"),
+    ("HPOSP2", "player 2 horizontal position (read: M2PF)"), ("HPOSP3", "player 3 horizontal position (read: M3PF)"),
+    ("HPOSM0", "missile 0 horizontal position (read: P0PF)"), ("HPOSM1", "missile 1 horizontal position (read: P1PF)"),
+    ("HPOSM2", "missile 2 horizontal position (read: P2PF)"), ("HPOSM3", "missile 3 horizontal position (read: P3PF)"),
+    ("SIZEP0", "player 0 width (read: M0PL)"), ("SIZEP1", "player 1 width (read: M1PL)"),
+    ("SIZEP2", "player 2 width (read: M2PL)"), ("SIZEP3", "player 3 width (read: M3PL)"),
+    ("SIZEM", "missile widths (read: P0PL)"),
+    ("GRAFP0", "player 0 graphics (read: P1PL)"), ("GRAFP1", "player 1 graphics (read: P2PL)"),
+    ("GRAFP2", "player 2 graphics (read: P3PL)"), ("GRAFP3", "player 3 graphics (read: TRIG0)"),
+    ("GRAFM", "missile graphics (read: TRIG1)"),
+    ("COLPM0", "player/missile 0 color (read: TRIG2)"), ("COLPM1", "player/missile 1 color (read: TRIG3)"),
+    ("COLPM2", "player/missile 2 color (read: PAL)"), ("COLPM3", "player/missile 3 color"),
+    ("COLPF0", "playfield 0 color"), ("COLPF1", "playfield 1 color"),
+    ("COLPF2", "playfield 2 color"), ("COLPF3", "playfield 3 color"),
+    ("COLBK", "background color"), ("PRIOR", "priority and GTIA modes"),
+    ("VDELAY", "vertical delay"), ("GRACTL", "graphics control"),
+    ("HITCLR", "clear collisions"), ("CONSOL", "console switches and speaker")
+];
+
+const POKEY_REGISTERS: [(&str, &str); 16] = [
+    ("AUDF1", "channel 1 frequency (read: POT0)"), ("AUDC1", "channel 1 control (read: POT1)"),
+    ("AUDF2", "channel 2 frequency (read: POT2)"), ("AUDC2", "channel 2 control (read: POT3)"),
+    ("AUDF3", "channel 3 frequency (read: POT4)"), ("AUDC3", "channel 3 control (read: POT5)"),
+    ("AUDF4", "channel 4 frequency (read: POT6)"), ("AUDC4", "channel 4 control (read: POT7)"),
+    ("AUDCTL", "audio control (read: ALLPOT)"), ("STIMER", "start timers (read: KBCODE)"),
+    ("SKRES", "reset serial status (read: RANDOM)"), ("POTGO", "start paddle scan"),
+    ("", ""), ("SEROUT", "serial output (read: SERIN)"),
+    ("IRQEN", "interrupt enable (read: IRQST)"), ("SKCTL", "serial port control (read: SKSTAT)")
+];
+
+const PIA_REGISTERS: [(&str, &str); 4] = [
+    ("PORTA", "joystick ports"), ("PORTB", "memory control (XL/XE), joystick ports 3 and 4 (400/800)"),
+    ("PACTL", "port A control"), ("PBCTL", "port B control")
+];
+
+const ANTIC_REGISTERS: [(&str, &str); 16] = [
+    ("DMACTL", "DMA control"), ("CHACTL", "character control"),
+    ("DLISTL", "display list address low byte"), ("DLISTH", "display list address high byte"),
+    ("HSCROL", "horizontal fine scroll"), ("VSCROL", "vertical fine scroll"),
+    ("", ""), ("PMBASE", "player/missile graphics base"),
+    ("", ""), ("CHBASE", "character set base"),
+    ("WSYNC", "wait for horizontal sync"), ("VCOUNT", "vertical line counter / 2"),
+    ("PENH", "light pen horizontal position"), ("PENV", "light pen vertical position"),
+    ("NMIEN", "NMI enable"), ("NMIRES", "reset NMI status (read: NMIST)")
+];
+
+const ATARI_OS: [(u16, &str, &str); 31] = [
+    (0x000A, "DOSVEC", "DOS run vector"),
+    (0x000C, "DOSINI", "DOS init vector"),
+    (0x0200, "VDSLST", "display list interrupt vector"),
+    (0x0202, "VPRCED", "serial proceed interrupt vector"),
+    (0x0204, "VINTER", "serial interrupt vector"),
+    (0x0206, "VBREAK", "BRK vector"),
+    (0x0208, "VKEYBD", "keyboard interrupt vector"),
+    (0x020A, "VSERIN", "serial input ready vector"),
+    (0x020C, "VSEROR", "serial output ready vector"),
+    (0x020E, "VSEROC", "serial output complete vector"),
+    (0x0210, "VTIMR1", "timer 1 vector"),
+    (0x0212, "VTIMR2", "timer 2 vector"),
+    (0x0214, "VTIMR4", "timer 4 vector"),
+    (0x0216, "VIMIRQ", "IRQ vector"),
+    (0x0222, "VVBLKI", "immediate vertical blank vector"),
+    (0x0224, "VVBLKD", "deferred vertical blank vector"),
+    (0x022F, "SDMCTL", "shadow of DMACTL"),
+    (0x0230, "SDLSTL", "shadow of DLISTL"),
+    (0x0231, "SDLSTH", "shadow of DLISTH"),
+    (0x02C4, "COLOR0", "shadow of COLPF0"),
+    (0x02C5, "COLOR1", "shadow of COLPF1"),
+    (0x02C6, "COLOR2", "shadow of COLPF2"),
+    (0x02C7, "COLOR3", "shadow of COLPF3"),
+    (0x02C8, "COLOR4", "shadow of COLBK"),
+    (0xE456, "CIOV", "central I/O entry"),
+    (0xE459, "SIOV", "serial I/O entry"),
+    (0xE45C, "SETVBV", "set vertical blank vectors and timers"),
+    (0xE45F, "SYSVBV", "system vertical blank routine"),
+    (0xE462, "XITVBV", "exit vertical blank routine"),
+    (0xE474, "WARMSV", "warm start"),
+    (0xE477, "COLDSV", "cold start")
+];
+
+const TIA_READ_REGISTERS: [(&str, &str); 14] = [
+    ("CXM0P", "missile 0 collisions with players"), ("CXM1P", "missile 1 collisions with players"),
+    ("CXP0FB", "player 0 collisions with playfield and ball"), ("CXP1FB", "player 1 collisions with playfield and ball"),
+    ("CXM0FB", "missile 0 collisions with playfield and ball"), ("CXM1FB", "missile 1 collisions with playfield and ball"),
+    ("CXBLPF", "ball collision with playfield"), ("CXPPMM", "player and missile collisions"),
+    ("INPT0", "paddle 0"), ("INPT1", "paddle 1"), ("INPT2", "paddle 2"), ("INPT3", "paddle 3"),
+    ("INPT4", "joystick 0 fire button"), ("INPT5", "joystick 1 fire button")
+];
+
+const RIOT: [(u16, &str, &str); 10] = [
+    (0x0280, "SWCHA", "joystick directions"),
+    (0x0281, "SWACNT", "port A data direction"),
+    (0x0282, "SWCHB", "console switches"),
+    (0x0283, "SWBCNT", "port B data direction"),
+    (0x0284, "INTIM", "timer value"),
+    (0x0285, "TIMINT", "timer interrupt flag"),
+    (0x0294, "TIM1T", "set timer, 1 cycle intervals"),
+    (0x0295, "TIM8T", "set timer, 8 cycle intervals"),
+    (0x0296, "TIM64T", "set timer, 64 cycle intervals"),
+    (0x0297, "T1024T", "set timer, 1024 cycle intervals")
+];
+
+const APPLE2_SOFT_SWITCHES: [(u16, &str, &str); 33] = [
+    (0xC000, "KBD", "keyboard data"),
+    (0xC010, "KBDSTRB", "clear keyboard strobe"),
+    (0xC020, "TAPEOUT", "toggle cassette output"),
+    (0xC030, "SPKR", "toggle speaker"),
+    (0xC050, "TXTCLR", "graphics mode"),
+    (0xC051, "TXTSET", "text mode"),
+    (0xC052, "MIXCLR", "full screen graphics"),
+    (0xC053, "MIXSET", "mixed text and graphics"),
+    (0xC054, "LOWSCR", "display page 1"),
+    (0xC055, "HISCR", "display page 2"),
+    (0xC056, "LORES", "low resolution graphics"),
+    (0xC057, "HIRES", "high resolution graphics"),
+    (0xC058, "CLRAN0", "annunciator 0 off"),
+    (0xC059, "SETAN0", "annunciator 0 on"),
+    (0xC05A, "CLRAN1", "annunciator 1 off"),
+    (0xC05B, "SETAN1", "annunciator 1 on"),
+    (0xC05C, "CLRAN2", "annunciator 2 off"),
+    (0xC05D, "SETAN2", "annunciator 2 on"),
+    (0xC05E, "CLRAN3", "annunciator 3 off"),
+    (0xC05F, "SETAN3", "annunciator 3 on"),
+    (0xC060, "TAPEIN", "cassette input"),
+    (0xC061, "PB0", "push button 0"),
+    (0xC062, "PB1", "push button 1"),
+    (0xC063, "PB2", "push button 2"),
+    (0xC064, "PADDL0", "paddle 0 timer"),
+    (0xC065, "PADDL1", "paddle 1 timer"),
+    (0xC066, "PADDL2", "paddle 2 timer"),
+    (0xC067, "PADDL3", "paddle 3 timer"),
+    (0xC070, "PTRIG", "start paddle timers"),
+    (0xC080, "LCRAMIN2", "read language card RAM bank 2, no write"),
+    (0xC081, "ROMIN2", "read ROM, write language card RAM bank 2 (read twice)"),
+    (0xC083, "LCBANK2", "read and write language card RAM bank 2 (read twice)"),
+    (0xC08B, "LCBANK1", "read and write language card RAM bank 1 (read twice)")
+];
+
+const APPLE2_MONITOR: [(u16, &str, &str); 32] = [
+    (0xF800, "PLOT", "plot low resolution block"),
+    (0xF819, "HLINE", "draw horizontal low resolution line"),
+    (0xF828, "VLINE", "draw vertical low resolution line"),
+    (0xF832, "CLRSCR", "clear low resolution screen"),
+    (0xF836, "CLRTOP", "clear top of low resolution screen"),
+    (0xF864, "SETCOL", "set low resolution color"),
+    (0xF941, "PRNTAX", "print A and X as hex"),
+    (0xF948, "PRBLNK", "print three spaces"),
+    (0xFB1E, "PREAD", "read paddle"),
+    (0xFB2F, "INIT", "initialize text screen"),
+    (0xFB39, "SETTXT", "set text mode"),
+    (0xFB40, "SETGR", "set mixed graphics mode"),
+    (0xFBDD, "BELL1", "beep"),
+    (0xFC22, "VTAB", "set cursor row"),
+    (0xFC58, "HOME", "clear screen and home cursor"),
+    (0xFC9C, "CLREOL", "clear to end of line"),
+    (0xFCA8, "WAIT", "delay"),
+    (0xFD0C, "RDKEY", "read key"),
+    (0xFD35, "RDCHAR", "read key with escape handling"),
+    (0xFD6A, "GETLN", "read input line"),
+    (0xFD8E, "CROUT", "print carriage return"),
+    (0xFDDA, "PRBYTE", "print A as hex"),
+    (0xFDE3, "PRHEX", "print low nibble of A as hex"),
+    (0xFDED, "COUT", "print character through output hook"),
+    (0xFDF0, "COUT1", "print character to screen"),
+    (0xFE2C, "MOVE", "copy memory"),
+    (0xFE80, "SETINV", "set inverse text"),
+    (0xFE84, "SETNORM", "set normal text"),
+    (0xFF3A, "BELL", "print bell character"),
+    (0xFF3F, "IOREST", "restore registers"),
+    (0xFF4A, "IOSAVE", "save registers"),
+    (0xFF69, "MONZ", "enter monitor")
+];
+
+const MOS: [(u16, &str, &str); 37] = [
+    (0x0200, "USERV", "user vector"),
+    (0x0202, "BRKV", "BRK vector"),
+    (0x0204, "IRQ1V", "primary IRQ vector"),
+    (0x0206, "IRQ2V", "secondary IRQ vector"),
+    (0x0208, "CLIV", "command line interpreter vector"),
+    (0x020A, "BYTEV", "OSBYTE vector"),
+    (0x020C, "WORDV", "OSWORD vector"),
+    (0x020E, "WRCHV", "write character vector"),
+    (0x0210, "RDCHV", "read character vector"),
+    (0x0212, "FILEV", "OSFILE vector"),
+    (0x0214, "ARGSV", "OSARGS vector"),
+    (0x0216, "BGETV", "OSBGET vector"),
+    (0x0218, "BPUTV", "OSBPUT vector"),
+    (0x021A, "GBPBV", "OSGBPB vector"),
+    (0x021C, "FINDV", "OSFIND vector"),
+    (0x021E, "FSCV", "filing system control vector"),
+    (0x0220, "EVNTV", "event vector"),
+    (0xFFB9, "OSRDRM", "read byte from paged ROM"),
+    (0xFFBC, "VDUCHR", "write character to VDU"),
+    (0xFFBF, "OSEVEN", "generate event"),
+    (0xFFC2, "GSINIT", "initialize string read"),
+    (0xFFC5, "GSREAD", "read character from string"),
+    (0xFFC8, "NVRDCH", "read character (non-vectored)"),
+    (0xFFCB, "NVWRCH", "write character (non-vectored)"),
+    (0xFFCE, "OSFIND", "open or close file"),
+    (0xFFD1, "OSGBPB", "read or write multiple bytes"),
+    (0xFFD4, "OSBPUT", "write byte to file"),
+    (0xFFD7, "OSBGET", "read byte from file"),
+    (0xFFDA, "OSARGS", "read or write file arguments"),
+    (0xFFDD, "OSFILE", "load or save file"),
+    (0xFFE0, "OSRDCH", "read character"),
+    (0xFFE3, "OSASCI", "write character, converting CR to CR/LF"),
+    (0xFFE7, "OSNEWL", "write newline"),
+    (0xFFEE, "OSWRCH", "write character"),
+    (0xFFF1, "OSWORD", "miscellaneous OS call with parameter block"),
+    (0xFFF4, "OSBYTE", "miscellaneous OS call"),
+    (0xFFF7, "OSCLI", "execute command line")
+];
+
+const SHEILA: [(u16, &str, &str); 14] = [
+    (0xFE00, "CRTC_ADDR", "6845 CRTC register select"),
+    (0xFE01, "CRTC_DATA", "6845 CRTC register data"),
+    (0xFE08, "ACIA_CTRL", "6850 ACIA control and status"),
+    (0xFE09, "ACIA_DATA", "6850 ACIA data"),
+    (0xFE10, "SERPROC", "serial ULA control"),
+    (0xFE20, "VIDULA_CTRL", "video ULA control"),
+    (0xFE21, "VIDULA_PAL", "video ULA palette"),
+    (0xFE30, "ROMSEL", "paged ROM select"),
+    (0xFE80, "FDC_STAT", "floppy disc controller command and status"),
+    (0xFEA0, "ADLC", "Econet controller"),
+    (0xFEC0, "ADC_STAT", "analogue to digital converter control and status"),
+    (0xFEC1, "ADC_HI", "analogue to digital converter result high byte"),
+    (0xFEC2, "ADC_LO", "analogue to digital converter result low byte"),
+    (0xFEE0, "TUBE", "Tube ULA status")
+];
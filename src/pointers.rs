@@ -0,0 +1,208 @@
+//! Detection of 16-bit addresses built from two immediate operands.
+//!
+//! Code often loads the halves of an address with immediate instructions, either storing them to
+//! consecutive bytes (`LDA #$00 / STA $FB / LDA #$C0 / STA $FC`) or passing them in two registers
+//! to a subroutine (`LDA #<text / LDY #>text / JSR print`). Showing such operands as `#<label`
+//! and `#>label` keeps reassembled code working when the target moves.
+//!
+//! Detection works on straight-line code: the state is reset at every branch, jump, call, return
+//! and gap in the code. For register pairs the low byte is taken from the first register in
+//! A, X, Y order. Only targets within the disassembled image are accepted, looked up in the bank
+//! of the loading code first; targets inside an instruction or string are shown relative to the
+//! start of its line (`#<L0810+1`).
+use std::collections::BTreeMap;
+use analysis::Line;
+use banking::BankedAddress;
+use instruction::{AddrMode, CPURegister, Instruction, OpCode};
+use listing::Annotations;
+use operand::OperandFormat;
+use symbols::SymbolTable;
+
+/// Two immediate operands forming an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PointerPair {
+    /// instruction loading the low byte
+    pub low: BankedAddress,
+    /// instruction loading the high byte
+    pub high: BankedAddress,
+    /// address formed by both bytes, in the bank holding it
+    pub target: BankedAddress,
+    /// distance of the target from the start of the line containing it
+    pub offset: u16
+}
+
+/// Find immediate operand pairs forming addresses in disassembled lines.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::analysis;
+/// use disasm6502::banking::BankedAddress;
+///
+/// // $C000: LDA #$00 / STA $FB / LDA #$C0 / STA $FC / RTS
+/// let bytes = vec![0xA9, 0x00, 0x85, 0xFB, 0xA9, 0xC0, 0x85, 0xFC, 0x60];
+/// let map = analysis::analyze(&bytes, 0xC000, &[0xC000], &[]);
+/// let pairs = disasm6502::pointers::find_pointer_pairs(&analysis::disassemble(&bytes, 0xC000, &map));
+///
+/// assert_eq!(pairs.len(), 1);
+/// assert_eq!((pairs[0].low, pairs[0].target, pairs[0].offset), (BankedAddress::unbanked(0xC000), BankedAddress::unbanked(0xC000), 0));
+/// ```
+pub fn find_pointer_pairs(lines: &[Line]) -> Vec<PointerPair> {
+    // start of the line containing each byte of the image
+    let mut image: BTreeMap<BankedAddress, BankedAddress> = BTreeMap::new();
+    for line in lines.iter() {
+        let (start, size) = match *line {
+            Line::Code(ref i, _) => (i.banked_address(), i.size()),
+            Line::Data(at, _) => (at, 1),
+            Line::Text(ref t) => (t.start, t.bytes.len() as u16),
+            // starts a line of its own, the other bytes belong to the preceding instruction
+            Line::Overlapping(ref i, _) => (i.banked_address(), 1)
+        };
+        image.extend((0..size).map(|n| (BankedAddress { address: start.address.wrapping_add(n), ..start }, start)));
+    }
+
+    // target in the bank of the loading code, or in the only bank holding it
+    let pair = |low: BankedAddress, high: BankedAddress, target: u16| {
+        let start = image.get(&BankedAddress { address: target, ..low }).or_else(|| {
+            let mut banks = image.iter().filter(|&(at, _)| at.address == target).map(|(_, start)| start);
+            match (banks.next(), banks.next()) {
+                (Some(start), None) => Some(start),
+                _ => None
+            }
+        });
+        start.map(|&start| PointerPair { low, high, target: BankedAddress { address: target, ..start }, offset: target.wrapping_sub(start.address) })
+    };
+
+    let mut pairs = Vec::new();
+    let mut loads: [Option<(u8, BankedAddress)>; 3] = [None; 3];
+    let mut stores: Vec<(u16, u8, BankedAddress)> = Vec::new();
+    let mut next: Option<BankedAddress> = None;
+
+    for line in lines.iter() {
+        let instruction = match *line {
+            Line::Code(ref i, _) => i,
+            Line::Data(..) | Line::Text(_) | Line::Overlapping(..) => { next = None; continue; }
+        };
+
+        let at = instruction.banked_address();
+        if next != Some(at) {
+            loads = [None; 3];
+            stores.clear();
+        }
+        next = Some(BankedAddress { address: at.address.wrapping_add(instruction.size()), ..at });
+
+        if let (AddrMode::Immediate, Some(r)) = (instruction.addr_mode, load_register(instruction)) {
+            loads[r] = Some((instruction.operand.unwrap_or(0) as u8, at));
+            continue;
+        }
+
+        if let Some((r, destination)) = store(instruction) {
+            if let Some((value, load)) = loads[r] {
+                let other = stores.iter().position(|&(d, _, l)| l != load && (d == destination.wrapping_add(1) || destination == d.wrapping_add(1)));
+                match other {
+                    Some(i) => {
+                        let (d, v, l) = stores.remove(i);
+                        let (low, high) = if d == destination.wrapping_add(1) { ((value, load), (v, l)) } else { ((v, l), (value, load)) };
+                        pairs.extend(pair(low.1, high.1, (high.0 as u16) << 8 | low.0 as u16));
+                    },
+                    None => stores.push((destination, value, load))
+                }
+            }
+            continue;
+        }
+
+        // with all three registers loaded the pair cannot be told apart
+        if is_call(instruction) && loads.iter().filter(|l| l.is_some()).count() == 2 {
+            let ((low, low_at), (high, high_at)) = match loads {
+                [Some(low), Some(high), None] | [Some(low), None, Some(high)] | [None, Some(low), Some(high)] => (low, high),
+                _ => unreachable!()
+            };
+            pairs.extend(pair(low_at, high_at, (high as u16) << 8 | low as u16));
+        }
+
+        if is_control_flow(instruction) {
+            loads = [None; 3];
+            stores.clear();
+            continue;
+        }
+
+        for r in instruction.registers_written.iter().flatten() {
+            loads[index(*r)] = None;
+        }
+    }
+
+    pairs
+}
+
+/// Show the operands of pointer pairs as low and high bytes of the label of the line containing
+/// the target (plus the offset into it), adding `Lxxxx` labels (`Lbb_xxxx` in banks) for lines
+/// without one. Existing operand formats are kept.
+pub fn apply(pairs: &[PointerPair], symbols: &mut SymbolTable, annotations: &mut Annotations) {
+    for pair in pairs.iter() {
+        let start = BankedAddress { address: pair.target.address.wrapping_sub(pair.offset), ..pair.target };
+        let label = match symbols.get_banked(start) {
+            Some(label) => label.to_string(),
+            None => {
+                let label = match start.bank {
+                    Some(bank) => format!("L{:02X}_{:04X}", bank, start.address),
+                    None => format!("L{:04X}", start.address)
+                };
+                symbols.insert_banked(start, &label);
+                label
+            }
+        };
+
+        let offset = pair.offset as i32;
+        annotations.operand_formats.entry(pair.low).or_insert_with(|| OperandFormat::low_byte(&label, offset));
+        annotations.operand_formats.entry(pair.high).or_insert_with(|| OperandFormat::high_byte(&label, offset));
+    }
+}
+
+fn index(register: CPURegister) -> usize {
+    match register {
+        CPURegister::A => 0,
+        CPURegister::X => 1,
+        CPURegister::Y => 2
+    }
+}
+
+// register loaded by LDA/LDX/LDY
+fn load_register(instruction: &Instruction) -> Option<usize> {
+    match instruction.opcode {
+        OpCode::LDA(_) => Some(0),
+        OpCode::LDX(_) => Some(1),
+        OpCode::LDY(_) => Some(2),
+        _ => None
+    }
+}
+
+// register and address of a non-indexed STA/STX/STY
+fn store(instruction: &Instruction) -> Option<(usize, u16)> {
+    let register = match instruction.opcode {
+        OpCode::STA(_) => 0,
+        OpCode::STX(_) => 1,
+        OpCode::STY(_) => 2,
+        _ => return None
+    };
+
+    match instruction.addr_mode {
+        AddrMode::Zeropage | AddrMode::Absolute => Some((register, instruction.operand.unwrap_or(0))),
+        _ => None
+    }
+}
+
+fn is_call(instruction: &Instruction) -> bool {
+    match instruction.opcode {
+        OpCode::JSR(_) => true,
+        OpCode::JMP(_) => instruction.addr_mode == AddrMode::Absolute,
+        _ => false
+    }
+}
+
+fn is_control_flow(instruction: &Instruction) -> bool {
+    match instruction.opcode {
+        OpCode::JSR(_) | OpCode::JMP(_) | OpCode::RTS(_) | OpCode::RTI(_) | OpCode::BRK(_) => true,
+        _ => instruction.addr_mode == AddrMode::Relative
+    }
+}
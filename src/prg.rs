@@ -0,0 +1,76 @@
+//! Commodore PRG file loader.
+//!
+//! PRG files start with a two-byte little-endian load address followed by the data. Machine code
+//! programs are often preceded by a BASIC stub (e.g. `10 SYS 2061`) which starts the code;
+//! the address of the `SYS` call is used as the entry point.
+use basic;
+use error::{Disasm6502Error, Result};
+
+/// BASIC V2 token for the `SYS` keyword.
+pub const SYS_TOKEN: u8 = 0x9E;
+
+/// Contents of a PRG file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Prg {
+    /// load address from the file header
+    pub load_address: u16,
+    /// data following the header
+    pub data: Vec<u8>,
+    /// machine code entry point found in the BASIC stub (if any)
+    pub entry_point: Option<u16>
+}
+
+impl Prg {
+    /// Parse contents of a PRG file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::prg::Prg;
+    ///
+    /// // 10 SYS 2061 followed by RTS
+    /// let bytes = vec![0x01, 0x08, 0x0B, 0x08, 0x0A, 0x00, 0x9E, 0x32, 0x30, 0x36, 0x31, 0x00, 0x00, 0x00, 0x60];
+    /// let prg = Prg::parse(&bytes).unwrap();
+    ///
+    /// assert_eq!(prg.load_address, 0x0801);
+    /// assert_eq!(prg.entry_point, Some(2061));
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Prg> {
+        if bytes.len() < 2 {
+            return Err(Disasm6502Error::InvalidFormat(String::from("PRG file too short for load address")));
+        }
+
+        let load_address = bytes[0] as u16 | (bytes[1] as u16) << 8;
+        let data = bytes[2..].to_vec();
+        let entry_point = find_sys(load_address, &data).filter(|&addr| {
+            addr >= load_address && ((addr - load_address) as usize) < data.len()
+        });
+
+        Ok(Prg { load_address, data, entry_point })
+    }
+
+    /// Machine code part of the file: start address and bytes from the entry point on
+    /// (whole data if there is no BASIC stub).
+    pub fn code(&self) -> (u16, &[u8]) {
+        match self.entry_point {
+            Some(addr) => (addr, &self.data[(addr - self.load_address) as usize..]),
+            None => (self.load_address, &self.data)
+        }
+    }
+}
+
+/// Find the target address of the first `SYS` statement in a tokenized BASIC program.
+///
+/// Lines are followed through their link pointers; the search stops at the end of the program
+/// or at the first inconsistent link.
+pub fn find_sys(load_address: u16, data: &[u8]) -> Option<u16> {
+    let arguments = basic::lines(load_address, data)
+        .find_map(|line| line.text.iter().position(|&b| b == SYS_TOKEN).map(|pos| &line.text[pos + 1..]))?;
+    let digits: String = arguments.iter()
+        .map(|&b| b as char)
+        .skip_while(|&c| c == ' ' || c == '(')
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse::<u32>().ok().filter(|&a| a <= 0xFFFF).map(|a| a as u16)
+}
@@ -0,0 +1,593 @@
+//! Disassembly project: the input image with all user annotations.
+//!
+//! A project records where the image comes from (the input file, its format and, for disk
+//! images, the file on the disk), the CPU variant, the platform, entry points, code/data region
+//! overrides, the text encoding, labels, comments and operand formats. It is stored in a small,
+//! stable subset of TOML (strings, arrays of strings, tables and arrays of tables) so it can be
+//! kept under version control and edited by hand. Addresses are written as `$0810` or, in
+//! banked images, as `01:$8000`. Running the disassembly of a project applies all annotations.
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use analysis::{self, CodeMap};
+use apple2::DiskImage;
+use banking::{BankedAddress, BankLayout};
+use crt::Crt;
+use d64::D64;
+use dfs::Dfs;
+use error::{Disasm6502Error, Result};
+use hex_file::HexFile;
+use ines::Nes;
+use listing::{self, Annotations};
+use memory_image::MemoryImage;
+use nsf::Nsf;
+use operand::OperandFormat;
+use platform::Platform;
+use pointers;
+use prg::Prg;
+use segment::Segment;
+use sid::Sid;
+use symbols::SymbolTable;
+use t64::T64;
+use text::{self, Encoding};
+use xex::Xex;
+
+/// Instruction set of the analyzed CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CpuVariant {
+    /// NMOS 6502 including undocumented opcodes
+    Nmos6502,
+    /// NMOS 6502 with documented opcodes only (undocumented opcodes are treated as data)
+    Documented6502
+}
+
+impl CpuVariant {
+    /// Name used in project files.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CpuVariant::Nmos6502 => "6502",
+            CpuVariant::Documented6502 => "6502-documented"
+        }
+    }
+
+    /// Find CPU variant by name.
+    pub fn parse(name: &str) -> Option<CpuVariant> {
+        match name.trim() {
+            "6502" => Some(CpuVariant::Nmos6502),
+            "6502-documented" => Some(CpuVariant::Documented6502),
+            _ => None
+        }
+    }
+}
+
+/// Container format of the input file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputFormat {
+    /// raw binary placed at the load address
+    Binary,
+    /// Commodore PRG file
+    Prg,
+    /// C64 tape archive (all files)
+    T64,
+    /// C64 cartridge (all CHIP packets)
+    Crt,
+    /// iNES/NES 2.0 ROM (power-on mapping)
+    Nes,
+    /// NES Sound Format file
+    Nsf,
+    /// PSID/RSID file
+    Sid,
+    /// Atari 8-bit DOS binary file
+    Xex,
+    /// Intel HEX or Motorola S-record file
+    Hex,
+    /// PRG file on a 1541 disk image
+    D64,
+    /// binary file on an Apple II DOS 3.3 or ProDOS disk image
+    Apple2,
+    /// file on a single sided BBC Micro DFS disk image
+    Ssd,
+    /// file on a double sided BBC Micro DFS disk image
+    Dsd
+}
+
+/// All input formats.
+pub const INPUT_FORMATS: [InputFormat; 13] = [
+    InputFormat::Binary, InputFormat::Prg, InputFormat::T64, InputFormat::Crt, InputFormat::Nes,
+    InputFormat::Nsf, InputFormat::Sid, InputFormat::Xex, InputFormat::Hex, InputFormat::D64,
+    InputFormat::Apple2, InputFormat::Ssd, InputFormat::Dsd
+];
+
+impl InputFormat {
+    /// Name used in project files.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            InputFormat::Binary => "binary",
+            InputFormat::Prg => "prg",
+            InputFormat::T64 => "t64",
+            InputFormat::Crt => "crt",
+            InputFormat::Nes => "nes",
+            InputFormat::Nsf => "nsf",
+            InputFormat::Sid => "sid",
+            InputFormat::Xex => "xex",
+            InputFormat::Hex => "hex",
+            InputFormat::D64 => "d64",
+            InputFormat::Apple2 => "apple2",
+            InputFormat::Ssd => "ssd",
+            InputFormat::Dsd => "dsd"
+        }
+    }
+
+    /// Find input format by name (case insensitive).
+    pub fn parse(name: &str) -> Option<InputFormat> {
+        INPUT_FORMATS.iter().find(|f| f.name().eq_ignore_ascii_case(name.trim())).cloned()
+    }
+}
+
+/// Kind of a region override.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegionType {
+    /// region starts with code
+    Code,
+    /// region holds no code
+    Data
+}
+
+/// Address range forced to hold code or data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RegionOverride {
+    /// first address (regions without a bank apply to all banks)
+    pub start: BankedAddress,
+    /// last address (inclusive)
+    pub end: u16,
+    /// kind of the region
+    pub region_type: RegionType
+}
+
+impl RegionOverride {
+    /// Check if the bank-qualified address falls within the region.
+    pub fn contains(&self, at: BankedAddress) -> bool {
+        (self.start.bank.is_none() || self.start.bank == at.bank) && (self.start.address..=self.end).contains(&at.address)
+    }
+}
+
+/// Disassembly project.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Project {
+    /// path of the input file (relative paths are relative to the project file)
+    pub input: String,
+    /// container format of the input file (None for a raw binary if a load address is given,
+    /// a PRG file otherwise)
+    pub format: Option<InputFormat>,
+    /// name of the file in a disk image
+    pub file: Option<String>,
+    /// load address of a raw binary input, or of a file on a disk image without one
+    pub load_address: Option<BankedAddress>,
+    /// instruction set
+    pub cpu: CpuVariant,
+    /// platform whose hardware symbols are used (if any)
+    pub platform: Option<Platform>,
+    /// encoding of text (the platform encoding if not given)
+    pub encoding: Option<Encoding>,
+    /// analysis entry points
+    pub entry_points: Vec<BankedAddress>,
+    /// code and data region overrides
+    pub regions: Vec<RegionOverride>,
+    /// labels and descriptions
+    pub symbols: SymbolTable,
+    /// comments and operand formats
+    pub annotations: Annotations
+}
+
+impl Project {
+    /// Create a project for the input file without any annotations.
+    pub fn new(input: &str) -> Project {
+        Project {
+            input: input.to_string(),
+            format: None,
+            file: None,
+            load_address: None,
+            cpu: CpuVariant::Nmos6502,
+            platform: None,
+            encoding: None,
+            entry_points: Vec::new(),
+            regions: Vec::new(),
+            symbols: SymbolTable::new(),
+            annotations: Annotations::default()
+        }
+    }
+
+    /// Parse project file contents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::project::Project;
+    ///
+    /// let text = "input = \"game.prg\"\nplatform = \"c64\"\nentry_points = [\"$080D\"]\n\n[labels]\n\"$080D\" = \"start\"\n";
+    /// let project = Project::parse(text).unwrap();
+    ///
+    /// assert_eq!(project.symbols.get(0x080D), Some("start"));
+    /// assert_eq!(project.to_string(), Project::parse(&project.to_string()).unwrap().to_string());
+    /// ```
+    pub fn parse(text: &str) -> Result<Project> {
+        let mut project = Project::new("");
+        let mut table = String::new();
+        let mut regions: Vec<PartialRegion> = Vec::new();
+
+        for (index, line) in text.lines().enumerate().map(|(i, l)| (i + 1, l.trim())) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line == "[[region]]" {
+                table = String::from("region");
+                regions.push(PartialRegion { line: index, start: None, end: None, region_type: None });
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                table = line[1..line.len() - 1].trim().to_string();
+                continue;
+            }
+
+            let (key, value) = key_value(line).ok_or_else(|| invalid(index, "expected key = value"))?;
+            match regions.last_mut() {
+                Some(region) if table == "region" => region.set(&key, value).map_err(|e| invalid(index, &e))?,
+                _ => project.set(&table, &key, value).map_err(|e| invalid(index, &e))?
+            }
+        }
+
+        for region in regions {
+            match (region.start, region.end, region.region_type) {
+                (Some(start), Some(end), Some(region_type)) => project.regions.push(RegionOverride { start, end, region_type }),
+                _ => return Err(invalid(region.line, "region without start, end or type"))
+            }
+        }
+
+        if project.regions.iter().any(|r| r.end < r.start.address) {
+            return Err(Disasm6502Error::InvalidFormat(String::from("region ends before its start")));
+        }
+        Ok(project)
+    }
+
+    /// Load project file.
+    pub fn load(filename: &str) -> Result<Project> {
+        let mut text = String::new();
+        File::open(Path::new(filename))?.read_to_string(&mut text)?;
+        Project::parse(&text)
+    }
+
+    /// Save project file.
+    pub fn save(&self, filename: &str) -> Result<()> {
+        File::create(Path::new(filename))?.write_all(self.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// Load the input file in its format. A relative input path is resolved against the
+    /// directory of the project file.
+    pub fn load_image(&self, project_file: &str) -> Result<MemoryImage> {
+        let path = Path::new(project_file).parent().unwrap_or(Path::new("")).join(&self.input);
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        self.image(&bytes)
+    }
+
+    /// Read the contents of the input file in its format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::banking::BankedAddress;
+    /// use disasm6502::project::{InputFormat, Project};
+    ///
+    /// let mut project = Project::new("bank1.bin");
+    /// project.format = Some(InputFormat::Binary);
+    /// project.load_address = Some(BankedAddress::new(1, 0x8000));
+    ///
+    /// let image = project.image(&[0xEA, 0x60]).unwrap();
+    /// assert_eq!(image.segments()[0].to_string(), "01:$8000-$8001 (2 bytes)");
+    /// ```
+    pub fn image(&self, bytes: &[u8]) -> Result<MemoryImage> {
+        let format = self.format.unwrap_or(if self.load_address.is_some() { InputFormat::Binary } else { InputFormat::Prg });
+        let file = || self.file.as_deref()
+            .ok_or_else(|| Disasm6502Error::InvalidFormat(format!("no file given for {} disk image", format.name())));
+        let load_address = || self.load_address.ok_or_else(|| Disasm6502Error::InvalidFormat(String::from("no load address given")));
+        let prg = |bytes: &[u8]| Prg::parse(bytes).map(|prg| vec![Segment::new(prg.load_address, prg.data)]);
+
+        let segments = match format {
+            InputFormat::Binary => {
+                let at = load_address()?;
+                vec![Segment { bank: at.bank, address: at.address, data: bytes.to_vec() }]
+            },
+            InputFormat::Prg => prg(bytes)?,
+            InputFormat::T64 => T64::parse(bytes)?.segments(),
+            InputFormat::Crt => Crt::parse(bytes)?.segments(),
+            InputFormat::Nes => Nes::parse(bytes)?.mapped_segments(),
+            InputFormat::Nsf => Nsf::parse(bytes)?.segments(),
+            InputFormat::Sid => vec![Sid::parse(bytes)?.segment()],
+            InputFormat::Xex => return Ok(Xex::parse(bytes)?.memory_image()),
+            InputFormat::Hex => HexFile::parse(&String::from_utf8_lossy(bytes))?.segments,
+            InputFormat::D64 => prg(&D64::parse(bytes)?.read_file_by_name(file()?)?.data)?,
+            InputFormat::Apple2 => {
+                let apple_file = DiskImage::parse(bytes, None)?.read_file_by_name(file()?)?;
+                match apple_file.segment() {
+                    Some(segment) => vec![segment],
+                    None => vec![Segment::new(load_address()?.address, apple_file.data)]
+                }
+            },
+            InputFormat::Ssd | InputFormat::Dsd => {
+                let dfs = Dfs::parse(bytes, format == InputFormat::Dsd)?;
+                let entry = dfs.find(file()?).ok_or_else(|| Disasm6502Error::FileNotFound(file().unwrap_or("").to_string()))?;
+                vec![dfs.segment(&entry)?]
+            }
+        };
+        Ok(MemoryImage::from(segments))
+    }
+
+    /// Analyze the image from the entry points and the starts of code regions. Data regions and,
+    /// for the documented instruction set, undocumented opcodes are not treated as code.
+    pub fn analyze(&self, image: &MemoryImage) -> CodeMap {
+        let mut entry_points = self.entry_points.clone();
+        entry_points.extend(self.regions.iter().filter(|r| r.region_type == RegionType::Code).map(|r| r.start));
+
+        let data: Vec<&RegionOverride> = self.regions.iter().filter(|r| r.region_type == RegionType::Data).collect();
+        analysis::analyze_filtered(image, &BankLayout::new(), &entry_points, &[], |instruction| {
+            if self.cpu == CpuVariant::Documented6502 && instruction.illegal {
+                return false;
+            }
+
+            let start = instruction.banked_address();
+            !(0..instruction.size()).any(|i| {
+                let at = BankedAddress { address: start.address.wrapping_add(i), ..start };
+                data.iter().any(|r| r.contains(at))
+            })
+        })
+    }
+
+    /// Symbols of the platform (if any) with the project labels and descriptions.
+    pub fn all_symbols(&self) -> SymbolTable {
+        let mut symbols = self.platform.map_or(SymbolTable::new(), |p| p.symbols());
+        symbols.extend(self.symbols.clone());
+        symbols
+    }
+
+    /// Encoding of text: the project encoding, else the platform encoding, else ASCII.
+    pub fn text_encoding(&self) -> Encoding {
+        self.encoding.or_else(|| self.platform.map(|p| p.encoding())).unwrap_or(Encoding::Ascii)
+    }
+
+    /// Analyze and disassemble the image, applying all annotations. Immediate operands forming
+    /// addresses are shown as low and high bytes of the target label unless their format is
+    /// given (see `pointers`). Strings in data and `char` operands use the text encoding.
+    pub fn listing(&self, image: &MemoryImage) -> Vec<String> {
+        let map = self.analyze(image);
+        let lines = analysis::disassemble_image(image, &map);
+        let encoding = self.text_encoding();
+        let strings = text::find_strings(&lines, encoding, text::DEFAULT_MIN_LENGTH);
+        let lines = text::apply(&lines, &strings);
+
+        let mut symbols = self.all_symbols();
+        let mut annotations = self.annotations.clone();
+        pointers::apply(&pointers::find_pointer_pairs(&lines), &mut symbols, &mut annotations);
+        if encoding != Encoding::Ascii {
+            for format in annotations.operand_formats.values_mut().filter(|f| **f == OperandFormat::Char) {
+                *format = OperandFormat::EncodedChar(encoding);
+            }
+        }
+
+        listing::format_annotated(&lines, &symbols, &annotations)
+    }
+
+    // assign a value read from the file
+    fn set(&mut self, table: &str, key: &str, value: Value) -> ::std::result::Result<(), String> {
+        let address = |key: &str| BankedAddress::parse(key).ok_or_else(|| format!("invalid address \"{}\"", key));
+
+        match (table, key, value) {
+            ("", "input", Value::Text(v)) => self.input = v,
+            ("", "format", Value::Text(v)) => self.format = Some(InputFormat::parse(&v).ok_or_else(|| format!("unknown format \"{}\"", v))?),
+            ("", "file", Value::Text(v)) => self.file = Some(v),
+            ("", "load_address", Value::Text(v)) => self.load_address = Some(address(&v)?),
+            ("", "cpu", Value::Text(v)) => self.cpu = CpuVariant::parse(&v).ok_or_else(|| format!("unknown CPU \"{}\"", v))?,
+            ("", "platform", Value::Text(v)) => self.platform = Some(Platform::parse(&v).ok_or_else(|| format!("unknown platform \"{}\"", v))?),
+            ("", "encoding", Value::Text(v)) => self.encoding = Some(Encoding::parse(&v).ok_or_else(|| format!("unknown encoding \"{}\"", v))?),
+            ("", "entry_points", Value::List(v)) => {
+                self.entry_points = v.iter().map(|a| address(a)).collect::<::std::result::Result<Vec<_>, String>>()?;
+            },
+            ("labels", _, Value::Text(v)) => self.symbols.insert_banked(address(key)?, &v),
+            ("descriptions", _, Value::Text(v)) => self.symbols.describe_banked(address(key)?, &v),
+            ("comments", _, Value::Text(v)) => { self.annotations.comments.insert(address(key)?, v); },
+            ("block_comments", _, Value::Text(v)) => { self.annotations.block_comments.insert(address(key)?, v); },
+            ("operands", _, Value::Text(v)) => {
+                let format = OperandFormat::parse(&v).ok_or_else(|| format!("unknown operand format \"{}\"", v))?;
+                self.annotations.operand_formats.insert(address(key)?, format);
+            },
+            _ => return Err(format!("unexpected key \"{}\"", key))
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Project {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# disasm6502 project")?;
+        writeln!(f, "input = {}", quote(&self.input))?;
+        if let Some(format) = self.format {
+            writeln!(f, "format = {}", quote(format.name()))?;
+        }
+        if let Some(ref file) = self.file {
+            writeln!(f, "file = {}", quote(file))?;
+        }
+        if let Some(address) = self.load_address {
+            writeln!(f, "load_address = \"{}\"", address)?;
+        }
+        writeln!(f, "cpu = {}", quote(self.cpu.name()))?;
+        if let Some(platform) = self.platform {
+            writeln!(f, "platform = {}", quote(platform.name()))?;
+        }
+        if let Some(encoding) = self.encoding {
+            writeln!(f, "encoding = {}", quote(encoding.name()))?;
+        }
+        let entry_points: Vec<String> = self.entry_points.iter().map(|e| quote(&e.to_string())).collect();
+        writeln!(f, "entry_points = [{}]", entry_points.join(", "))?;
+
+        for region in self.regions.iter() {
+            writeln!(f, "\n[[region]]")?;
+            writeln!(f, "start = \"{}\"", region.start)?;
+            writeln!(f, "end = \"${:04X}\"", region.end)?;
+            writeln!(f, "type = \"{}\"", if region.region_type == RegionType::Code { "code" } else { "data" })?;
+        }
+
+        table(f, "labels", self.symbols.iter())?;
+        table(f, "descriptions", self.symbols.descriptions())?;
+        table(f, "comments", self.annotations.comments.iter())?;
+        table(f, "block_comments", self.annotations.block_comments.iter())?;
+        let operands: Vec<(BankedAddress, String)> = self.annotations.operand_formats.iter().map(|(&a, o)| (a, o.to_string())).collect();
+        table(f, "operands", operands.iter().map(|(a, o)| (a, o)))
+    }
+}
+
+// table of strings keyed by address, omitted if empty
+fn table<'a, I>(f: &mut fmt::Formatter, name: &str, entries: I) -> fmt::Result where I: Iterator<Item = (&'a BankedAddress, &'a String)> {
+    let mut entries = entries.peekable();
+    if entries.peek().is_some() {
+        writeln!(f, "\n[{}]", name)?;
+    }
+    for (address, value) in entries {
+        writeln!(f, "\"{}\" = {}", address, quote(value))?;
+    }
+    Ok(())
+}
+
+// [[region]] table with the values read so far
+struct PartialRegion {
+    line: usize,
+    start: Option<BankedAddress>,
+    end: Option<u16>,
+    region_type: Option<RegionType>
+}
+
+impl PartialRegion {
+    fn set(&mut self, key: &str, value: Value) -> ::std::result::Result<(), String> {
+        let v = match value {
+            Value::Text(v) => v,
+            Value::List(_) => return Err(format!("unexpected key \"{}\"", key))
+        };
+        let address = || BankedAddress::parse(&v).ok_or_else(|| format!("invalid address \"{}\"", v));
+
+        match key {
+            "start" => self.start = Some(address()?),
+            "end" => self.end = Some(address()?.address),
+            "type" if v == "code" => self.region_type = Some(RegionType::Code),
+            "type" if v == "data" => self.region_type = Some(RegionType::Data),
+            _ => return Err(format!("invalid region {} \"{}\"", key, v))
+        }
+        Ok(())
+    }
+}
+
+fn invalid(index: usize, message: &str) -> Disasm6502Error {
+    Disasm6502Error::InvalidFormat(format!("line {}: {}", index, message))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    Text(String),
+    List(Vec<String>)
+}
+
+fn quote(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c => result.push(c)
+        }
+    }
+    result.push('"');
+    result
+}
+
+// key = "value" or key = ["value", ...], keys may be quoted, comments may follow
+fn key_value(line: &str) -> Option<(String, Value)> {
+    let mut chars = line.chars().peekable();
+    let key = if chars.peek() == Some(&'"') {
+        string(&mut chars)?
+    } else {
+        let mut key = String::new();
+        while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_' || **c == '-') {
+            key.push(c);
+            chars.next();
+        }
+        key
+    };
+
+    skip_spaces(&mut chars);
+    if key.is_empty() || chars.next() != Some('=') {
+        return None;
+    }
+    skip_spaces(&mut chars);
+
+    let value = match chars.peek() {
+        Some('"') => Value::Text(string(&mut chars)?),
+        Some('[') => {
+            chars.next();
+            let mut list = Vec::new();
+            loop {
+                skip_spaces(&mut chars);
+                match chars.peek() {
+                    Some(']') => { chars.next(); break; },
+                    Some('"') => list.push(string(&mut chars)?),
+                    _ => return None
+                }
+                skip_spaces(&mut chars);
+                if chars.peek() == Some(&',') {
+                    chars.next();
+                }
+            }
+            Value::List(list)
+        },
+        _ => return None
+    };
+
+    skip_spaces(&mut chars);
+    match chars.next() {
+        None | Some('#') => Some((key, value)),
+        _ => None
+    }
+}
+
+fn skip_spaces<I: Iterator<Item = char>>(chars: &mut ::std::iter::Peekable<I>) {
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+// quoted string with escapes
+fn string<I: Iterator<Item = char>>(chars: &mut ::std::iter::Peekable<I>) -> Option<String> {
+    if chars.next() != Some('"') {
+        return None;
+    }
+
+    let mut result = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(result),
+            '\\' => result.push(match chars.next()? {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '"' => '"',
+                '\\' => '\\',
+                _ => return None
+            }),
+            c => result.push(c)
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Atari 2600 (6507) scanline timing analysis.
+//!
+//! Follows every path through a kernel starting at a given entry point and accumulates CPU
+//! cycles between `STA WSYNC` writes. Each line is reported with its cycle count and the
+//! position of every TIA register write, so lines that exceed the 76 cycle budget can be spotted
+//! before running the code on real hardware.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use instruction::{AddrMode, Instruction};
+use instruction::OpCode::*;
+
+/// TIA WSYNC (wait for horizontal sync) register.
+pub const WSYNC: u8 = 0x02;
+
+/// Number of CPU cycles in a single scanline.
+pub const CYCLES_PER_LINE: u32 = 76;
+
+/// Number of color clocks per CPU cycle.
+pub const CLOCKS_PER_CYCLE: u32 = 3;
+
+/// Number of color clocks spent in horizontal blank before the visible part of a line.
+pub const HBLANK_CLOCKS: u32 = 68;
+
+/// Names and descriptions of TIA write registers, indexed by register number.
+pub const TIA_WRITE_REGISTERS: [(&str, &str); 0x2D] = [
+    ("VSYNC", "vertical sync"), ("VBLANK", "vertical blank"), ("WSYNC", "wait for horizontal sync"),
+    ("RSYNC", "reset horizontal sync counter"), ("NUSIZ0", "player/missile 0 size and copies"), ("NUSIZ1", "player/missile 1 size and copies"),
+    ("COLUP0", "player/missile 0 color"), ("COLUP1", "player/missile 1 color"), ("COLUPF", "playfield and ball color"),
+    ("COLUBK", "background color"), ("CTRLPF", "playfield control and ball size"), ("REFP0", "player 0 reflection"),
+    ("REFP1", "player 1 reflection"), ("PF0", "playfield register 0"), ("PF1", "playfield register 1"),
+    ("PF2", "playfield register 2"), ("RESP0", "reset player 0 position"), ("RESP1", "reset player 1 position"),
+    ("RESM0", "reset missile 0 position"), ("RESM1", "reset missile 1 position"), ("RESBL", "reset ball position"),
+    ("AUDC0", "audio 0 control"), ("AUDC1", "audio 1 control"), ("AUDF0", "audio 0 frequency"),
+    ("AUDF1", "audio 1 frequency"), ("AUDV0", "audio 0 volume"), ("AUDV1", "audio 1 volume"),
+    ("GRP0", "player 0 graphics"), ("GRP1", "player 1 graphics"), ("ENAM0", "enable missile 0"),
+    ("ENAM1", "enable missile 1"), ("ENABL", "enable ball"), ("HMP0", "player 0 horizontal motion"),
+    ("HMP1", "player 1 horizontal motion"), ("HMM0", "missile 0 horizontal motion"), ("HMM1", "missile 1 horizontal motion"),
+    ("HMBL", "ball horizontal motion"), ("VDELP0", "vertical delay player 0"), ("VDELP1", "vertical delay player 1"),
+    ("VDELBL", "vertical delay ball"), ("RESMP0", "reset missile 0 to player 0"), ("RESMP1", "reset missile 1 to player 1"),
+    ("HMOVE", "apply horizontal motion"), ("HMCLR", "clear horizontal motion registers"), ("CXCLR", "clear collision latches")
+];
+
+/// Returns TIA register number if the address is mapped to TIA in the 6507 address space.
+pub fn tia_register(address: u16) -> Option<u8> {
+    // TIA is selected when A12 and A7 are both low
+    if address & 0x1080 == 0 {
+        Some((address & 0x3F) as u8)
+    } else {
+        None
+    }
+}
+
+/// Single write to a TIA register.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TiaWrite {
+    /// address of the writing instruction
+    pub address: u16,
+    /// TIA register number
+    pub register: u8,
+    /// CPU cycle of the line on which the write completes
+    pub cycle: u32
+}
+
+impl TiaWrite {
+    /// Color clock of the line on which the write completes.
+    pub fn color_clock(&self) -> u32 {
+        self.cycle * CLOCKS_PER_CYCLE
+    }
+
+    /// Horizontal position of the write relative to the visible part of the line, if any.
+    pub fn pixel(&self) -> Option<u32> {
+        self.color_clock().checked_sub(HBLANK_CLOCKS)
+    }
+
+    /// Register name (None for unused register numbers).
+    pub fn register_name(&self) -> Option<&'static str> {
+        TIA_WRITE_REGISTERS.get(self.register as usize).map(|&(name, _)| name)
+    }
+}
+
+impl fmt::Display for TiaWrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.register_name() {
+            Some(name) => write!(f, "${:04X}: {:<6}", self.address, name)?,
+            None => write!(f, "${:04X}: ${:02X}   ", self.address, self.register)?
+        }
+
+        write!(f, " cycle {:2} clock {:3}", self.cycle, self.color_clock())
+    }
+}
+
+/// Cycle count of a single path between two scanline boundaries.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Scanline {
+    /// address of the first instruction of the line
+    pub start: u16,
+    /// address of the instruction which ended the line (WSYNC write, or the instruction exceeding the budget)
+    pub end: u16,
+    /// CPU cycles spent on the line
+    pub cycles: u32,
+    /// TIA register writes on the line, in execution order
+    pub writes: Vec<TiaWrite>
+}
+
+impl Scanline {
+    /// Line takes more cycles than available on a single scanline.
+    pub fn is_overlong(&self) -> bool {
+        self.cycles > CYCLES_PER_LINE
+    }
+}
+
+impl fmt::Display for Scanline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "${:04X}-${:04X}: {} cycles", self.start, self.end, self.cycles)?;
+        if self.is_overlong() {
+            write!(f, " (exceeds {})", CYCLES_PER_LINE)?;
+        }
+
+        for w in self.writes.iter() {
+            write!(f, "\n    {}", w)?;
+        }
+
+        Ok(())
+    }
+}
+
+// deepest subroutine nesting followed by the path walker
+const MAX_CALL_DEPTH: usize = 16;
+
+// state of the path walker
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct PathState {
+    line_start: u16,
+    pc: u16,
+    cycles: u32,
+    return_stack: Vec<u16>
+}
+
+/// Checks scanline timing of the code reachable from the entry point.
+///
+/// Cycles are accumulated along each path and a new line begins after every `STA WSYNC`.
+/// Taken branches add their extra cycles (including page crossing, which is known statically),
+/// indexed addressing is assumed to always cross a page. Subroutines are followed through
+/// `JSR`/`RTS`. Paths reaching the same point of a line with the same cycle count are merged.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// // loop: STA WSYNC / DEX / BNE loop
+/// let bytes = vec![0x85, 0x02, 0xCA, 0xD0, 0xFB];
+/// let instructions = disasm6502::from_addr_array(&bytes, 0xF000).unwrap();
+///
+/// for line in disasm6502::scanline::check(&instructions, 0xF000) {
+///     println!("{}", line);
+/// }
+/// ```
+pub fn check(instructions: &[Instruction], entry: u16) -> Vec<Scanline> {
+    let by_address: HashMap<u16, &Instruction> = instructions.iter().map(|i| (i.address, i)).collect();
+    let mut lines = Vec::<Scanline>::new();
+    let mut visited = HashSet::<PathState>::new();
+    let mut pending = vec![(PathState { line_start: entry, pc: entry, cycles: 0, return_stack: Vec::new() }, Vec::<TiaWrite>::new())];
+
+    while let Some((state, mut writes)) = pending.pop() {
+        if !visited.insert(state.clone()) {
+            continue;
+        }
+
+        let instruction = match by_address.get(&state.pc) {
+            Some(i) => i,
+            None => continue
+        };
+
+        let next_pc = state.pc.wrapping_add(instruction.size());
+        let cycles = state.cycles + base_cycles(instruction);
+
+        if let Some(register) = written_address(instruction).and_then(tia_register) {
+            writes.push(TiaWrite { address: state.pc, register, cycle: cycles });
+
+            if register == WSYNC {
+                push_line(&mut lines, Scanline { start: state.line_start, end: state.pc, cycles, writes: writes.clone() });
+                let next = PathState { line_start: next_pc, pc: next_pc, cycles: 0, return_stack: state.return_stack.clone() };
+                pending.push((next, Vec::new()));
+                continue;
+            }
+        }
+
+        if cycles > CYCLES_PER_LINE {
+            push_line(&mut lines, Scanline { start: state.line_start, end: state.pc, cycles, writes });
+            continue;
+        }
+
+        let mut follow = |pc: u16, cycles: u32, return_stack: Vec<u16>| {
+            pending.push((PathState { line_start: state.line_start, pc, cycles, return_stack }, writes.clone()));
+        };
+
+        match (&instruction.opcode, &instruction.addr_mode) {
+            (_, &AddrMode::Relative) => {
+                let target = next_pc.wrapping_add(instruction.operand.unwrap_or(0) as i8 as u16);
+                let penalty = if target & 0xFF00 != next_pc & 0xFF00 { 2 } else { 1 };
+                follow(next_pc, cycles, state.return_stack.clone());
+                follow(target, cycles + penalty, state.return_stack.clone());
+            },
+            (&JMP(_), &AddrMode::Absolute) => follow(instruction.operand.unwrap_or(0), cycles, state.return_stack.clone()),
+            (&JSR(_), _) if state.return_stack.len() < MAX_CALL_DEPTH => {
+                let mut return_stack = state.return_stack.clone();
+                return_stack.push(next_pc);
+                follow(instruction.operand.unwrap_or(0), cycles, return_stack);
+            },
+            (&RTS(_), _) => {
+                let mut return_stack = state.return_stack.clone();
+                if let Some(pc) = return_stack.pop() {
+                    follow(pc, cycles, return_stack);
+                }
+            },
+            (&JMP(_), _) | (&JSR(_), _) | (&RTI(_), _) | (&BRK(_), _) | (&HLT(_), _) => {},
+            _ => follow(next_pc, cycles, state.return_stack.clone())
+        }
+    }
+
+    lines.sort_by_key(|l| (l.start, l.end, l.cycles));
+    lines
+}
+
+fn push_line(lines: &mut Vec<Scanline>, line: Scanline) {
+    if !lines.contains(&line) {
+        lines.push(line);
+    }
+}
+
+// cycle count without the optional branch/page crossing cycles
+fn base_cycles(instruction: &Instruction) -> u32 {
+    match instruction.addr_mode {
+        AddrMode::Relative => instruction.cycles as u32 - 2,
+        _ => instruction.cycles as u32
+    }
+}
+
+// statically known address written by the instruction
+fn written_address(instruction: &Instruction) -> Option<u16> {
+    let writes = matches!(instruction.opcode,
+        STA(_) | STX(_) | STY(_) | SAX(_) | ASL(_) | LSR(_) | ROL(_) | ROR(_) | INC(_) | DEC(_) |
+        SLO(_) | RLA(_) | SRE(_) | RRA(_) | DCP(_) | ISC(_));
+
+    match instruction.addr_mode {
+        AddrMode::Zeropage | AddrMode::Absolute if writes => instruction.operand,
+        _ => None
+    }
+}
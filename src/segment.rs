@@ -0,0 +1,52 @@
+//! Address-tagged chunks of data produced by file loaders.
+use std::fmt;
+
+/// Contiguous block of bytes placed at an address, optionally in a specific ROM/RAM bank.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    /// bank number (None if the data is not banked); the unit is set by the loader, iNES
+    /// PRG-ROM is numbered in 16K banks whatever the mapper switches
+    pub bank: Option<u16>,
+    /// address of the first byte
+    pub address: u16,
+    /// segment contents
+    pub data: Vec<u8>
+}
+
+impl Segment {
+    /// Create a segment which is not banked.
+    pub fn new(address: u16, data: Vec<u8>) -> Segment {
+        Segment { bank: None, address, data }
+    }
+
+    /// Create a segment in a specific bank.
+    pub fn banked(bank: u16, address: u16, data: Vec<u8>) -> Segment {
+        Segment { bank: Some(bank), address, data }
+    }
+
+    /// Check if the address falls within the segment.
+    pub fn contains(&self, address: u16) -> bool {
+        (address.wrapping_sub(self.address) as usize) < self.data.len()
+    }
+
+    /// Byte at the given address (None if outside of the segment).
+    pub fn read(&self, address: u16) -> Option<u8> {
+        self.data.get(address.wrapping_sub(self.address) as usize).cloned()
+    }
+
+    /// Little-endian word at the given address (None if any byte is outside of the segment).
+    pub fn read_word(&self, address: u16) -> Option<u16> {
+        Some(self.read(address)? as u16 | (self.read(address.wrapping_add(1))? as u16) << 8)
+    }
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(bank) = self.bank {
+            write!(f, "{:02X}:", bank)?;
+        }
+
+        let last = self.address.wrapping_add(self.data.len().saturating_sub(1) as u16);
+        write!(f, "${:04X}-${:04X} ({} bytes)", self.address, last, self.data.len())
+    }
+}
@@ -0,0 +1,190 @@
+//! PSID/RSID (SID tune) file loader.
+//!
+//! SID files start with a big-endian header (versions 1-4) holding the data offset, load, init
+//! and play addresses and tune metadata. A load address of 0 means the real load address is
+//! stored in the first two bytes of the data (little-endian, like in a PRG file). A play
+//! address of 0 means the init routine installs its own interrupt handler.
+use analysis;
+use error::{Disasm6502Error, Result};
+use fields::{long_be, text, word_be};
+use listing;
+use nsf::{INIT_LABEL, PLAY_LABEL};
+use segment::Segment;
+use symbols::SymbolTable;
+
+/// Address of the first SID chip.
+pub const SID_BASE: u16 = 0xD400;
+
+/// SID register names and descriptions, indexed by offset from the chip's base address.
+pub const SID_REGISTERS: [(&str, &str); 29] = [
+    ("V1_FREQ_LO", "voice 1 frequency low byte"), ("V1_FREQ_HI", "voice 1 frequency high byte"),
+    ("V1_PW_LO", "voice 1 pulse width low byte"), ("V1_PW_HI", "voice 1 pulse width high byte"),
+    ("V1_CTRL", "voice 1 control (waveform, gate)"), ("V1_AD", "voice 1 attack/decay"), ("V1_SR", "voice 1 sustain/release"),
+    ("V2_FREQ_LO", "voice 2 frequency low byte"), ("V2_FREQ_HI", "voice 2 frequency high byte"),
+    ("V2_PW_LO", "voice 2 pulse width low byte"), ("V2_PW_HI", "voice 2 pulse width high byte"),
+    ("V2_CTRL", "voice 2 control (waveform, gate)"), ("V2_AD", "voice 2 attack/decay"), ("V2_SR", "voice 2 sustain/release"),
+    ("V3_FREQ_LO", "voice 3 frequency low byte"), ("V3_FREQ_HI", "voice 3 frequency high byte"),
+    ("V3_PW_LO", "voice 3 pulse width low byte"), ("V3_PW_HI", "voice 3 pulse width high byte"),
+    ("V3_CTRL", "voice 3 control (waveform, gate)"), ("V3_AD", "voice 3 attack/decay"), ("V3_SR", "voice 3 sustain/release"),
+    ("FC_LO", "filter cutoff low bits"), ("FC_HI", "filter cutoff high byte"), ("RES_FILT", "filter resonance and routing"),
+    ("MODE_VOL", "filter mode and volume"), ("POTX", "paddle x"), ("POTY", "paddle y"),
+    ("OSC3", "voice 3 oscillator output"), ("ENV3", "voice 3 envelope output")
+];
+
+/// Contents of a PSID/RSID file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sid {
+    /// file is an RSID (real C64 environment required)
+    pub rsid: bool,
+    /// header version
+    pub version: u16,
+    /// address the data is loaded to
+    pub load_address: u16,
+    /// address of the init routine
+    pub init_address: u16,
+    /// address of the play routine (0 if the tune installs its own interrupt handler)
+    pub play_address: u16,
+    /// number of songs
+    pub songs: u16,
+    /// first song to play (starting at 1)
+    pub start_song: u16,
+    /// speed bits (vertical blank or CIA timer) for the first 32 songs
+    pub speed: u32,
+    /// tune name
+    pub name: String,
+    /// author name
+    pub author: String,
+    /// release year and publisher
+    pub released: String,
+    /// flags (version 2 and later, 0 otherwise)
+    pub flags: u16,
+    /// address of the second SID chip (version 3 and later)
+    pub second_sid: Option<u16>,
+    /// address of the third SID chip (version 4)
+    pub third_sid: Option<u16>,
+    /// tune data (without the embedded load address)
+    pub data: Vec<u8>
+}
+
+impl Sid {
+    /// Parse contents of a PSID/RSID file.
+    pub fn parse(bytes: &[u8]) -> Result<Sid> {
+        let rsid = bytes.starts_with(b"RSID");
+        if !rsid && !bytes.starts_with(b"PSID") || bytes.len() < 0x76 {
+            return Err(Disasm6502Error::InvalidFormat(String::from("missing PSID/RSID signature")));
+        }
+
+        let version = word_be(bytes, 0x04);
+        if !(1..=4).contains(&version) {
+            return Err(Disasm6502Error::InvalidFormat(format!("unsupported SID file version {}", version)));
+        }
+
+        let data_offset = word_be(bytes, 0x06) as usize;
+        let mut data = bytes.get(data_offset..)
+            .ok_or_else(|| Disasm6502Error::InvalidFormat(String::from("data offset outside of the file")))?;
+
+        let mut load_address = word_be(bytes, 0x08);
+        if load_address == 0 {
+            if data.len() < 2 {
+                return Err(Disasm6502Error::InvalidFormat(String::from("missing embedded load address")));
+            }
+            load_address = data[0] as u16 | (data[1] as u16) << 8;
+            data = &data[2..];
+        }
+
+        let init_address = match word_be(bytes, 0x0A) {
+            0 => load_address,
+            address => address
+        };
+
+        let extended = version >= 2 && bytes.len() >= 0x7C;
+        let extra_sid = |offset: usize, min_version: u16| {
+            if extended && version >= min_version { sid_address(bytes[offset]) } else { None }
+        };
+
+        Ok(Sid {
+            rsid,
+            version,
+            load_address,
+            init_address,
+            play_address: word_be(bytes, 0x0C),
+            songs: word_be(bytes, 0x0E),
+            start_song: word_be(bytes, 0x10),
+            speed: long_be(bytes, 0x12),
+            name: text(&bytes[0x16..0x36]),
+            author: text(&bytes[0x36..0x56]),
+            released: text(&bytes[0x56..0x76]),
+            flags: if extended { word_be(bytes, 0x76) } else { 0 },
+            second_sid: extra_sid(0x7A, 3),
+            third_sid: extra_sid(0x7B, 4),
+            data: data.to_vec()
+        })
+    }
+
+    /// Tune data placed at the load address.
+    pub fn segment(&self) -> Segment {
+        Segment::new(self.load_address, self.data.clone())
+    }
+
+    /// Init and play routine addresses (play only if set).
+    pub fn entry_points(&self) -> Vec<u16> {
+        let mut result = vec![self.init_address];
+        if self.play_address != 0 && self.play_address != self.init_address {
+            result.push(self.play_address);
+        }
+        result
+    }
+
+    /// Labels of the init and play routines and of the registers of all SID chips used by the tune.
+    pub fn symbols(&self) -> SymbolTable {
+        let mut symbols = register_symbols(SID_BASE, "SID_");
+        if let Some(address) = self.second_sid {
+            symbols.extend(register_symbols(address, "SID2_"));
+        }
+        if let Some(address) = self.third_sid {
+            symbols.extend(register_symbols(address, "SID3_"));
+        }
+
+        symbols.insert(self.init_address, INIT_LABEL);
+        if self.play_address != 0 {
+            symbols.insert(self.play_address, PLAY_LABEL);
+        }
+        symbols
+    }
+
+    /// Disassemble the tune starting from the init and play routines.
+    pub fn listing(&self) -> Vec<String> {
+        let map = analysis::analyze(&self.data, self.load_address, &self.entry_points(), &[]);
+        listing::format(&analysis::disassemble(&self.data, self.load_address, &map), &self.symbols())
+    }
+}
+
+/// Symbols for the registers of a SID chip at the given address, with names prefixed and
+/// descriptions.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// let symbols = disasm6502::sid::register_symbols(disasm6502::sid::SID_BASE, "SID_");
+/// assert_eq!(symbols.get(0xD418), Some("SID_MODE_VOL"));
+/// ```
+pub fn register_symbols(base: u16, prefix: &str) -> SymbolTable {
+    let mut symbols = SymbolTable::new();
+    for (offset, &(name, description)) in SID_REGISTERS.iter().enumerate() {
+        let address = base.wrapping_add(offset as u16);
+        symbols.insert(address, &format!("{}{}", prefix, name));
+        symbols.describe(address, description);
+    }
+    symbols
+}
+
+// extra SID chips are given as the middle two digits of an even $Dxx0 address
+// in $D420-$D7F0 or $DE00-$DFF0
+fn sid_address(value: u8) -> Option<u16> {
+    match value {
+        0x42..=0x7F | 0xE0..=0xFE if value & 0x01 == 0 => Some(0xD000 | (value as u16) << 4),
+        _ => None
+    }
+}
@@ -0,0 +1,475 @@
+//! Symbol file readers and writers for emulators, assemblers and linkers.
+//!
+//! Labels from VICE monitor label files (also written by ld65 `-Ln` for ca65 projects), ld65
+//! debug info and map files, Mesen `.mlb`, FCEUX `.nl` and DASM/ACME symbol dumps are read into
+//! a `SymbolTable`. Tables from several files can be merged with `SymbolTable::extend`. Values
+//! above $FFFF are split into a 64K bank and an address, as in hex files.
+//!
+//! Labels and descriptions can be written back as VICE monitor commands, Mesen and FCEUX label
+//! files, DASM and ACME symbol lists and CSV, so they can be used in emulator debuggers.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use banking::BankedAddress;
+use error::{Disasm6502Error, Result};
+use ines::{PRG_BANK_SIZE, PRG_ROM_START};
+use symbols::SymbolTable;
+
+/// Symbol file format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SymbolFormat {
+    /// VICE monitor labels: `al C:0810 .loop` (ca65 `.lbl` files)
+    Vice,
+    /// ld65 debug info (`--dbgfile`)
+    Ld65Debug,
+    /// ld65 map file (`-m`), exports list
+    Ld65Map,
+    /// Mesen labels: `P:0810:loop:comment`; PRG-ROM offsets are mapped to 16K banks, the last
+    /// bank of a PRG-ROM of the given size at $C000 and all others at $8000
+    Mesen {
+        /// PRG-ROM size in bytes
+        prg_size: usize
+    },
+    /// FCEUX name list: `$C000#loop#comment`, for the given PRG bank (None for RAM files)
+    Fceux {
+        /// 16K PRG-ROM bank the file describes (`game.nes.0.nl` is bank 0)
+        bank: Option<u16>
+    },
+    /// DASM symbol dump (`-s`): `loop 0810`
+    Dasm,
+    /// ACME symbol list (`-l`): `loop = $0810`
+    Acme,
+    /// comma separated values with a `bank,address,label,description` header
+    Csv
+}
+
+impl SymbolFormat {
+    /// Guess the format from the contents. Mesen files are assumed to describe a 32K PRG-ROM
+    /// and FCEUX files to describe RAM.
+    pub fn detect(text: &str) -> Option<SymbolFormat> {
+        let first = text.lines().map(|l| l.trim()).find(|l| !l.is_empty() && !l.starts_with(';'))?;
+        let prefix = first.split(':').next().unwrap_or("");
+
+        if first == CSV_HEADER {
+            Some(SymbolFormat::Csv)
+        } else if text.contains("Exports list by name:") {
+            Some(SymbolFormat::Ld65Map)
+        } else if first.starts_with("version\t") || first.starts_with("version ") {
+            Some(SymbolFormat::Ld65Debug)
+        } else if first.starts_with("al ") {
+            Some(SymbolFormat::Vice)
+        } else if first.starts_with('$') && first.contains('#') {
+            Some(SymbolFormat::Fceux { bank: None })
+        } else if first.contains(':') && !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_alphabetic()) {
+            Some(SymbolFormat::Mesen { prg_size: 2 * PRG_BANK_SIZE })
+        } else if first.contains('=') {
+            Some(SymbolFormat::Acme)
+        } else {
+            Some(SymbolFormat::Dasm)
+        }
+    }
+}
+
+/// Read a symbol file in the given format.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::symbol_file::{self, SymbolFormat};
+///
+/// let symbols = symbol_file::parse("al C:0810 .loop\nal C:0820 .done\n", SymbolFormat::Vice).unwrap();
+/// assert_eq!(symbols.get(0x0810), Some("loop"));
+/// assert_eq!(symbols.address("done"), Some(0x0820));
+/// ```
+pub fn parse(text: &str, format: SymbolFormat) -> Result<SymbolTable> {
+    let mut symbols = SymbolTable::new();
+
+    match format {
+        SymbolFormat::Vice => parse_vice(text, &mut symbols)?,
+        SymbolFormat::Ld65Debug => parse_ld65_debug(text, &mut symbols)?,
+        SymbolFormat::Ld65Map => parse_ld65_map(text, &mut symbols)?,
+        SymbolFormat::Mesen { prg_size } => parse_mesen(text, prg_size, &mut symbols)?,
+        SymbolFormat::Fceux { bank } => parse_fceux(text, bank, &mut symbols)?,
+        SymbolFormat::Dasm => parse_dasm(text, &mut symbols)?,
+        SymbolFormat::Acme => parse_acme(text, &mut symbols)?,
+        SymbolFormat::Csv => parse_csv(text, &mut symbols)?
+    }
+
+    Ok(symbols)
+}
+
+/// Read a symbol file, detecting its format (see `SymbolFormat::detect`).
+pub fn parse_any(text: &str) -> Result<SymbolTable> {
+    match SymbolFormat::detect(text) {
+        Some(format) => parse(text, format),
+        None => Ok(SymbolTable::new())
+    }
+}
+
+/// Write labels and descriptions in the given format.
+///
+/// Formats without banks (VICE, DASM, ACME) drop the bank of banked labels. VICE, DASM and
+/// ACME files hold no descriptions. FCEUX files hold a single bank, other labels are skipped.
+/// Mesen files map banked and ROM addresses back to PRG-ROM offsets (the reverse of reading).
+/// ld65 files cannot be written.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::symbol_file::{self, SymbolFormat};
+/// use disasm6502::symbols::SymbolTable;
+///
+/// let mut symbols = SymbolTable::new();
+/// symbols.insert(0x0810, "loop");
+/// symbols.describe(0x0810, "main loop");
+///
+/// assert_eq!(symbol_file::export(&symbols, SymbolFormat::Vice).unwrap(), "al C:0810 .loop\n");
+/// assert_eq!(symbol_file::export(&symbols, SymbolFormat::Fceux { bank: None }).unwrap(), "$0810#loop#main loop\n");
+/// ```
+pub fn export(symbols: &SymbolTable, format: SymbolFormat) -> Result<String> {
+    let labels: BTreeMap<BankedAddress, &str> = symbols.iter().map(|(&a, l)| (a, l.as_str())).collect();
+    let descriptions: BTreeMap<BankedAddress, &str> = symbols.descriptions().map(|(&a, d)| (a, d.as_str())).collect();
+    let addresses: BTreeSet<BankedAddress> = labels.keys().chain(descriptions.keys()).cloned().collect();
+    let mut text = String::new();
+
+    if format == SymbolFormat::Ld65Debug || format == SymbolFormat::Ld65Map {
+        return Err(Disasm6502Error::InvalidFormat(String::from("ld65 files cannot be written")));
+    }
+    if format == SymbolFormat::Csv {
+        text.push_str(CSV_HEADER);
+        text.push('\n');
+    }
+
+    for at in addresses.iter() {
+        let label = labels.get(at).cloned();
+        let description = descriptions.get(at).cloned();
+        let _ = match format {
+            SymbolFormat::Vice => match label {
+                Some(label) => writeln!(text, "al C:{:04X} .{}", at.address, label),
+                None => Ok(())
+            },
+            SymbolFormat::Mesen { prg_size } => {
+                let (kind, offset) = mesen_location(*at, prg_size);
+                writeln!(text, "{}:{:04X}:{}{}", kind, offset, label.unwrap_or(""),
+                         description.map_or(String::new(), |d| format!(":{}", d.replace('\n', "\\n"))))
+            },
+            SymbolFormat::Fceux { bank } if at.bank == bank => {
+                writeln!(text, "${:04X}#{}#{}", at.address, label.unwrap_or(""), description.unwrap_or("").replace('\n', " "))
+            },
+            SymbolFormat::Fceux { .. } => Ok(()),
+            SymbolFormat::Dasm => match label {
+                Some(label) => writeln!(text, "{:<24} {:04X}", label, at.address),
+                None => Ok(())
+            },
+            SymbolFormat::Acme => match label {
+                Some(label) => writeln!(text, "{}\t= ${:04X}", label, at.address),
+                None => Ok(())
+            },
+            SymbolFormat::Csv => {
+                writeln!(text, "{},{:04X},{},{}", at.bank.map_or(String::new(), |b| format!("{:02X}", b)), at.address,
+                         csv_field(label.unwrap_or("")), csv_field(description.unwrap_or("")))
+            },
+            SymbolFormat::Ld65Debug | SymbolFormat::Ld65Map => Ok(())
+        };
+    }
+
+    Ok(text)
+}
+
+const CSV_HEADER: &str = "bank,address,label,description";
+
+// memory type and offset of an address in a Mesen label file
+fn mesen_location(at: BankedAddress, prg_size: usize) -> (&'static str, u32) {
+    let last_bank = (prg_size.saturating_sub(1) / PRG_BANK_SIZE) as u32;
+    let offset = (at.address as usize % PRG_BANK_SIZE) as u32;
+
+    match (at.bank, at.address) {
+        (Some(bank), _) => ("P", bank as u32 * PRG_BANK_SIZE as u32 + offset),
+        (None, 0x0000..=0x1FFF) => ("R", at.address as u32 & 0x07FF),
+        (None, 0x6000..=0x7FFF) => ("W", at.address as u32 - 0x6000),
+        (None, 0x8000..=0xBFFF) => ("P", offset),
+        (None, 0xC000..=0xFFFF) => ("P", last_bank * PRG_BANK_SIZE as u32 + offset),
+        (None, _) => ("G", at.address as u32)
+    }
+}
+
+// quote fields containing separators, quotes or line breaks
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// non-empty lines without comments, with their line numbers (starting at 1)
+fn lines<'a>(text: &'a str, comment: &'a str) -> impl Iterator<Item = (usize, &'a str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(move |&(_, l)| !l.is_empty() && !l.starts_with(comment))
+}
+
+fn invalid(index: usize, message: &str) -> Disasm6502Error {
+    Disasm6502Error::InvalidFormat(format!("line {}: {}", index, message))
+}
+
+fn hex(text: &str) -> Option<u32> {
+    let text = text.trim().trim_start_matches('$');
+    let text = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")).unwrap_or(text);
+    u32::from_str_radix(text, 16).ok()
+}
+
+// values above $FFFF carry the bank in the upper bits
+fn banked(value: u32) -> BankedAddress {
+    match value >> 16 {
+        0 => BankedAddress::unbanked(value as u16),
+        bank => BankedAddress::new(bank as u16, value as u16)
+    }
+}
+
+// al [C:]0810 .loop
+fn parse_vice(text: &str, symbols: &mut SymbolTable) -> Result<()> {
+    for (index, line) in lines(text, "#") {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 3 || !fields[0].eq_ignore_ascii_case("al") {
+            return Err(invalid(index, "expected \"al <address> <label>\""));
+        }
+
+        let address = match fields[1].find(':') {
+            Some(i) if fields[1][..i].eq_ignore_ascii_case("c") => &fields[1][i + 1..],
+            // labels of other memory spaces (disk drives) do not apply to the computer
+            Some(_) => continue,
+            None => fields[1]
+        };
+        let value = hex(address).ok_or_else(|| invalid(index, "invalid address"))?;
+        symbols.insert_banked(banked(value), fields[2].trim_start_matches('.'));
+    }
+    Ok(())
+}
+
+// sym id=0,name="loop",addrsize=absolute,scope=0,def=1,val=0x810,seg=0,type=lab
+fn parse_ld65_debug(text: &str, symbols: &mut SymbolTable) -> Result<()> {
+    for (index, line) in lines(text, "#") {
+        let (kind, rest) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], &line[i..]),
+            None => return Err(invalid(index, "missing attributes"))
+        };
+        if kind != "sym" {
+            continue;
+        }
+
+        let attributes = attributes(rest.trim());
+        if attributes.get("type").map(|t| t.as_str()) != Some("lab") {
+            continue;
+        }
+
+        let name = attributes.get("name").ok_or_else(|| invalid(index, "symbol without a name"))?;
+        // imported symbols have no value
+        if let Some(value) = attributes.get("val") {
+            let value = hex(value).ok_or_else(|| invalid(index, "invalid symbol value"))?;
+            symbols.insert_banked(banked(value), name);
+        }
+    }
+    Ok(())
+}
+
+// comma separated key=value pairs, values may be quoted
+fn attributes(text: &str) -> BTreeMap<String, String> {
+    let mut result = BTreeMap::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(::std::mem::take(&mut field)),
+            c => field.push(c)
+        }
+    }
+    fields.push(field);
+
+    for field in fields.iter() {
+        if let Some(i) = field.find('=') {
+            result.insert(field[..i].to_string(), field[i + 1..].to_string());
+        }
+    }
+    result
+}
+
+// Exports list by name:
+// ---------------------
+// done                      000820 RLA    loop                      000810 RLA
+fn parse_ld65_map(text: &str, symbols: &mut SymbolTable) -> Result<()> {
+    let mut section = text.lines().enumerate().skip_while(|&(_, l)| !l.starts_with("Exports list by name:")).skip(1);
+
+    for (index, line) in section.by_ref() {
+        let line = line.trim();
+        if line.starts_with('-') {
+            continue;
+        }
+        // the list ends with an empty line
+        if line.is_empty() {
+            break;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() % 3 != 0 {
+            return Err(invalid(index + 1, "expected name, value and flags"));
+        }
+
+        for export in fields.chunks(3) {
+            let value = hex(export[1]).ok_or_else(|| invalid(index + 1, "invalid export value"))?;
+            // only labels (L flag) are addresses, other exports are constants
+            if export[2].contains('L') {
+                symbols.insert_banked(banked(value), export[0]);
+            }
+        }
+    }
+    Ok(())
+}
+
+// P:0810:loop:comment, R:0000-0001:ptr, G:2000:PPUCTRL
+fn parse_mesen(text: &str, prg_size: usize, symbols: &mut SymbolTable) -> Result<()> {
+    let last_bank = prg_size.saturating_sub(1) / PRG_BANK_SIZE;
+
+    for (index, line) in lines(text, ";") {
+        let fields: Vec<&str> = line.splitn(4, ':').collect();
+        if fields.len() < 3 {
+            return Err(invalid(index, "expected \"<type>:<address>:<label>\""));
+        }
+
+        let offset = fields[1].split('-').next().and_then(hex).ok_or_else(|| invalid(index, "invalid address"))?;
+        let at = match fields[0] {
+            "P" | "NesPrgRom" => {
+                let bank = offset as usize / PRG_BANK_SIZE;
+                let window = if bank == last_bank { PRG_ROM_START + PRG_BANK_SIZE as u16 } else { PRG_ROM_START };
+                BankedAddress::new(bank as u16, window + (offset as usize % PRG_BANK_SIZE) as u16)
+            },
+            "R" | "G" | "NesInternalRam" | "NesMemory" | "Register" => BankedAddress::unbanked(offset as u16),
+            "S" | "W" | "NesSaveRam" | "NesWorkRam" => BankedAddress::unbanked(0x6000u16.wrapping_add(offset as u16)),
+            // CHR and other PPU memory is not visible to the CPU
+            _ => continue
+        };
+
+        // labels may be empty if the line only carries a comment
+        if !fields[2].is_empty() {
+            symbols.insert_banked(at, fields[2]);
+        }
+        if let Some(comment) = fields.get(3).filter(|c| !c.is_empty()) {
+            symbols.describe_banked(at, &comment.replace("\\n", "\n"));
+        }
+    }
+    Ok(())
+}
+
+// $C000#loop#comment, $0200/10#buffer#
+fn parse_fceux(text: &str, bank: Option<u16>, symbols: &mut SymbolTable) -> Result<()> {
+    for (index, line) in lines(text, ";") {
+        let fields: Vec<&str> = line.splitn(3, '#').collect();
+        if fields.len() < 2 || !fields[0].starts_with('$') {
+            return Err(invalid(index, "expected \"$<address>#<label>#<comment>\""));
+        }
+
+        let address = fields[0].split('/').next().and_then(hex).ok_or_else(|| invalid(index, "invalid address"))?;
+        let at = BankedAddress { bank, address: address as u16 };
+        if !fields[1].is_empty() {
+            symbols.insert_banked(at, fields[1]);
+        }
+        if let Some(comment) = fields.get(2).map(|c| c.trim_end_matches('#')).filter(|c| !c.is_empty()) {
+            symbols.describe_banked(at, comment);
+        }
+    }
+    Ok(())
+}
+
+// --- Symbol List (sorted by symbol)
+// loop                     0810              (R )
+fn parse_dasm(text: &str, symbols: &mut SymbolTable) -> Result<()> {
+    for (index, line) in lines(text, "---") {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 2 {
+            return Err(invalid(index, "expected \"<label> <value>\""));
+        }
+
+        // string symbols are quoted
+        if fields[1].starts_with('"') {
+            continue;
+        }
+        let value = hex(fields[1]).ok_or_else(|| invalid(index, "invalid symbol value"))?;
+        symbols.insert_banked(banked(value), fields[0]);
+    }
+    Ok(())
+}
+
+// loop	= $0810	; ?
+fn parse_acme(text: &str, symbols: &mut SymbolTable) -> Result<()> {
+    for (index, line) in lines(text, ";") {
+        let line = line.split(';').next().unwrap_or("");
+        let (name, value) = match line.find('=') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(invalid(index, "expected \"<label> = <value>\""))
+        };
+
+        // decimal values have no prefix
+        let value = if value.starts_with('$') { hex(value) } else { value.parse::<u32>().ok() };
+        let value = value.ok_or_else(|| invalid(index, "invalid symbol value"))?;
+        symbols.insert_banked(banked(value), name);
+    }
+    Ok(())
+}
+
+// 02,8000,reset,"power on, cold start"
+fn parse_csv(text: &str, symbols: &mut SymbolTable) -> Result<()> {
+    for (index, line) in csv_records(text).into_iter().enumerate().skip(1) {
+        if line.iter().all(|f| f.is_empty()) {
+            continue;
+        }
+        if line.len() != 4 {
+            return Err(invalid(index + 1, "expected bank, address, label and description"));
+        }
+
+        let address = hex(&line[1]).ok_or_else(|| invalid(index + 1, "invalid address"))?;
+        let at = match line[0].as_str() {
+            "" => BankedAddress::unbanked(address as u16),
+            bank => BankedAddress::new(hex(bank).ok_or_else(|| invalid(index + 1, "invalid bank"))? as u16, address as u16)
+        };
+        if !line[2].is_empty() {
+            symbols.insert_banked(at, &line[2]);
+        }
+        if !line[3].is_empty() {
+            symbols.describe_banked(at, &line[3]);
+        }
+    }
+    Ok(())
+}
+
+// records of fields, quoted fields may contain separators, doubled quotes and line breaks
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => { chars.next(); field.push('"'); },
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(::std::mem::take(&mut field)),
+            '\r' if !quoted => {},
+            '\n' if !quoted => {
+                record.push(::std::mem::take(&mut field));
+                records.push(::std::mem::take(&mut record));
+            },
+            c => field.push(c)
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
@@ -0,0 +1,197 @@
+//! Symbol table mapping addresses to labels.
+//!
+//! Labels are bank-qualified: a label assigned to an address without a bank applies to all
+//! banks that have no label of their own at the same address. Addresses may also carry a
+//! description (e.g. what a hardware register does), and a separate label used by instructions
+//! which only read them (for I/O addresses with different registers on reads and writes).
+use std::collections::BTreeMap;
+use std::collections::btree_map;
+use banking::BankedAddress;
+use bus_cycle::{BusAddress, BusOperation};
+use instruction::Instruction;
+
+/// Labels assigned to addresses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    labels: BTreeMap<BankedAddress, String>,
+    read_labels: BTreeMap<u16, String>,
+    descriptions: BTreeMap<BankedAddress, String>
+}
+
+impl SymbolTable {
+    /// Create an empty symbol table.
+    pub fn new() -> SymbolTable {
+        SymbolTable { labels: BTreeMap::new(), read_labels: BTreeMap::new(), descriptions: BTreeMap::new() }
+    }
+
+    /// Assign a label to the address, replacing the previous one.
+    pub fn insert(&mut self, address: u16, label: &str) {
+        self.insert_banked(BankedAddress::unbanked(address), label);
+    }
+
+    /// Assign a label to the bank-qualified address, replacing the previous one.
+    pub fn insert_banked(&mut self, at: BankedAddress, label: &str) {
+        self.labels.insert(at, label.to_string());
+    }
+
+    /// Remove the label of the address.
+    pub fn remove(&mut self, address: u16) -> Option<String> {
+        self.remove_banked(BankedAddress::unbanked(address))
+    }
+
+    /// Remove the label of the bank-qualified address.
+    pub fn remove_banked(&mut self, at: BankedAddress) -> Option<String> {
+        self.labels.remove(&at)
+    }
+
+    /// Label of the address.
+    pub fn get(&self, address: u16) -> Option<&str> {
+        self.get_banked(BankedAddress::unbanked(address))
+    }
+
+    /// Label of the bank-qualified address, or the label of the address without a bank.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::banking::BankedAddress;
+    /// use disasm6502::symbols::SymbolTable;
+    ///
+    /// let mut symbols = SymbolTable::new();
+    /// symbols.insert(0x8000, "start");
+    /// symbols.insert_banked(BankedAddress::new(2, 0x8000), "level2");
+    ///
+    /// assert_eq!(symbols.get_banked(BankedAddress::new(2, 0x8000)), Some("level2"));
+    /// assert_eq!(symbols.get_banked(BankedAddress::new(1, 0x8000)), Some("start"));
+    /// ```
+    pub fn get_banked(&self, at: BankedAddress) -> Option<&str> {
+        self.labels.get(&at)
+            .or_else(|| self.labels.get(&BankedAddress::unbanked(at.address)))
+            .map(|l| l.as_str())
+    }
+
+    /// Assign a label used by instructions which only read the address, replacing the previous one.
+    pub fn insert_read(&mut self, address: u16, label: &str) {
+        self.read_labels.insert(address, label.to_string());
+    }
+
+    /// Label of the address used by instructions which only read it.
+    pub fn get_read(&self, address: u16) -> Option<&str> {
+        self.read_labels.get(&address).map(|l| l.as_str())
+    }
+
+    /// Address of the label.
+    pub fn address(&self, label: &str) -> Option<u16> {
+        self.banked_address(label).map(|a| a.address)
+    }
+
+    /// Bank-qualified address of the label.
+    pub fn banked_address(&self, label: &str) -> Option<BankedAddress> {
+        self.labels.iter().find(|&(_, l)| l == label).map(|(&a, _)| a)
+    }
+
+    /// Assign a description to the address, replacing the previous one.
+    pub fn describe(&mut self, address: u16, description: &str) {
+        self.describe_banked(BankedAddress::unbanked(address), description);
+    }
+
+    /// Assign a description to the bank-qualified address, replacing the previous one.
+    pub fn describe_banked(&mut self, at: BankedAddress, description: &str) {
+        self.descriptions.insert(at, description.to_string());
+    }
+
+    /// Description of the address.
+    pub fn description(&self, address: u16) -> Option<&str> {
+        self.description_banked(BankedAddress::unbanked(address))
+    }
+
+    /// Description of the bank-qualified address, or the description of the address without a bank.
+    pub fn description_banked(&self, at: BankedAddress) -> Option<&str> {
+        self.descriptions.get(&at)
+            .or_else(|| self.descriptions.get(&BankedAddress::unbanked(at.address)))
+            .map(|d| d.as_str())
+    }
+
+    /// Add all labels and descriptions of another table, replacing existing ones of the same addresses.
+    pub fn extend(&mut self, other: SymbolTable) {
+        self.labels.extend(other.labels);
+        self.read_labels.extend(other.read_labels);
+        self.descriptions.extend(other.descriptions);
+    }
+
+    /// Number of labels.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Check if the table holds no labels.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Labels ordered by bank (unbanked first) and address.
+    pub fn iter(&self) -> btree_map::Iter<'_, BankedAddress, String> {
+        self.labels.iter()
+    }
+
+    /// Descriptions ordered by bank (unbanked first) and address.
+    pub fn descriptions(&self) -> btree_map::Iter<'_, BankedAddress, String> {
+        self.descriptions.iter()
+    }
+
+    /// Convert instruction to assembler mnemonic, using the label of the operand address (if any).
+    /// Instructions which only read the operand address use its read label if it has one.
+    /// Otherwise the address is looked up in the bank of the instruction, then without a bank,
+    /// then in any bank if only one of them has a label there.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::symbols::SymbolTable;
+    ///
+    /// let mut symbols = SymbolTable::new();
+    /// symbols.insert(0x00FB, "ptr");
+    ///
+    /// let memory = vec![0xB1, 0xFB];
+    /// let instruction = disasm6502::instruction::decode(0x0800, &mut 0, &memory);
+    ///
+    /// assert_eq!(symbols.format_instruction(&instruction), "LDA (ptr),Y");
+    ///
+    /// // register with different meanings on reads and writes
+    /// symbols.insert(0x0000, "VSYNC");
+    /// symbols.insert_read(0x0000, "CXM0P");
+    /// let bit = disasm6502::instruction::decode(0x0800, &mut 0, &[0x24, 0x00]);
+    /// let sta = disasm6502::instruction::decode(0x0800, &mut 0, &[0x85, 0x00]);
+    ///
+    /// assert_eq!(symbols.format_instruction(&bit), "BIT CXM0P");
+    /// assert_eq!(symbols.format_instruction(&sta), "STA VSYNC");
+    /// ```
+    pub fn format_instruction(&self, instruction: &Instruction) -> String {
+        let read = |address: u16| if reads_only(instruction) { self.get_read(address) } else { None };
+        match instruction.operand_address().and_then(|a| read(a).or_else(|| self.lookup(instruction.bank, a))) {
+            Some(label) => instruction.as_str_with_label(label),
+            None => instruction.as_str()
+        }
+    }
+
+    fn lookup(&self, bank: Option<u16>, address: u16) -> Option<&str> {
+        if let Some(label) = self.get_banked(BankedAddress { bank, address }) {
+            return Some(label);
+        }
+
+        let mut banked = self.labels.iter().filter(|&(a, _)| a.address == address);
+        match (banked.next(), banked.next()) {
+            (Some((_, label)), None) => Some(label.as_str()),
+            _ => None
+        }
+    }
+}
+
+// instruction reads its effective address without writing it
+fn reads_only(instruction: &Instruction) -> bool {
+    let cycles = instruction.bus_cycles();
+    let effective = |operation: BusOperation| cycles.iter().any(|c| c.address == BusAddress::Effective && c.operation == operation);
+    effective(BusOperation::Read) && !effective(BusOperation::Write)
+}
@@ -0,0 +1,85 @@
+//! T64 tape archive reader.
+//!
+//! T64 files hold a directory of tape files with their start and end addresses, followed by
+//! the file contents. End addresses written by some tools are wrong, so file sizes are taken
+//! from the distance between file offsets in the container instead.
+use error::{Disasm6502Error, Result};
+use fields::{petscii_name, word};
+use segment::Segment;
+
+// directory starts after the 64 byte header, 32 bytes per entry
+const HEADER_SIZE: usize = 0x40;
+const ENTRY_SIZE: usize = 0x20;
+
+/// File stored in a tape archive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TapeFile {
+    /// file name (PETSCII converted for display)
+    pub name: String,
+    /// C64 file type byte (as in 1541 directory entries)
+    pub file_type: u8,
+    /// file contents placed at its start address
+    pub segment: Segment
+}
+
+/// T64 tape archive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct T64 {
+    /// tape name
+    pub name: String,
+    /// files stored in the archive
+    pub files: Vec<TapeFile>
+}
+
+impl T64 {
+    /// Parse T64 file contents.
+    pub fn parse(bytes: &[u8]) -> Result<T64> {
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(b"C64") {
+            return Err(Disasm6502Error::InvalidFormat(String::from("missing T64 signature")));
+        }
+
+        let max_entries = word(bytes, 0x22) as usize;
+        let name = petscii_name(&bytes[0x28..0x40], 0x00).trim_end().to_string();
+
+        // (offset, start address, end address, type, name) of used entries
+        let mut entries = Vec::new();
+        for i in 0..max_entries {
+            let e = match bytes.get(HEADER_SIZE + i * ENTRY_SIZE..HEADER_SIZE + (i + 1) * ENTRY_SIZE) {
+                Some(e) => e,
+                None => break
+            };
+
+            if e[0] == 0 {
+                continue;
+            }
+
+            let offset = e[8] as usize | (e[9] as usize) << 8 | (e[10] as usize) << 16 | (e[11] as usize) << 24;
+            entries.push((offset, word(e, 2), word(e, 4), e[1], petscii_name(&e[0x10..0x20], 0x00).trim_end().to_string()));
+        }
+
+        let mut offsets: Vec<usize> = entries.iter().map(|e| e.0).collect();
+        offsets.push(bytes.len());
+        offsets.sort();
+
+        let mut files = Vec::new();
+        for (offset, start, end, file_type, name) in entries {
+            if offset > bytes.len() {
+                return Err(Disasm6502Error::InvalidFormat(format!("file \"{}\" lies outside of the archive", name)));
+            }
+
+            // data ends at the next file or at the declared end address, whichever comes first
+            let next = offsets.iter().cloned().find(|&o| o > offset).unwrap_or(bytes.len());
+            let declared = end.wrapping_sub(start) as usize;
+            let size = if declared == 0 { next - offset } else { declared.min(next - offset) };
+
+            files.push(TapeFile { name, file_type, segment: Segment::new(start, bytes[offset..offset + size].to_vec()) });
+        }
+
+        Ok(T64 { name, files })
+    }
+
+    /// Segments of all files in the archive.
+    pub fn segments(&self) -> Vec<Segment> {
+        self.files.iter().map(|f| f.segment.clone()).collect()
+    }
+}
@@ -0,0 +1,318 @@
+//! Text string detection in data and character encodings of 8-bit platforms.
+//!
+//! Strings are runs of printable characters in data bytes, decoded as ASCII, PETSCII (upper case
+//! or lower case character set), C64 screen codes, ATASCII or Apple II high-bit ASCII. A string
+//! may end with a zero byte, with the high bit set on its last character, or be preceded by its
+//! length. Strings are shown with the 64tass directives for each kind of termination (`.TEXT`,
+//! `.NULL`, `.SHIFT` and `.PTEXT`). To keep garbage out, a string must contain a letter and more
+//! than one distinct character.
+use std::collections::BTreeMap;
+use std::fmt;
+use analysis::Line;
+use banking::BankedAddress;
+
+/// Minimum number of characters of a detected string used by projects.
+pub const DEFAULT_MIN_LENGTH: usize = 4;
+
+/// Character encoding of text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// 7-bit ASCII
+    Ascii,
+    /// Commodore PETSCII, upper case/graphics character set
+    PetsciiUpper,
+    /// Commodore PETSCII, lower/upper case character set
+    PetsciiLower,
+    /// Commodore 64 screen codes (upper case character set)
+    ScreenCode,
+    /// Atari 8-bit ATASCII
+    Atascii,
+    /// Apple II ASCII with the high bit set
+    AppleHighBit
+}
+
+/// All encodings.
+pub const ENCODINGS: [Encoding; 6] = [Encoding::Ascii, Encoding::PetsciiUpper, Encoding::PetsciiLower,
+                                      Encoding::ScreenCode, Encoding::Atascii, Encoding::AppleHighBit];
+
+impl Encoding {
+    /// Short name of the encoding, as used in project files and in `.ENC` directives of listings.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Encoding::Ascii => "ascii",
+            Encoding::PetsciiUpper => "petscii-upper",
+            Encoding::PetsciiLower => "petscii-lower",
+            Encoding::ScreenCode => "screen",
+            Encoding::Atascii => "atascii",
+            Encoding::AppleHighBit => "apple"
+        }
+    }
+
+    /// Find encoding by its short name (case insensitive).
+    pub fn parse(name: &str) -> Option<Encoding> {
+        ENCODINGS.iter().find(|e| e.name().eq_ignore_ascii_case(name.trim())).cloned()
+    }
+
+    /// Decode a byte to a printable character, None for control codes and graphics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::text::Encoding;
+    ///
+    /// assert_eq!(Encoding::ScreenCode.decode(0x01), Some('A'));
+    /// assert_eq!(Encoding::PetsciiLower.decode(0x41), Some('a'));
+    /// assert_eq!(Encoding::AppleHighBit.decode(0xC1), Some('A'));
+    /// assert_eq!(Encoding::Ascii.decode(0xC1), None);
+    /// ```
+    pub fn decode(&self, byte: u8) -> Option<char> {
+        let c = match (*self, byte) {
+            (Encoding::Ascii, 0x20..=0x7E) => byte,
+            (Encoding::PetsciiUpper, 0x20..=0x5B) | (Encoding::PetsciiUpper, 0x5D) => byte,
+            (Encoding::PetsciiLower, 0x41..=0x5A) => byte + 0x20,
+            (Encoding::PetsciiLower, 0x61..=0x7A) => byte - 0x20,
+            (Encoding::PetsciiLower, 0xC1..=0xDA) => byte - 0x80,
+            (Encoding::PetsciiLower, 0x20..=0x40) | (Encoding::PetsciiLower, 0x5B) | (Encoding::PetsciiLower, 0x5D) => byte,
+            (Encoding::ScreenCode, 0x00..=0x1B) | (Encoding::ScreenCode, 0x1D) => byte + 0x40,
+            (Encoding::ScreenCode, 0x20..=0x3F) => byte,
+            (Encoding::Atascii, 0x20..=0x5F) | (Encoding::Atascii, 0x61..=0x7A) => byte,
+            (Encoding::AppleHighBit, 0xA0..=0xFE) => byte & 0x7F,
+            _ => return None
+        };
+        Some(c as char)
+    }
+
+    /// Byte an assembler should produce for a character, None if the character is not printable
+    /// in the encoding. When several bytes decode to the same character the highest one is
+    /// used (PETSCII shifted letters rather than their copies at $61-$7A).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::text::Encoding;
+    ///
+    /// assert_eq!(Encoding::PetsciiLower.encode('A'), Some(0xC1));
+    /// assert_eq!(Encoding::ScreenCode.encode('a'), None);
+    /// ```
+    pub fn encode(&self, c: char) -> Option<u8> {
+        (0..=0xFF).rev().find(|&b| self.decode(b) == Some(c))
+    }
+
+    /// Runs of consecutive characters encoded as consecutive bytes, as (first character, last
+    /// character, first byte), for assembler character tables.
+    pub fn ranges(&self) -> Vec<(char, char, u8)> {
+        let mut ranges: Vec<(char, char, u8)> = Vec::new();
+        for b in 0..=0xFF {
+            let c = match self.decode(b) {
+                Some(c) if self.encode(c) == Some(b) => c,
+                _ => continue
+            };
+
+            match ranges.last_mut() {
+                // both the character and the byte continue the run
+                Some(range) if range.1 as u32 + 1 == c as u32 && range.2 as u32 + (c as u32 - range.0 as u32) == b as u32 =>
+                    range.1 = c,
+                _ => ranges.push((c, c, b))
+            }
+        }
+        ranges
+    }
+
+    // high bit on the last character marks the end of a string (unused by the character set)
+    fn allows_high_bit_end(&self) -> bool {
+        !matches!(*self, Encoding::PetsciiLower | Encoding::AppleHighBit)
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How the end of a string is marked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Termination {
+    /// string ends at the first non-printable byte
+    Unterminated,
+    /// string is followed by a zero byte
+    Null,
+    /// last character has the high bit set
+    HighBit,
+    /// string is preceded by its length
+    LengthPrefix
+}
+
+/// Text string found in data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextString {
+    /// address of the first byte (the length of length-prefixed strings)
+    pub start: BankedAddress,
+    /// all bytes of the string including terminator and length
+    pub bytes: Vec<u8>,
+    /// decoded characters
+    pub text: String,
+    /// encoding of the characters
+    pub encoding: Encoding,
+    /// how the end of the string is marked
+    pub termination: Termination
+}
+
+impl TextString {
+    /// Assembler directive producing the string, e.g. `.NULL "HELLO"`.
+    pub fn directive(&self) -> String {
+        let directive = match self.termination {
+            Termination::Unterminated => ".TEXT",
+            Termination::Null => ".NULL",
+            Termination::HighBit => ".SHIFT",
+            Termination::LengthPrefix => ".PTEXT"
+        };
+        format!("{} \"{}\"", directive, self.text.replace('"', "\"\""))
+    }
+
+    /// Check if `directive` assembles back to the bytes of the string with the characters
+    /// encoded by `Encoding::encode`.
+    pub fn round_trips(&self) -> bool {
+        let mut bytes: Vec<u8> = match self.text.chars().map(|c| self.encoding.encode(c)).collect() {
+            Some(bytes) => bytes,
+            None => return false
+        };
+
+        match self.termination {
+            Termination::Unterminated => {},
+            Termination::Null => bytes.push(0x00),
+            Termination::HighBit => if let Some(last) = bytes.last_mut() { *last |= 0x80 },
+            Termination::LengthPrefix => bytes.insert(0, self.text.chars().count() as u8)
+        }
+        bytes == self.bytes
+    }
+
+    /// Check if the string covers the bank-qualified address.
+    pub fn contains(&self, at: BankedAddress) -> bool {
+        at.bank == self.start.bank && at.address >= self.start.address &&
+            ((at.address - self.start.address) as usize) < self.bytes.len()
+    }
+}
+
+impl fmt::Display for TextString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hex: Vec<String> = self.bytes.iter().take(3).map(|b| format!("{:02X}", b)).collect();
+        write!(f, "{}: {:<8} {}", self.start, hex.join(" "), self.directive())
+    }
+}
+
+/// Find strings of at least `min_length` characters in the data lines of a disassembly.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::analysis;
+/// use disasm6502::text::{self, Encoding, Termination};
+///
+/// // $C000: RTS, then "HELLO" in screen codes followed by a zero byte
+/// let bytes = vec![0x60, 0x08, 0x05, 0x0C, 0x0C, 0x0F, 0x00];
+/// let map = analysis::analyze(&bytes, 0xC000, &[0xC000], &[]);
+/// let strings = text::find_strings(&analysis::disassemble(&bytes, 0xC000, &map), Encoding::ScreenCode, 4);
+///
+/// assert_eq!(strings.len(), 1);
+/// assert_eq!((strings[0].text.as_str(), strings[0].termination), ("HELLO", Termination::Null));
+/// assert_eq!(strings[0].to_string(), "$C001: 08 05 0C .NULL \"HELLO\"");
+/// ```
+pub fn find_strings(lines: &[Line], encoding: Encoding, min_length: usize) -> Vec<TextString> {
+    let mut strings = Vec::new();
+    let mut run: Vec<(BankedAddress, u8)> = Vec::new();
+
+    for line in lines.iter() {
+        match *line {
+            Line::Data(at, value) => {
+                let adjacent = run.last().map_or(true, |&(last, _)| last.bank == at.bank && last.address.wrapping_add(1) == at.address);
+                if !adjacent {
+                    scan(&run, encoding, min_length.max(1), &mut strings);
+                    run.clear();
+                }
+                run.push((at, value));
+            },
+            _ => {
+                scan(&run, encoding, min_length.max(1), &mut strings);
+                run.clear();
+            }
+        }
+    }
+    scan(&run, encoding, min_length.max(1), &mut strings);
+
+    strings
+}
+
+/// Replace the data lines covered by strings with text lines.
+pub fn apply(lines: &[Line], strings: &[TextString]) -> Vec<Line> {
+    // string covering each byte
+    let mut covered: BTreeMap<BankedAddress, &TextString> = BTreeMap::new();
+    for string in strings.iter() {
+        for offset in 0..string.bytes.len() as u16 {
+            covered.insert(BankedAddress { address: string.start.address.wrapping_add(offset), ..string.start }, string);
+        }
+    }
+
+    lines.iter().filter_map(|line| match *line {
+        Line::Data(at, _) => match covered.get(&at) {
+            Some(string) if string.start == at => Some(Line::Text((*string).clone())),
+            Some(_) => None,
+            None => Some(line.clone())
+        },
+        _ => Some(line.clone())
+    }).collect()
+}
+
+// find strings in consecutive data bytes
+fn scan(run: &[(BankedAddress, u8)], encoding: Encoding, min_length: usize, strings: &mut Vec<TextString>) {
+    // zero bytes end strings even where they decode to a character
+    let printable = |i: usize| run.get(i).and_then(|&(_, b)| if b == 0 { None } else { encoding.decode(b) });
+    let count = |from: usize| (from..run.len()).take_while(|&i| printable(i).is_some()).count();
+    let decode = |from: usize, length: usize| -> String { (from..from + length).filter_map(&printable).collect() };
+
+    let mut i = 0;
+    while i < run.len() {
+        let length = run[i].1 as usize;
+        if length >= min_length && count(i + 1) == length && plausible(&decode(i + 1, length)) {
+            push(strings, &run[i..i + 1 + length], decode(i + 1, length), encoding, Termination::LengthPrefix);
+            i += 1 + length;
+            continue;
+        }
+
+        let length = count(i);
+        let text = decode(i, length);
+        let end = run.get(i + length).map(|&(_, b)| b);
+        let last = end.and_then(|b| if b & 0x80 != 0 { encoding.decode(b & 0x7F) } else { None });
+
+        if let (Some(last), true) = (last, encoding.allows_high_bit_end()) {
+            let text = format!("{}{}", text, last);
+            if length + 1 >= min_length && plausible(&text) {
+                push(strings, &run[i..i + length + 1], text, encoding, Termination::HighBit);
+                i += length + 1;
+                continue;
+            }
+        }
+
+        if length >= min_length && plausible(&text) {
+            let termination = if end == Some(0) { Termination::Null } else { Termination::Unterminated };
+            let size = if termination == Termination::Null { length + 1 } else { length };
+            push(strings, &run[i..i + size], text, encoding, termination);
+            i += size;
+            continue;
+        }
+
+        i += length.max(1);
+    }
+}
+
+fn push(strings: &mut Vec<TextString>, bytes: &[(BankedAddress, u8)], text: String, encoding: Encoding, termination: Termination) {
+    strings.push(TextString { start: bytes[0].0, bytes: bytes.iter().map(|&(_, b)| b).collect(), text, encoding, termination });
+}
+
+// a letter and more than one distinct character
+fn plausible(text: &str) -> bool {
+    text.chars().any(|c| c.is_ascii_alphabetic()) && text.chars().any(|c| !text.starts_with(c))
+}
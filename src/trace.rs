@@ -0,0 +1,236 @@
+//! Execution trace logging in the format used by `nestest.log` (Nintendulator/Mesen).
+//!
+//! Each executed instruction produces a line with program counter, instruction bytes,
+//! disassembly and CPU state before the instruction is executed. Like Nintendulator, the
+//! disassembly shows the memory accessed by the instruction (effective address, pointer and
+//! value) and undocumented opcodes are marked with `*`. The PPU position assumes NTSC timing
+//! (3 dots per CPU cycle, 341 dots per scanline, 262 scanlines) starting at scanline 0, dot 0:
+//!
+//! ```text
+//! C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+//! C72A  B1 89     LDA ($89),Y = 0300 @ 0300 = 89  A:00 X:00 Y:00 P:26 SP:FB PPU: 14, 98 CYC:1666
+//! ```
+//!
+//! Produced traces can be compared against a reference log to find the first divergence.
+use std::fmt;
+use emulator::{Bus, Cpu};
+use instruction::{AddrMode, Instruction, OpCode};
+
+/// Format trace line for an instruction executed from the given CPU state. Memory shown in the
+/// disassembly is read with `Bus::peek` before the instruction is executed.
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+/// use disasm6502::emulator::Cpu;
+///
+/// let mut memory = vec![0; 0x10000];
+/// memory[0xC000..0xC003].copy_from_slice(&[0x4C, 0xF5, 0xC5]);
+///
+/// let mut cpu = Cpu::new();
+/// cpu.pc = 0xC000;
+/// cpu.sp = 0xFD;
+/// cpu.cycles = 7;
+///
+/// let instruction = cpu.fetch(&mut memory);
+/// let line = disasm6502::trace::format_line(&cpu, &instruction, &mut memory);
+/// assert!(line.starts_with("C000  4C F5 C5  JMP $C5F5"));
+/// assert!(line.ends_with("A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7"));
+/// ```
+pub fn format_line<B: Bus>(cpu: &Cpu, instruction: &Instruction, bus: &mut B) -> String {
+    let mut disassembly = instruction.as_str().trim_end().to_string();
+    if let OpCode::ISC(_) = instruction.opcode {
+        // Nintendulator name of ISC
+        disassembly = disassembly.replacen("ISC", "ISB", 1);
+    }
+    disassembly.push_str(&memory_access(cpu, instruction, bus));
+
+    let dots = cpu.cycles * 3;
+    format!("{:04X}  {} {}{:<31} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            instruction.address, instruction.as_hex_str(), if instruction.illegal { "*" } else { " " },
+            disassembly, cpu.a, cpu.x, cpu.y, cpu.p, cpu.sp, dots / 341 % 262, dots % 341, cpu.cycles)
+}
+
+/// Execute a single instruction and return its trace line.
+pub fn step<B: Bus>(cpu: &mut Cpu, bus: &mut B) -> String {
+    let instruction = cpu.fetch(bus);
+    let line = format_line(cpu, &instruction, bus);
+    cpu.step(bus);
+    line
+}
+
+// memory accessed by the instruction as shown by Nintendulator: ` = 00` (value), ` @ 0300 = 89`
+// (indexed address and value), ` = 0300 @ 0300 = 89` (pointer, indexed address and value)
+fn memory_access<B: Bus>(cpu: &Cpu, instruction: &Instruction, bus: &mut B) -> String {
+    let operand = instruction.operand.unwrap_or(0);
+    let mut word = |low: u16, high: u16| bus.peek(low) as u16 | (bus.peek(high) as u16) << 8;
+    // pointers in the zero page wrap within it
+    let zp = |address: u8| (address as u16, address.wrapping_add(1) as u16);
+
+    match instruction.addr_mode {
+        AddrMode::Absolute => match instruction.opcode {
+            OpCode::JMP(_) | OpCode::JSR(_) => String::new(),
+            _ => format!(" = {:02X}", bus.peek(operand))
+        },
+        AddrMode::Zeropage => format!(" = {:02X}", bus.peek(operand)),
+        AddrMode::ZeropageIndexedX | AddrMode::ZeropageIndexedY => {
+            let index = if instruction.addr_mode == AddrMode::ZeropageIndexedX { cpu.x } else { cpu.y };
+            let address = (operand as u8).wrapping_add(index) as u16;
+            format!(" @ {:02X} = {:02X}", address, bus.peek(address))
+        },
+        AddrMode::AbsoluteIndexedX(_) | AddrMode::AbsoluteIndexedY(_) => {
+            let index = if let AddrMode::AbsoluteIndexedX(_) = instruction.addr_mode { cpu.x } else { cpu.y };
+            let address = operand.wrapping_add(index as u16);
+            format!(" @ {:04X} = {:02X}", address, bus.peek(address))
+        },
+        // JMP ($xxFF) reads the high byte from $xx00
+        AddrMode::Indirect => format!(" = {:04X}", word(operand, operand & 0xFF00 | (operand as u8).wrapping_add(1) as u16)),
+        AddrMode::IndexedIndirectX => {
+            let pointer = (operand as u8).wrapping_add(cpu.x);
+            let (low, high) = zp(pointer);
+            let address = word(low, high);
+            format!(" @ {:02X} = {:04X} = {:02X}", pointer, address, bus.peek(address))
+        },
+        AddrMode::IndirectIndexedY(_) => {
+            let (low, high) = zp(operand as u8);
+            let base = word(low, high);
+            let address = base.wrapping_add(cpu.y as u16);
+            format!(" = {:04X} @ {:04X} = {:02X}", base, address, bus.peek(address))
+        },
+        _ => String::new()
+    }
+}
+
+/// CPU state parsed from a trace line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// program counter
+    pub pc: u16,
+    /// instruction bytes
+    pub bytes: Vec<u8>,
+    /// accumulator
+    pub a: u8,
+    /// X index register
+    pub x: u8,
+    /// Y index register
+    pub y: u8,
+    /// processor status register
+    pub p: u8,
+    /// stack pointer
+    pub sp: u8,
+    /// cycle counter (if present in the log)
+    pub cycles: Option<u64>
+}
+
+impl TraceEntry {
+    /// Parse a trace line. Disassembly text and fields other than CPU registers (e.g. PPU position) are ignored.
+    pub fn parse(line: &str) -> Option<TraceEntry> {
+        let pc = u16::from_str_radix(line.get(0..4)?, 16).ok()?;
+        let bytes = line.get(6..14)?
+            .split_whitespace()
+            .map(|b| u8::from_str_radix(b, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+
+        let registers = &line[line.find(" A:")?..];
+        let field = |name: &str| registers.split_whitespace().find(|t| t.starts_with(name)).map(|t| &t[name.len()..]);
+        let register = |name: &str| field(name).and_then(|v| u8::from_str_radix(v, 16).ok());
+
+        Some(TraceEntry {
+            pc,
+            bytes,
+            a: register("A:")?,
+            x: register("X:")?,
+            y: register("Y:")?,
+            p: register("P:")?,
+            sp: register("SP:")?,
+            cycles: field("CYC:").and_then(|v| v.parse().ok())
+        })
+    }
+}
+
+/// First difference between a produced trace and a reference log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// line number (starting at 1)
+    pub line: usize,
+    /// name of the first differing field
+    pub field: &'static str,
+    /// value in the reference log
+    pub expected: String,
+    /// value in the produced trace
+    pub actual: String
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {} is {}, expected {}", self.line, self.field, self.actual, self.expected)
+    }
+}
+
+/// Compare produced trace with a reference log and report the first divergence.
+///
+/// Program counter, instruction bytes, registers and cycle count (if present in both) are
+/// compared, disassembly text is not. Lines which cannot be parsed, and the first line present
+/// in only one of the traces, diverge on the `line` field (a missing line is shown as
+/// `end of trace`).
+///
+/// # Examples
+///
+/// ```
+/// extern crate disasm6502;
+///
+/// let produced = "C000  4C F5 C5  JMP $C5F5   A:00 X:00 Y:00 P:24 SP:FD CYC:7";
+/// let reference = "C000  4C F5 C5  JMP $C5F5   A:00 X:00 Y:00 P:24 SP:FB PPU:  0, 21 CYC:7";
+///
+/// let divergence = disasm6502::trace::compare(produced.lines(), reference.lines()).unwrap();
+/// assert_eq!(divergence.field, "SP");
+/// ```
+pub fn compare<'a, 'b, P, R>(produced: P, reference: R) -> Option<Divergence>
+    where P: IntoIterator<Item = &'a str>, R: IntoIterator<Item = &'b str>
+{
+    let mut produced = produced.into_iter();
+    let mut reference = reference.into_iter();
+
+    for index in 0.. {
+        let divergence = |field: &'static str, actual: String, expected: String| {
+            Some(Divergence { line: index + 1, field, expected, actual })
+        };
+
+        let (actual, expected) = match (produced.next(), reference.next()) {
+            (None, None) => break,
+            (Some(actual), Some(expected)) => (actual, expected),
+            (actual, expected) => {
+                let line = |l: Option<&str>| l.unwrap_or("end of trace").to_string();
+                return divergence("line", line(actual), line(expected));
+            }
+        };
+
+        let (a, e) = match (TraceEntry::parse(actual), TraceEntry::parse(expected)) {
+            (Some(a), Some(e)) => (a, e),
+            _ => return divergence("line", actual.to_string(), expected.to_string())
+        };
+
+        if a.pc != e.pc {
+            return divergence("PC", format!("{:04X}", a.pc), format!("{:04X}", e.pc));
+        }
+        if a.bytes != e.bytes {
+            return divergence("bytes", format!("{:02X?}", a.bytes), format!("{:02X?}", e.bytes));
+        }
+
+        let registers = [("A", a.a, e.a), ("X", a.x, e.x), ("Y", a.y, e.y), ("P", a.p, e.p), ("SP", a.sp, e.sp)];
+        for &(name, actual, expected) in registers.iter() {
+            if actual != expected {
+                return divergence(name, format!("{:02X}", actual), format!("{:02X}", expected));
+            }
+        }
+
+        if let (Some(actual), Some(expected)) = (a.cycles, e.cycles) {
+            if actual != expected {
+                return divergence("CYC", actual.to_string(), expected.to_string());
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,160 @@
+//! Atari 8-bit DOS binary (XEX) loader.
+//!
+//! Binary files are a sequence of segments, each with a start and end address (inclusive),
+//! optionally preceded by a $FFFF marker (mandatory for the first one). Segments written to
+//! INITAD ($02E2) hold init routines called as soon as the segment is loaded; a segment written
+//! to RUNAD ($02E0) holds the address jumped to after the whole file is loaded.
+use analysis;
+use banking::{BankedAddress, BankLayout};
+use error::{Disasm6502Error, Result};
+use fields::word;
+use listing;
+use memory_image::MemoryImage;
+use segment::Segment;
+use symbols::SymbolTable;
+
+/// Run address vector.
+pub const RUNAD: u16 = 0x02E0;
+
+/// Init address vector.
+pub const INITAD: u16 = 0x02E2;
+
+// segment header marker
+const MARKER: u16 = 0xFFFF;
+
+/// Contents of a DOS binary file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xex {
+    /// segments in file order (including the ones writing to RUNAD/INITAD)
+    pub segments: Vec<Segment>,
+    /// init routines, in the order they are called
+    pub init_addresses: Vec<u16>,
+    /// address jumped to after loading (last value written to RUNAD)
+    pub run_address: Option<u16>
+}
+
+impl Xex {
+    /// Parse contents of a DOS binary file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate disasm6502;
+    /// use disasm6502::xex::Xex;
+    ///
+    /// // $2000: RTS, RUNAD = $2000
+    /// let bytes = vec![0xFF, 0xFF, 0x00, 0x20, 0x00, 0x20, 0x60,
+    ///                  0xE0, 0x02, 0xE1, 0x02, 0x00, 0x20];
+    /// let xex = Xex::parse(&bytes).unwrap();
+    ///
+    /// assert_eq!(xex.segments.len(), 2);
+    /// assert_eq!(xex.run_address, Some(0x2000));
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Result<Xex> {
+        if bytes.len() < 2 || word(bytes, 0) != MARKER {
+            return Err(Disasm6502Error::InvalidFormat(String::from("missing $FFFF header")));
+        }
+
+        let mut xex = Xex { segments: Vec::new(), init_addresses: Vec::new(), run_address: None };
+        let mut offset = 0;
+        while offset < bytes.len() {
+            if offset + 2 <= bytes.len() && word(bytes, offset) == MARKER {
+                offset += 2;
+            }
+
+            if offset + 4 > bytes.len() {
+                return Err(Disasm6502Error::InvalidFormat(format!("truncated segment header at offset ${:X}", offset)));
+            }
+
+            let start = word(bytes, offset);
+            let end = word(bytes, offset + 2);
+            if end < start {
+                return Err(Disasm6502Error::InvalidFormat(format!("segment ${:04X}-${:04X} ends before it starts", start, end)));
+            }
+
+            offset += 4;
+            let size = (end - start) as usize + 1;
+            let data = bytes.get(offset..offset + size)
+                .ok_or_else(|| Disasm6502Error::InvalidFormat(format!("truncated segment ${:04X}-${:04X}", start, end)))?;
+            offset += size;
+
+            let segment = Segment::new(start, data.to_vec());
+            if let Some(address) = segment.read_word(INITAD) {
+                xex.init_addresses.push(address);
+            }
+            if let Some(address) = segment.read_word(RUNAD) {
+                xex.run_address = Some(address);
+            }
+            xex.segments.push(segment);
+        }
+
+        Ok(xex)
+    }
+
+    /// Init routines followed by the run address.
+    pub fn entry_points(&self) -> Vec<u16> {
+        let mut result = self.init_addresses.clone();
+        result.extend(self.run_address);
+        result
+    }
+
+    /// Labels of the vectors and of the routines they point to, in the banks of the segments
+    /// loaded at their addresses when they are called (see `memory_image`).
+    pub fn symbols(&self) -> SymbolTable {
+        let mut symbols = SymbolTable::new();
+        symbols.insert(RUNAD, "RUNAD");
+        symbols.insert(INITAD, "INITAD");
+        for (at, label) in self.entries() {
+            symbols.insert_banked(at, &label);
+        }
+        symbols
+    }
+
+    /// Code and data segments (without the ones consisting only of RUNAD/INITAD vectors).
+    /// Segments loaded over another one are kept apart in banks numbered with their index in
+    /// `segments` (see `MemoryImage::with_overlays`).
+    pub fn memory_image(&self) -> MemoryImage {
+        let image = MemoryImage::with_overlays(self.segments.clone());
+        MemoryImage::from(image.segments().iter().filter(|s| {
+            let last = s.address.wrapping_add(s.data.len() as u16 - 1);
+            s.address < RUNAD || last > INITAD + 1
+        }).cloned().collect::<Vec<Segment>>())
+    }
+
+    /// Disassemble every segment at its address starting from the entry points, following
+    /// control flow between segments.
+    pub fn listing(&self) -> Vec<String> {
+        let image = self.memory_image();
+        let entry_points: Vec<BankedAddress> = self.entries().into_iter().map(|(at, _)| at).collect();
+        let map = analysis::analyze_banked(&image, &BankLayout::new(), &entry_points, &[]);
+        listing::format(&analysis::disassemble_image(&image, &map), &self.symbols())
+    }
+
+    // init routines and the run address with their labels, in the segments loaded at their
+    // addresses when they are called
+    fn entries(&self) -> Vec<(BankedAddress, String)> {
+        let image = MemoryImage::with_overlays(self.segments.clone());
+        let loaded = |i: usize, address: u16| {
+            let bank = image.segments()[..=i].iter().rev().find(|s| s.contains(address)).and_then(|s| s.bank);
+            BankedAddress { bank, address }
+        };
+
+        let mut entries = Vec::new();
+        let mut run = None;
+        for (i, segment) in self.segments.iter().enumerate() {
+            if let Some(address) = segment.read_word(INITAD) {
+                let label = if entries.is_empty() { String::from("INIT") } else { format!("INIT{}", entries.len() + 1) };
+                entries.push((loaded(i, address), label));
+            }
+            if let Some(address) = segment.read_word(RUNAD) {
+                run = Some(address);
+            }
+        }
+
+        // the run address is jumped to after the whole file is loaded
+        if let Some(address) = run {
+            entries.push((loaded(self.segments.len() - 1, address), String::from("RUN")));
+        }
+        entries
+    }
+}
@@ -27,7 +27,7 @@ fn check_disasm_file() {
             for r in reg_read.iter() {
                 r_str.push_str(format!("{}", r).as_str());
             }
-            r_str.push_str("]");
+            r_str.push(']');
             r_str.to_owned()
         } else {
             String::from("          ")
@@ -38,7 +38,7 @@ fn check_disasm_file() {
             for r in reg_written.iter() {
                 r_str.push_str(format!("{}", r).as_str());
             }
-            r_str.push_str("]");
+            r_str.push(']');
             r_str.to_owned()
         } else {
             String::from("")
@@ -49,7 +49,7 @@ fn check_disasm_file() {
             for f in aff_flags.iter() {
                 f_str.push_str(format!("{}", f).as_str());
             }
-            f_str.push_str("]");
+            f_str.push(']');
             f_str.to_owned()
         } else {
             String::from("")
@@ -73,7 +73,7 @@ fn check_relative_addressing() {
         start: u16,
         bytes: &'static [u8],
         expect: u16
-    };
+    }
     static TESTS: [RelativeTest; 60] = [
         RelativeTest { start: 0x0000, bytes: &[BEQ, 0xFD], expect: 0xFFFF },
         RelativeTest { start: 0x0000, bytes: &[BEQ, 0xFE], expect: 0x0000 },
@@ -139,9 +139,9 @@ fn check_relative_addressing() {
 
     for test in TESTS.iter() {
         // Disassemble the instruction
-        let instructions = disasm6502::from_addr_array(&test.bytes, test.start).unwrap();
+        let instructions = disasm6502::from_addr_array(test.bytes, test.start).unwrap();
         assert_eq!(instructions.len(), 1);
-        let disassembly = format!("{}", instructions.get(0).unwrap());
+        let disassembly = format!("{}", instructions.first().unwrap());
         // Extract the address of the branch target
         let address = u16::from_str_radix(
             disassembly
@@ -155,3 +155,1451 @@ fn check_relative_addressing() {
         assert_eq!(address, test.expect);
     }
 }
+
+#[test]
+fn check_undocumented_operands() {
+    // $0000: NOP $10 (zero page, 2 bytes)
+    // $0002: ARR #$7F
+    let instructions = disasm6502::from_addr_array(&[0x44, 0x10, 0x6B, 0x7F], 0x0000).unwrap();
+
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(instructions[0].as_str(), "NOP $10");
+    assert_eq!(instructions[1].address, 0x0002);
+    assert_eq!(instructions[1].as_str(), "ARR #$7F");
+}
+
+#[test]
+fn check_scanline_timing() {
+    // $F000: LDX #$C0
+    // $F002: STA WSYNC
+    // $F004: LDA #$0E
+    // $F006: STA COLUBK
+    // $F008: DEX
+    // $F009: BNE $F002
+    // $F00B: RTS
+    let bytes = vec![0xA2, 0xC0, 0x85, 0x02, 0xA9, 0x0E, 0x85, 0x09, 0xCA, 0xD0, 0xF7, 0x60];
+    let instructions = disasm6502::from_addr_array(&bytes, 0xF000).unwrap();
+    let lines = disasm6502::scanline::check(&instructions, 0xF000);
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!((lines[0].start, lines[0].end, lines[0].cycles), (0xF000, 0xF002, 5));
+    assert_eq!((lines[1].start, lines[1].end, lines[1].cycles), (0xF004, 0xF002, 13));
+
+    let writes = &lines[1].writes;
+    assert_eq!(writes.len(), 2);
+    assert_eq!(writes[0].register_name(), Some("COLUBK"));
+    assert_eq!(writes[0].color_clock(), 15);
+    assert_eq!(writes[1].register_name(), Some("WSYNC"));
+    assert!(lines.iter().all(|l| !l.is_overlong()));
+
+    // $F000: DEX / BNE $F000 - never reaches WSYNC
+    let instructions = disasm6502::from_addr_array(&[0xCA, 0xD0, 0xFD], 0xF000).unwrap();
+    let lines = disasm6502::scanline::check(&instructions, 0xF000);
+    assert!(!lines.is_empty());
+    assert!(lines.iter().all(|l| l.is_overlong()));
+}
+
+#[test]
+fn check_emulator_program() {
+    use disasm6502::emulator::Cpu;
+
+    let mut memory = vec![0; 0x10000];
+    let program = [
+        0xA2, 0x05,       // $0400: LDX #$05
+        0xA9, 0x00,       // $0402: LDA #$00
+        0x20, 0x20, 0x04, // $0404: JSR $0420
+        0xCA,             // $0407: DEX
+        0xD0, 0xFA,       // $0408: BNE $0404
+        0x85, 0x10,       // $040A: STA $10
+        0x00, 0xEA,       // $040C: BRK
+        0x6C, 0xFF, 0x04, // $040E: JMP ($04FF) - pointer wraps within the page
+    ];
+    memory[0x0400..0x0400 + program.len()].copy_from_slice(&program);
+    // $0420: CLC / ADC #$03 / RTS
+    memory[0x0420..0x0424].copy_from_slice(&[0x18, 0x69, 0x03, 0x60]);
+    // $0430: INC $11 / RTI
+    memory[0x0430..0x0433].copy_from_slice(&[0xE6, 0x11, 0x40]);
+    memory[0x04FF] = 0x00;
+    memory[0x0500] = 0x99;
+    // NMI, RESET and IRQ/BRK vectors
+    memory[0xFFFA..0x10000].copy_from_slice(&[0x30, 0x04, 0x00, 0x04, 0x30, 0x04]);
+
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut memory);
+    assert_eq!((cpu.pc, cpu.sp, cpu.p, cpu.cycles), (0x0400, 0xFD, 0x24, 7));
+
+    while cpu.pc != 0x040E {
+        cpu.step(&mut memory);
+    }
+    assert_eq!(memory[0x10], 15);
+    assert_eq!(memory[0x11], 1);
+    assert_eq!(cpu.sp, 0xFD);
+
+    // JMP ($04FF) reads the high byte from $0400, not $0500
+    cpu.step(&mut memory);
+    assert_eq!(cpu.pc, 0xA200);
+
+    // interrupts disabled after reset
+    assert!(!cpu.irq(&mut memory));
+    cpu.nmi(&mut memory);
+    assert_eq!(memory[0x01FB] & 0x30, 0x20);
+    assert_eq!(cpu.pc, 0x0430);
+}
+
+#[test]
+fn check_emulator_halt() {
+    use disasm6502::emulator::{Bus, Cpu};
+    use disasm6502::instruction::OpCode;
+
+    // memory which counts bus reads
+    struct Counting(Vec<u8>, usize);
+    impl Bus for Counting {
+        fn read(&mut self, address: u16) -> u8 {
+            self.1 += 1;
+            self.0.read(address)
+        }
+        fn write(&mut self, address: u16, value: u8) {
+            self.0.write(address, value)
+        }
+    }
+
+    let mut memory = Counting(vec![0; 0x10000], 0);
+    // NOP / HLT
+    memory.0[0x0200..0x0202].copy_from_slice(&[0xEA, 0x12]);
+
+    let mut cpu = Cpu::new();
+    cpu.pc = 0x0200;
+    cpu.step(&mut memory);
+    cpu.step(&mut memory);
+    assert!(cpu.halted);
+    assert_eq!(cpu.pc, 0x0201);
+
+    // a halted CPU stays at the HLT without touching the bus
+    let reads = memory.1;
+    let instruction = cpu.step(&mut memory);
+    assert_eq!(memory.1, reads);
+    assert_eq!(instruction.address, 0x0201);
+    assert!(matches!(instruction.opcode, OpCode::HLT(_)));
+    assert_eq!(cpu.pc, 0x0201);
+}
+
+#[test]
+fn check_emulator_decimal_mode() {
+    use disasm6502::emulator::Cpu;
+    use disasm6502::instruction::CPUStatusFlag;
+
+    // expected NMOS results, as described in Bruce Clark's "Decimal Mode" tutorial
+    fn adc(a: u8, b: u8, c: bool) -> (u8, bool, bool, bool, bool) {
+        let (a, b, c) = (a as i32, b as i32, c as i32);
+        let mut al = (a & 0x0F) + (b & 0x0F) + c;
+        if al >= 0x0A {
+            al = ((al + 0x06) & 0x0F) + 0x10;
+        }
+        let mut r = (a & 0xF0) + (b & 0xF0) + al;
+        if r >= 0xA0 {
+            r += 0x60;
+        }
+        let s = (a & 0xF0) as u8 as i8 as i32 + (b & 0xF0) as u8 as i8 as i32 + al;
+        (r as u8, r >= 0x100, s & 0x80 != 0, !(-128..=127).contains(&s), (a + b + c) & 0xFF == 0)
+    }
+
+    fn sbc(a: u8, b: u8, c: bool) -> (u8, bool, bool, bool, bool) {
+        let (a, b, c) = (a as i32, b as i32, c as i32);
+        let mut al = (a & 0x0F) - (b & 0x0F) + c - 1;
+        if al < 0 {
+            al = ((al - 0x06) & 0x0F) - 0x10;
+        }
+        let mut r = (a & 0xF0) - (b & 0xF0) + al;
+        if r < 0 {
+            r -= 0x60;
+        }
+        let binary = a - b + c - 1;
+        let v = ((a ^ binary) & (a ^ b) & 0x80) != 0;
+        (r as u8, binary >= 0, binary & 0x80 != 0, v, binary & 0xFF == 0)
+    }
+
+    let mut memory = vec![0; 0x10000];
+    for a in 0..=255u8 {
+        for b in 0..=255u8 {
+            for &c in [false, true].iter() {
+                for &(opcode, expect) in [(0x69, adc(a, b, c)), (0xE9, sbc(a, b, c))].iter() {
+                    memory[0] = opcode;
+                    memory[1] = b;
+
+                    let mut cpu = Cpu::new();
+                    cpu.a = a;
+                    cpu.set_flag(CPUStatusFlag::D, true);
+                    cpu.set_flag(CPUStatusFlag::C, c);
+                    cpu.step(&mut memory);
+
+                    let result = (cpu.a, cpu.flag(CPUStatusFlag::C), cpu.flag(CPUStatusFlag::N),
+                                  cpu.flag(CPUStatusFlag::V), cpu.flag(CPUStatusFlag::Z));
+                    assert_eq!(result, expect, "opcode ${:02X} A=${:02X} B=${:02X} C={}", opcode, a, b, c);
+                }
+            }
+        }
+    }
+}
+
+// Run one of Klaus Dormann's test images from `start` until the program counter stops changing
+// (`JMP *` or a branch to itself) or reaches `done`. Returns the final program counter and memory.
+fn run_dormann_test(filename: &str, start: u16, done: Option<u16>) -> (u16, Vec<u8>) {
+    use disasm6502::emulator::Cpu;
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut bytes = Vec::new();
+    File::open(filename).unwrap_or_else(|e| panic!("{}: {} (see tests/README.md)", filename, e))
+        .read_to_end(&mut bytes).unwrap();
+
+    // full 64K images are loaded at $0000, shorter ones at the start address
+    let mut memory = vec![0; 0x10000];
+    let load = if bytes.len() == 0x10000 { 0 } else { start as usize };
+    memory[load..load + bytes.len()].copy_from_slice(&bytes);
+
+    let mut cpu = Cpu::new();
+    cpu.pc = start;
+    for _ in 0..200_000_000u32 {
+        let pc = cpu.pc;
+        cpu.step(&mut memory);
+        if cpu.pc == pc || Some(cpu.pc) == done || cpu.halted {
+            return (cpu.pc, memory);
+        }
+    }
+    panic!("{}: no trap reached, program counter ${:04X}", filename, cpu.pc);
+}
+
+#[test]
+#[ignore = "needs tests/6502_functional_test.bin (see tests/README.md)"]
+fn check_emulator_functional_test() {
+    // success trap of the published binary (default configuration, code at $0400)
+    let (pc, _) = run_dormann_test("tests/6502_functional_test.bin", 0x0400, None);
+    assert_eq!(pc, 0x3469, "trapped at ${:04X}", pc);
+}
+
+#[test]
+#[ignore = "needs tests/6502_decimal_test.bin (see tests/README.md)"]
+fn check_emulator_decimal_test() {
+    // the test ends at DONE with ERROR ($0B) cleared on success; it ends with a 65C02 STP,
+    // which is not a trap on NMOS, so execution stops at the DONE address
+    let (pc, memory) = run_dormann_test("tests/6502_decimal_test.bin", 0x0200, Some(0x024B));
+    assert_eq!(pc, 0x024B, "trapped at ${:04X}", pc);
+    assert_eq!(memory[0x0B], 0, "ERROR set");
+}
+
+#[test]
+fn check_bus_cycles() {
+    use disasm6502::bus_cycle::{BusAddress, BusOperation, CycleCondition};
+    use disasm6502::instruction::{AddrMode, OpCode};
+
+    for op in 0..=255u8 {
+        let instruction = disasm6502::instruction::decode(0x0800, &mut 0, &[op, 0x00, 0x00]);
+        if let OpCode::HLT(_) = instruction.opcode {
+            continue;
+        }
+
+        let cycles = instruction.bus_cycles();
+        let always = cycles.iter().filter(|c| c.condition == CycleCondition::Always).count() as u8;
+        let optional = cycles.len() as u8 - always;
+
+        // decoder cycle count includes page crossing and branch penalties
+        let expected_optional = match instruction.addr_mode {
+            AddrMode::Relative => 2,
+            _ if instruction.extra_cycle => 1,
+            _ => 0
+        };
+        assert_eq!((always + optional, optional), (instruction.cycles, expected_optional), "opcode ${:02X}", op);
+    }
+
+    // sequences documented in "64doc" (John West, Marko Mäkelä)
+    let sequence = |bytes: &[u8]| -> Vec<(BusAddress, BusOperation, CycleCondition)> {
+        disasm6502::instruction::decode(0x0800, &mut 0, bytes).bus_cycles().iter()
+            .map(|c| (c.address, c.operation, c.condition)).collect()
+    };
+    let always = |address, operation| (address, operation, CycleCondition::Always);
+    let fetch = always(BusAddress::ProgramCounter, BusOperation::Read);
+
+    // INC $D0FF,X: dummy read at the un-carried address, then the original value written back
+    assert_eq!(sequence(&[0xFE, 0xFF, 0xD0]), vec![
+        fetch, fetch, fetch,
+        always(BusAddress::Unfixed, BusOperation::DummyRead),
+        always(BusAddress::Effective, BusOperation::Read),
+        always(BusAddress::Effective, BusOperation::DummyWrite),
+        always(BusAddress::Effective, BusOperation::Write)
+    ]);
+
+    // LDA $20FF,X: read at the un-carried address, repeated at the effective address on page crossing
+    assert_eq!(sequence(&[0xBD, 0xFF, 0x20]), vec![
+        fetch, fetch, fetch,
+        always(BusAddress::Unfixed, BusOperation::Read),
+        (BusAddress::Effective, BusOperation::Read, CycleCondition::PageCrossed)
+    ]);
+
+    // LDA ($FB),Y: pointer bytes read from the zero page before indexing
+    assert_eq!(sequence(&[0xB1, 0xFB]), vec![
+        fetch, fetch,
+        always(BusAddress::PointerLow, BusOperation::Read),
+        always(BusAddress::PointerHigh, BusOperation::Read),
+        always(BusAddress::Unfixed, BusOperation::Read),
+        (BusAddress::Effective, BusOperation::Read, CycleCondition::PageCrossed)
+    ]);
+
+    // BNE: taken branch reads the next opcode, page crossing reads it again at the un-carried PC
+    assert_eq!(sequence(&[0xD0, 0x80]), vec![
+        fetch, fetch,
+        (BusAddress::ProgramCounter, BusOperation::DummyRead, CycleCondition::BranchTaken),
+        (BusAddress::Unfixed, BusOperation::DummyRead, CycleCondition::PageCrossed)
+    ]);
+
+    // JSR $FFD2: stack peek, PCH and PCL pushed, then the high address byte fetched
+    assert_eq!(sequence(&[0x20, 0xD2, 0xFF]), vec![
+        fetch, fetch,
+        always(BusAddress::Stack, BusOperation::DummyRead),
+        always(BusAddress::Stack, BusOperation::Write),
+        always(BusAddress::Stack, BusOperation::Write),
+        fetch
+    ]);
+
+    // RTS: PCL and PCH pulled, then PC incremented past the JSR operand
+    assert_eq!(sequence(&[0x60]), vec![
+        fetch,
+        always(BusAddress::ProgramCounter, BusOperation::DummyRead),
+        always(BusAddress::Stack, BusOperation::DummyRead),
+        always(BusAddress::Stack, BusOperation::Read),
+        always(BusAddress::Stack, BusOperation::Read),
+        always(BusAddress::ProgramCounter, BusOperation::DummyRead)
+    ]);
+
+    // BRK: padding byte skipped, PCH, PCL and P pushed, PC loaded from the IRQ/BRK vector
+    assert_eq!(sequence(&[0x00, 0xEA]), vec![
+        fetch,
+        always(BusAddress::ProgramCounter, BusOperation::DummyRead),
+        always(BusAddress::Stack, BusOperation::Write),
+        always(BusAddress::Stack, BusOperation::Write),
+        always(BusAddress::Stack, BusOperation::Write),
+        always(BusAddress::Fixed(0xFFFE), BusOperation::Read),
+        always(BusAddress::Fixed(0xFFFF), BusOperation::Read)
+    ]);
+}
+
+#[test]
+fn check_trace_log() {
+    use disasm6502::emulator::Cpu;
+
+    let mut memory = vec![0; 0x10000];
+    // $C000: JMP $C5F5 / $C5F5: LDX #$00 / STX $00
+    memory[0xC000..0xC003].copy_from_slice(&[0x4C, 0xF5, 0xC5]);
+    memory[0xC5F5..0xC5F9].copy_from_slice(&[0xA2, 0x00, 0x86, 0x00]);
+
+    let mut cpu = Cpu::new();
+    cpu.pc = 0xC000;
+    cpu.sp = 0xFD;
+    cpu.cycles = 7;
+
+    let produced: Vec<String> = (0..3).map(|_| disasm6502::trace::step(&mut cpu, &mut memory)).collect();
+    let reference = "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7\n\
+                     C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10\n\
+                     C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12";
+
+    assert_eq!(produced, reference.lines().collect::<Vec<&str>>());
+    assert_eq!(disasm6502::trace::compare(produced.iter().map(|s| s.as_str()), reference.lines()), None);
+
+    let broken = reference.replace("CYC:12", "CYC:13");
+    let divergence = disasm6502::trace::compare(produced.iter().map(|s| s.as_str()), broken.lines()).unwrap();
+    assert_eq!((divergence.line, divergence.field), (3, "CYC"));
+
+    // a trace stopping early or running longer diverges at the first unmatched line
+    let divergence = disasm6502::trace::compare(produced[..2].iter().map(|s| s.as_str()), reference.lines()).unwrap();
+    assert_eq!((divergence.line, divergence.field, divergence.actual.as_str()), (3, "line", "end of trace"));
+    let divergence = disasm6502::trace::compare(produced.iter().map(|s| s.as_str()), reference.lines().take(1)).unwrap();
+    assert_eq!((divergence.line, divergence.field, divergence.expected.as_str()), (2, "line", "end of trace"));
+
+    // memory accessed through indexed and indirect modes
+    // $0400: LDA ($89),Y / LDA ($80,X) / LDA $02FF,X / JMP ($02FF) (high byte read from $0200)
+    memory[0x0400..0x040A].copy_from_slice(&[0xB1, 0x89, 0xA1, 0x80, 0xBD, 0xFF, 0x02, 0x6C, 0xFF, 0x02]);
+    memory[0x80..0x82].copy_from_slice(&[0x00, 0x02]);
+    memory[0x89..0x8B].copy_from_slice(&[0x00, 0x03]);
+    memory[0x0200] = 0x5A;
+    memory[0x02FF] = 0x7E;
+    memory[0x0300] = 0x89;
+    cpu.pc = 0x0400;
+    cpu.cycles = 1666;
+
+    let disassembly: Vec<String> = (0..4).map(|_| disasm6502::trace::step(&mut cpu, &mut memory)[16..47].trim_end().to_string()).collect();
+    assert_eq!(disassembly, vec!["LDA ($89),Y = 0300 @ 0300 = 89", "LDA ($80,X) @ 80 = 0200 = 5A",
+                                 "LDA $02FF,X @ 02FF = 7E", "JMP ($02FF) = 5A7E"]);
+}
+
+#[test]
+fn check_trace_guided_analysis() {
+    use disasm6502::analysis::{self, Line, Region};
+    use disasm6502::banking::BankedAddress;
+
+    let bytes = vec![
+        0xA9, 0x09,       // $0800: LDA #$09
+        0x48,             // $0802: PHA
+        0xA9, 0x0A,       // $0803: LDA #$0A
+        0x48,             // $0805: PHA
+        0x60,             // $0806: RTS - "returns" to $080B
+        0xFF, 0xFF,       // $0807: data
+        0x00, 0x00,       // $0809: data
+        0xF0, 0x01,       // $080B: BEQ $080E
+        0xE8,             // $080D: INX
+        0x60,             // $080E: RTS
+    ];
+    let trace = analysis::parse_trace("PC    BYTES\n0800  A9 09\n0802  48\n0803  A9 0A\n0805  48\n0806  60\n080B  F0 01\n080E  60\n");
+    assert_eq!(trace.len(), 7);
+    let map = analysis::analyze(&bytes, 0x0800, &[0x0800], &trace);
+
+    assert!(map.entry_points().contains(&BankedAddress::unbanked(0x080B)));
+    assert_eq!(map.region(0x0806), Region::Traced);
+    assert_eq!(map.region(0x0807), Region::Unknown);
+    assert_eq!(map.region(0x080B), Region::Traced);
+    assert_eq!(map.region(0x080D), Region::Inferred);
+
+    let lines = analysis::disassemble(&bytes, 0x0800, &map);
+    assert_eq!(lines.len(), 12);
+    assert_eq!(lines[5], Line::Data(BankedAddress::unbanked(0x0807), 0xFF));
+    assert_eq!(format!("{}", lines[5]), "$0807: FF       .BYTE $FF");
+    match lines[10] {
+        Line::Code(ref i, Region::Inferred) => assert_eq!(i.address, 0x080D),
+        ref l => panic!("unexpected line {}", l)
+    }
+
+    // $0900: BIT $01A9 skipping LDA #$01 at $0901 / STA $D020 / RTS
+    let bytes = vec![0x2C, 0xA9, 0x01, 0x8D, 0x20, 0xD0, 0x60];
+    let map = analysis::analyze(&bytes, 0x0900, &[0x0900], &[0x0900, 0x0903, 0x0906, 0x0901, 0x0903]);
+    let lines = analysis::disassemble(&bytes, 0x0900, &map);
+
+    assert_eq!(lines.len(), 4);
+    match lines[1] {
+        Line::Overlapping(ref i, Region::Traced) => assert_eq!(i.as_str(), "LDA #$01"),
+        ref l => panic!("unexpected line {}", l)
+    }
+    assert_eq!(disasm6502::listing::format(&lines, &disasm6502::symbols::SymbolTable::new())[1],
+               "$0901: A9 01    LDA #$01 ; overlapping");
+}
+
+#[test]
+fn check_disasm_prg_file() {
+    let instructions = disasm6502::from_prg_file("examples/data.prg").unwrap();
+    assert_eq!(instructions[0].address, 0x080D);
+    assert_eq!(format!("{}", instructions[0]), "$080D: 05 0B    ORA $0B");
+
+    // no BASIC stub - start at load address
+    let instructions = disasm6502::from_prg_array(&[0x00, 0xC0, 0xA9, 0x00, 0x60]).unwrap();
+    assert_eq!(instructions[0].address, 0xC000);
+    assert_eq!(instructions.len(), 2);
+
+    assert!(disasm6502::from_prg_array(&[0x01]).is_err());
+}
+
+#[test]
+fn check_basic_detokenize() {
+    // 10 PRINT"{CLR}HELLO"
+    // 20 IF A<>1 THEN GOTO 10
+    let bytes = vec![
+        0x0F, 0x08, 0x0A, 0x00, 0x99, 0x22, 0x93, 0x48, 0x45, 0x4C, 0x4C, 0x4F, 0x22, 0x00,
+        0x21, 0x08, 0x14, 0x00, 0x8B, 0x20, 0x41, 0xB3, 0xB1, 0x31, 0x20, 0xA7, 0x20, 0x89, 0x20, 0x31, 0x30, 0x00,
+        0x00, 0x00,
+        0xEA, 0x60,
+    ];
+    let program = disasm6502::basic::detokenize(0x0801, &bytes);
+    let listing: Vec<String> = program.lines.iter().map(|l| format!("{}", l)).collect();
+
+    assert_eq!(listing, vec!["10 PRINT\"{$93}HELLO\"", "20 IF A<>1 THEN GOTO 10"]);
+    assert_eq!(program.end_address, 0x0823);
+
+    let (program, instructions) = disasm6502::from_basic_prg_array(&[&[0x01, 0x08][..], &bytes[..]].concat()).unwrap();
+    assert_eq!(program.lines.len(), 2);
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(instructions[0].address, 0x0823);
+}
+
+#[test]
+fn check_d64_image() {
+    use disasm6502::d64::{D64, FileType};
+
+    fn offset(track: u8, sector: u8) -> usize {
+        let preceding: usize = (1..track).map(|t| disasm6502::d64::sectors_per_track(t) as usize).sum();
+        (preceding + sector as usize) * 256
+    }
+
+    // 35 track image with error info
+    let mut image = vec![0; 683 * 257];
+    let bam = offset(18, 0);
+    image[bam..bam + 2].copy_from_slice(&[18, 1]);
+    image[bam + 0x90..bam + 0xA0].copy_from_slice(b"TEST DISK\xA0\xA0\xA0\xA0\xA0\xA0\xA0");
+    image[bam + 4 * 17] = 19;
+
+    // directory: single PRG file starting at 17/0
+    let dir = offset(18, 1);
+    image[dir..dir + 2].copy_from_slice(&[0x00, 0xFF]);
+    image[dir + 2..dir + 5].copy_from_slice(&[0x82, 17, 0]);
+    image[dir + 5..dir + 0x15].copy_from_slice(b"HELLO\xA0\xA0\xA0\xA0\xA0\xA0\xA0\xA0\xA0\xA0\xA0");
+    image[dir + 0x1E] = 2;
+
+    // file spans two sectors: 254 bytes in 17/0, 3 bytes in 17/10
+    let first = offset(17, 0);
+    image[first..first + 2].copy_from_slice(&[17, 10]);
+    image[first + 2..first + 4].copy_from_slice(&[0x00, 0xC0]);
+    for b in image[first + 4..first + 256].iter_mut() {
+        *b = 0xEA;
+    }
+    let last = offset(17, 10);
+    image[last..last + 5].copy_from_slice(&[0, 4, 0xA9, 0x00, 0x60]);
+    // error info: sector 17/10 has a checksum error (code 5)
+    let sector_index = offset(17, 10) / 256;
+    image[683 * 256 + sector_index] = 5;
+
+    let disk = D64::parse(&image).unwrap();
+    assert_eq!(disk.tracks(), 35);
+    assert_eq!(disk.disk_name(), "TEST DISK");
+    assert_eq!(disk.free_blocks(), 19);
+
+    let directory = disk.directory().unwrap();
+    assert_eq!(directory.len(), 1);
+    assert_eq!(directory[0].file_type, FileType::Prg);
+    assert_eq!(format!("{}", directory[0]), "2     \"HELLO\"  PRG");
+
+    let file = disk.read_file_by_name("HELLO").unwrap();
+    assert_eq!(file.data.len(), 257);
+    assert_eq!(file.errors, vec![(17, 10, 5)]);
+
+    let instructions = disasm6502::from_prg_array(&file.data).unwrap();
+    assert_eq!(instructions.len(), 254);
+    assert_eq!(instructions[0].address, 0xC000);
+
+    match disk.read_file_by_name("MISSING") {
+        Err(disasm6502::error::Disasm6502Error::FileNotFound(ref name)) => assert_eq!(name, "MISSING"),
+        _ => panic!("missing file not reported")
+    }
+    assert!(D64::parse(&image[..1000]).is_err());
+}
+
+#[test]
+fn check_t64_and_crt_segments() {
+    use disasm6502::crt::{ChipType, Crt};
+    use disasm6502::t64::T64;
+
+    // T64 with two files; the second one has a wrong end address
+    let mut tape = vec![0; 0x80];
+    tape[..19].copy_from_slice(b"C64 tape image file");
+    tape[0x22] = 2;
+    tape[0x24] = 2;
+    tape[0x28..0x40].copy_from_slice(b"DEMO TAPE               ");
+    tape[0x40..0x4C].copy_from_slice(&[1, 0x82, 0x00, 0xC0, 0x03, 0xC0, 0, 0, 0x80, 0, 0, 0]);
+    tape[0x50..0x60].copy_from_slice(b"FIRST           ");
+    tape[0x60..0x6C].copy_from_slice(&[1, 0x82, 0x00, 0x10, 0xC6, 0xC3, 0, 0, 0x83, 0, 0, 0]);
+    tape[0x70..0x80].copy_from_slice(b"SECOND          ");
+    tape.extend_from_slice(&[0xA9, 0x00, 0x60, 0xE8, 0x60]);
+
+    let t64 = T64::parse(&tape).unwrap();
+    assert_eq!(t64.name, "DEMO TAPE");
+    assert_eq!(t64.files[0].name, "FIRST");
+    assert_eq!(t64.files[1].segment.address, 0x1000);
+    assert_eq!(t64.files[1].segment.data, vec![0xE8, 0x60]);
+    assert_eq!(format!("{}", t64.segments()[0]), "$C000-$C002 (3 bytes)");
+    assert!(T64::parse(&tape[..0x20]).is_err());
+
+    // 8K cartridge with two banks at $8000
+    let mut cart = vec![0; 0x40];
+    cart[..16].copy_from_slice(b"C64 CARTRIDGE   ");
+    cart[0x13] = 0x40;
+    cart[0x14] = 1;
+    cart[0x18] = 0;
+    cart[0x19] = 1;
+    cart[0x20..0x24].copy_from_slice(b"GAME");
+    for bank in 0..2u8 {
+        cart.extend_from_slice(b"CHIP");
+        cart.extend_from_slice(&[0, 0, 0x00, 0x18, 0, 0, 0, bank, 0x80, 0x00, 0x00, 0x08]);
+        let mut rom = vec![0xEA; 8];
+        rom[..2].copy_from_slice(&[0x09, 0x80]);
+        cart.extend_from_slice(&rom);
+    }
+
+    let crt = Crt::parse(&cart).unwrap();
+    assert_eq!(crt.name, "GAME");
+    assert_eq!(crt.hardware_name(), Some("Normal cartridge"));
+    assert_eq!(crt.chips.len(), 2);
+    assert_eq!(crt.chips[1].chip_type, ChipType::Rom);
+    assert_eq!(crt.chips[1].segment.bank, Some(1));
+    assert_eq!(format!("{}", crt.chips[1].segment), "01:$8000-$8007 (8 bytes)");
+    assert_eq!(crt.entry_points(), vec![0x8009]);
+    assert!(Crt::parse(&cart[..0x48]).is_err());
+}
+
+#[test]
+fn check_ines_mapping() {
+    use disasm6502::banking::BankedAddress;
+    use disasm6502::ines::Nes;
+    use disasm6502::symbol_file::{self, SymbolFormat};
+
+    // UxROM (mapper 2) with four 16K banks, last bank fixed at $C000
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 4, 2, 0x20, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    for bank in 0..4 {
+        rom.extend_from_slice(&vec![bank as u8; 0x4000]);
+    }
+    let vectors = 16 + 3 * 0x4000 + 0x3FFA;
+    rom[vectors..vectors + 6].copy_from_slice(&[0x10, 0xC0, 0x00, 0xC0, 0x20, 0xC0]);
+    rom.extend_from_slice(&vec![0xFF; 2 * 0x2000]);
+
+    let nes = Nes::parse(&rom).unwrap();
+    assert!(!nes.nes2);
+    assert_eq!(nes.mapper, 2);
+    assert_eq!(nes.prg_rom.len(), 0x10000);
+    assert_eq!(nes.chr_rom_size, 0x4000);
+    assert_eq!(nes.prg_banks(0x4000).len(), 4);
+
+    let segments = nes.mapped_segments();
+    assert_eq!((segments[1].bank, segments[1].address), (Some(3), 0xC000));
+    assert_eq!(nes.entry_points(), vec![0xC000, 0xC010, 0xC020]);
+
+    // MMC3 (mapper 4) as NES 2.0: 8K banks, last two fixed at $C000 and $E000, numbered in
+    // 16K banks
+    rom[6] = 0x40;
+    rom[7] = 0x08;
+    let nes = Nes::parse(&rom).unwrap();
+    assert!(nes.nes2);
+    let segments = nes.mapped_segments();
+    assert_eq!(segments.len(), 4);
+    assert_eq!((segments[1].bank, segments[1].address), (Some(0), 0xA000));
+    assert_eq!((segments[3].bank, segments[3].address), (Some(3), 0xE000));
+    assert_eq!(nes.reset_vector(), Some(0xC000));
+
+    let layout = nes.layout();
+    assert_eq!(layout.window(0xA000).unwrap().banks, vec![0]);
+    assert_eq!(layout.window(0xFFFF).unwrap().banks, vec![3]);
+    let listing = nes.listing();
+    let reset = listing.iter().position(|l| l == "RESET:").unwrap();
+    assert_eq!(listing[reset + 1], "03:$C000: 03 03    SLO ($03,X)");
+
+    // Mesen offsets follow the bank numbers
+    let mut symbols = disasm6502::symbols::SymbolTable::new();
+    symbols.insert_banked(BankedAddress::new(segments[3].bank.unwrap(), 0xE010), "fixed");
+    let mesen = symbol_file::export(&symbols, SymbolFormat::Mesen { prg_size: nes.prg_rom.len() }).unwrap();
+    assert_eq!(mesen, "P:E010:fixed\n");
+
+    let mut empty = nes.clone();
+    empty.prg_rom.clear();
+    assert!(empty.mapped_segments().is_empty());
+
+    let reset = segments.iter().find(|s| s.contains(0xC000)).unwrap();
+    let instructions = disasm6502::from_addr_array(&reset.data, reset.address).unwrap();
+    assert_eq!(instructions[0].address, 0xC000);
+
+    assert!(Nes::parse(&rom[..0x1000]).is_err());
+}
+
+#[test]
+fn check_nsf_listing() {
+    use disasm6502::nsf::Nsf;
+
+    let mut header = vec![0; 0x80];
+    header[..5].copy_from_slice(b"NESM\x1A");
+    header[5] = 1;
+    header[6] = 3;
+    header[7] = 1;
+    header[0x08..0x0E].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x05, 0x80]);
+    header[0x0E..0x14].copy_from_slice(b"Theme\0");
+
+    // INIT: LDA #$00 / STA $4015 / PLAY: JSR $800A / RTS / $800A: RTS
+    let code = [0xA9, 0x00, 0x8D, 0x15, 0x40, 0x20, 0x0A, 0x80, 0x60, 0xFF, 0x60];
+    let mut bytes = header.clone();
+    bytes.extend_from_slice(&code);
+
+    let nsf = Nsf::parse(&bytes).unwrap();
+    assert_eq!(nsf.name, "Theme");
+    assert_eq!(nsf.bankswitch, None);
+    assert_eq!(nsf.entry_points(), vec![0x8000, 0x8005]);
+
+    let listing = nsf.listing();
+    assert_eq!(listing[0], "INIT:");
+    assert_eq!(listing[3], "PLAY:");
+    assert_eq!(listing[4], "$8005: 20 0A 80 JSR $800A");
+    assert_eq!(listing[6], "$8009: FF       .BYTE $FF");
+
+    // bankswitched: data loaded at $8100, bank 1 mapped at $9000
+    let mut bytes = header;
+    bytes[0x08..0x0E].copy_from_slice(&[0x00, 0x81, 0x00, 0x81, 0x00, 0x90]);
+    bytes[0x70..0x78].copy_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    bytes.extend_from_slice(&code);
+    bytes.resize(0x80 + 0x1000 - 0x100, 0);
+    bytes.extend_from_slice(&[0x4C, 0x03, 0x90, 0x60]);
+
+    let nsf = Nsf::parse(&bytes).unwrap();
+    let segments = nsf.segments();
+    assert_eq!(segments.len(), 8);
+    assert_eq!((segments[0].bank, segments[0].read(0x8100)), (Some(0), Some(0xA9)));
+    assert_eq!((segments[1].bank, segments[1].read(0x9003)), (Some(1), Some(0x60)));
+    assert_eq!(nsf.symbols().get(0x9000), Some("PLAY"));
+
+    let listing = nsf.listing();
+    let play = listing.iter().position(|l| l == "PLAY:").unwrap();
+    assert_eq!(listing[play + 1], "01:$9000: 4C 03 90 JMP $9003");
+    assert_eq!(listing[play + 2], "01:$9003: 60       RTS ");
+
+    // INIT in bank 0 calls PLAY across the 4K slot boundary in bank 1
+    bytes[0x80..0x84].copy_from_slice(&[0x20, 0x00, 0x90, 0x60]);
+    let nsf = Nsf::parse(&bytes).unwrap();
+    let layout = nsf.layout();
+    assert_eq!(layout.windows().len(), 8);
+    assert_eq!(layout.window(0x9FFF).unwrap().banks, vec![1]);
+
+    let listing = nsf.listing();
+    let init = listing.iter().position(|l| l == "INIT:").unwrap();
+    assert_eq!(listing[init + 1], "00:$8100: 20 00 90 JSR PLAY");
+    let play = listing.iter().position(|l| l == "PLAY:").unwrap();
+    assert_eq!(listing[play + 1], "01:$9000: 4C 03 90 JMP $9003");
+
+    assert!(Nsf::parse(&bytes[..0x40]).is_err());
+}
+
+#[test]
+fn check_sid_tune() {
+    use disasm6502::sid::Sid;
+
+    // version 2 PSID with the load address embedded in the data
+    let mut bytes = vec![0; 0x7C];
+    bytes[..4].copy_from_slice(b"PSID");
+    bytes[0x04..0x10].copy_from_slice(&[0x00, 0x02, 0x00, 0x7C, 0x00, 0x00, 0x10, 0x00, 0x10, 0x06, 0x00, 0x01]);
+    bytes[0x16..0x1B].copy_from_slice(b"Tune\0");
+    bytes[0x36..0x3C].copy_from_slice(b"Artist");
+    bytes[0x56..0x5A].copy_from_slice(b"1987");
+
+    // INIT: LDA #$0F / STA $D418 / RTS / PLAY: INC $D401 / RTS
+    bytes.extend_from_slice(&[0x00, 0x10, 0xA9, 0x0F, 0x8D, 0x18, 0xD4, 0x60, 0xEE, 0x01, 0xD4, 0x60]);
+
+    let sid = Sid::parse(&bytes).unwrap();
+    assert!(!sid.rsid);
+    assert_eq!(sid.load_address, 0x1000);
+    assert_eq!(sid.init_address, 0x1000);
+    assert_eq!(sid.play_address, 0x1006);
+    assert_eq!((sid.name.as_str(), sid.author.as_str(), sid.released.as_str()), ("Tune", "Artist", "1987"));
+    assert_eq!(sid.second_sid, None);
+    assert_eq!(sid.segment().data.len(), 10);
+
+    let listing = sid.listing();
+    assert_eq!(listing, vec!["INIT:",
+                             "$1000: A9 0F    LDA #$0F",
+                             "$1002: 8D 18 D4 STA SID_MODE_VOL",
+                             "$1005: 60       RTS ",
+                             "PLAY:",
+                             "$1006: EE 01 D4 INC SID_V1_FREQ_HI",
+                             "$1009: 60       RTS "]);
+
+    // version 3 RSID with a second SID at $D420
+    bytes[..4].copy_from_slice(b"RSID");
+    bytes[0x05] = 3;
+    bytes[0x7A] = 0x42;
+    let sid = Sid::parse(&bytes).unwrap();
+    assert!(sid.rsid);
+    assert_eq!(sid.second_sid, Some(0xD420));
+    assert_eq!(sid.symbols().get(0xD424), Some("SID2_V1_CTRL"));
+
+    assert!(Sid::parse(&bytes[..0x40]).is_err());
+}
+
+#[test]
+fn check_xex_segments() {
+    use disasm6502::xex::Xex;
+
+    let bytes = vec![
+        // $0600: LDA #$01 / RTS (init routine)
+        0xFF, 0xFF, 0x00, 0x06, 0x02, 0x06, 0xA9, 0x01, 0x60,
+        // INITAD = $0600
+        0xE2, 0x02, 0xE3, 0x02, 0x00, 0x06,
+        // $2000: JMP $2000, followed by a data byte; second $FFFF marker
+        0xFF, 0xFF, 0x00, 0x20, 0x03, 0x20, 0x4C, 0x00, 0x20, 0x42,
+        // RUNAD = $2000
+        0xE0, 0x02, 0xE1, 0x02, 0x00, 0x20
+    ];
+
+    let xex = Xex::parse(&bytes).unwrap();
+    assert_eq!(xex.segments.len(), 4);
+    assert_eq!(xex.segments[2].address, 0x2000);
+    assert_eq!(xex.entry_points(), vec![0x0600, 0x2000]);
+
+    let instructions = disasm6502::from_segments(&xex.segments).unwrap();
+    assert_eq!(instructions[0].address, 0x0600);
+
+    let listing = xex.listing();
+    assert_eq!(listing, vec!["INIT:",
+                             "$0600: A9 01    LDA #$01",
+                             "$0602: 60       RTS ",
+                             "RUN:",
+                             "$2000: 4C 00 20 JMP RUN",
+                             "$2003: 42       .BYTE $42"]);
+
+    assert!(Xex::parse(&bytes[2..]).is_err());
+    assert!(Xex::parse(&bytes[..8]).is_err());
+
+    let bytes = vec![
+        // $2000: LDA #$01 / RTS (init routine)
+        0xFF, 0xFF, 0x00, 0x20, 0x02, 0x20, 0xA9, 0x01, 0x60,
+        // INITAD = $2000
+        0xE2, 0x02, 0xE3, 0x02, 0x00, 0x20,
+        // $2000: LDX #$02 / JMP $2000, loaded over the init routine
+        0x00, 0x20, 0x04, 0x20, 0xA2, 0x02, 0x4C, 0x00, 0x20,
+        // RUNAD = $2000
+        0xE0, 0x02, 0xE1, 0x02, 0x00, 0x20
+    ];
+
+    let xex = Xex::parse(&bytes).unwrap();
+    assert_eq!(xex.listing(), vec!["INIT:",
+                                   "00:$2000: A9 01    LDA #$01",
+                                   "00:$2002: 60       RTS ",
+                                   "RUN:",
+                                   "02:$2000: A2 02    LDX #$02",
+                                   "02:$2002: 4C 00 20 JMP RUN"]);
+}
+
+#[test]
+fn check_apple2_disk_images() {
+    use disasm6502::apple2::{DiskImage, FileSystem, SectorOrder};
+
+    // DOS 3.3 image in DOS order
+    let dos_offset = |track: usize, sector: usize| (track * 16 + sector) * 256;
+    let mut image = vec![0; 143360];
+    let vtoc = dos_offset(17, 0);
+    image[vtoc + 1..vtoc + 4].copy_from_slice(&[17, 15, 3]);
+    image[vtoc + 0x27] = 122;
+    image[vtoc + 0x34..vtoc + 0x36].copy_from_slice(&[35, 16]);
+
+    // catalog entry: locked binary file "HELLO" with track/sector list at 18/0
+    let catalog = dos_offset(17, 15);
+    image[catalog + 0x0B..catalog + 0x0E].copy_from_slice(&[18, 0, 0x84]);
+    for (i, b) in image[catalog + 0x0E..catalog + 0x2C].iter_mut().enumerate() {
+        *b = if i < 5 { b"HELLO"[i] | 0x80 } else { 0xA0 };
+    }
+    image[catalog + 0x2C] = 2;
+
+    // track/sector list with a single data sector 18/1: load $0300, length 3, LDA #$01 / RTS
+    image[dos_offset(18, 0) + 0x0C..dos_offset(18, 0) + 0x0E].copy_from_slice(&[18, 1]);
+    image[dos_offset(18, 1)..dos_offset(18, 1) + 7].copy_from_slice(&[0x00, 0x03, 0x03, 0x00, 0xA9, 0x01, 0x60]);
+
+    let disk = DiskImage::parse(&image, None).unwrap();
+    assert_eq!(disk.file_system(), FileSystem::Dos33);
+    assert_eq!(disk.order(), SectorOrder::Dos);
+
+    let catalog = disk.catalog().unwrap();
+    assert_eq!(catalog.len(), 1);
+    assert_eq!(format!("{}", catalog[0]), "*B   002 HELLO");
+
+    let file = disk.read_file_by_name("HELLO").unwrap();
+    let segment = file.segment().unwrap();
+    assert_eq!(segment.address, 0x0300);
+    assert_eq!(segment.data, vec![0xA9, 0x01, 0x60]);
+    match disk.read_file_by_name("MISSING") {
+        Err(disasm6502::error::Disasm6502Error::FileNotFound(ref name)) => assert_eq!(name, "MISSING"),
+        _ => panic!("missing file not reported")
+    }
+
+    // ProDOS image in ProDOS (block) order
+    let mut image = vec![0; 143360];
+    let block = |n: usize| n * 512;
+    let dir = block(2);
+    image[dir + 0x04] = 0xF4;
+    image[dir + 0x05..dir + 0x09].copy_from_slice(b"DISK");
+    image[dir + 0x23..dir + 0x25].copy_from_slice(&[0x27, 0x0D]);
+
+    // sapling BIN file "GAME" loaded at $2000, index block 7, data blocks 8 and 9 (600 bytes)
+    let entry = dir + 0x04 + 0x27;
+    image[entry] = 0x24;
+    image[entry + 1..entry + 5].copy_from_slice(b"GAME");
+    image[entry + 0x10..entry + 0x18].copy_from_slice(&[0x06, 7, 0, 3, 0, 0x58, 0x02, 0x00]);
+    image[entry + 0x1E..entry + 0x21].copy_from_slice(&[0xC3, 0x00, 0x20]);
+    image[block(7)] = 8;
+    image[block(7) + 1] = 9;
+    for b in image[block(8)..block(10)].iter_mut() {
+        *b = 0xEA;
+    }
+
+    let disk = DiskImage::parse(&image, None).unwrap();
+    assert_eq!(disk.file_system(), FileSystem::ProDos);
+    assert_eq!(disk.order(), SectorOrder::ProDos);
+
+    let catalog = disk.catalog().unwrap();
+    assert_eq!(format!("{}", catalog[0]), " BIN 003 GAME");
+
+    let file = disk.read_file(&catalog[0]).unwrap();
+    assert_eq!(file.load_address, Some(0x2000));
+    assert_eq!(file.data.len(), 600);
+
+    let instructions = disasm6502::from_segments(&[file.segment().unwrap()]).unwrap();
+    assert_eq!(instructions.len(), 600);
+
+    assert!(DiskImage::parse(&image, Some(SectorOrder::Dos)).is_err());
+    assert!(DiskImage::parse(&image[..1000], None).is_err());
+}
+
+#[test]
+fn check_dfs_disk_image() {
+    use disasm6502::dfs::Dfs;
+
+    // double-sided image: 2 tracks per side
+    let mut image = vec![0; 4 * 2560];
+    image[0..8].copy_from_slice(b"GAMES   ");
+    image[8..16].copy_from_slice(b"LOADER $");
+    image[256..260].copy_from_slice(b"    ");
+    image[256 + 5] = 8;
+    image[256 + 6] = 0x30;
+
+    // $.LOADER: load $1900, exec $1903, 6 bytes at sector 2
+    image[256 + 8..256 + 16].copy_from_slice(&[0x00, 0x19, 0x03, 0x19, 0x06, 0x00, 0x00, 0x02]);
+    // $1900: .BYTE $00 $01 $02 / $1903: JSR OSWRCH
+    image[512..520].copy_from_slice(&[0x00, 0x01, 0x02, 0x20, 0xEE, 0xFF, 0x00, 0x00]);
+
+    // side 1 (track 0 of side 1 follows track 0 of side 0): locked B.DATA in the I/O processor,
+    // 300 bytes at sector 12
+    let side1 = 2560;
+    image[side1 + 8..side1 + 16].copy_from_slice(b"DATA   \xC2");
+    image[side1 + 256 + 5] = 8;
+    image[side1 + 256 + 8..side1 + 256 + 16].copy_from_slice(&[0x00, 0x30, 0x00, 0x30, 0x2C, 0x01, 0xCC, 0x0C]);
+
+    let disk = Dfs::parse(&image, true).unwrap();
+    assert_eq!(disk.title(0), "GAMES");
+    assert_eq!(disk.boot_option(0), 3);
+
+    let catalog = disk.catalog(0);
+    assert_eq!(catalog.len(), 1);
+    assert_eq!(format!("{}", catalog[0]), "$.LOADER    001900 001903 000006 002");
+
+    let listing = disk.listing(&catalog[0]).unwrap();
+    assert_eq!(listing[3], "$1903: 20 EE FF JSR OSWRCH");
+    assert_eq!(listing[0], "$1900: 00       .BYTE $00");
+
+    let data = disk.find("B.DATA").unwrap();
+    assert!(data.locked);
+    assert_eq!((data.side, data.start_sector), (1, 12));
+    assert_eq!(data.load_address, 0xFF3000);
+    assert_eq!(data.exec_address, 0xFF3000);
+    assert_eq!(disk.read_file(&data).unwrap().len(), 300);
+    match disk.read_file_by_name("MISSING") {
+        Err(disasm6502::error::Disasm6502Error::FileNotFound(ref name)) => assert_eq!(name, "MISSING"),
+        _ => panic!("missing file not reported")
+    }
+
+    assert!(Dfs::parse(&image[..300], false).is_err());
+}
+
+#[test]
+fn check_hex_file_loaders() {
+    use disasm6502::error::Disasm6502Error;
+    use disasm6502::hex_file::HexFile;
+
+    fn intel(address: u16, kind: u8, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8, (address >> 8) as u8, address as u8, kind];
+        bytes.extend_from_slice(data);
+        let sum = bytes.iter().fold(0u8, |s, &b| s.wrapping_add(b));
+        bytes.push(0u8.wrapping_sub(sum));
+        format!(":{}", bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+    }
+
+    // two adjacent records, a gap, a record in the second 64K bank and a start address
+    let text = [intel(0xFFFC, 0x00, &[0x00, 0xF0]),
+                intel(0xFFFE, 0x00, &[0x10, 0xF0]),
+                intel(0xF000, 0x00, &[0xA2, 0xFF, 0x9A, 0x4C, 0x00, 0xF0]),
+                intel(0x0000, 0x04, &[0x00, 0x01]),
+                intel(0x8000, 0x00, &[0xEA]),
+                intel(0x0000, 0x05, &[0x00, 0x00, 0xF0, 0x00]),
+                intel(0x0000, 0x01, &[])].join("\r\n");
+
+    let hex = HexFile::parse(&text).unwrap();
+    assert_eq!(hex.segments.len(), 3);
+    assert_eq!((hex.segments[0].address, hex.segments[0].data.len()), (0xF000, 6));
+    assert_eq!(hex.segments[1].read_word(0xFFFC), Some(0xF000));
+    assert_eq!((hex.segments[2].bank, hex.segments[2].address), (Some(1), 0x8000));
+    assert_eq!(hex.entry_point, Some(0xF000));
+
+    let instructions = disasm6502::from_segments(&hex.segments[..1]).unwrap();
+    assert_eq!(instructions[2].as_str(), "JMP $F000");
+
+    // S28 with a gap and S8 start address
+    let srec = HexFile::parse("S00600004844521B\nS2070180000A0B0C56\nS2050180100D5C\nS8040180007A").unwrap();
+    assert_eq!(srec.segments.len(), 2);
+    assert_eq!(srec.segments[1].address, 0x8010);
+    assert_eq!(srec.segments[0].bank, Some(1));
+    assert_eq!(srec.entry_point, Some(0x018000));
+
+    // corrupted checksum
+    match HexFile::parse(":03800000A9016074") {
+        Err(Disasm6502Error::Checksum { line, expected, actual }) => assert_eq!((line, expected, actual), (1, 0x73, 0x74)),
+        r => panic!("unexpected result {:?}", r)
+    }
+    assert!(HexFile::parse(":0380000A90160").is_err());
+}
+
+#[test]
+fn check_memory_image() {
+    use disasm6502::analysis::{self, Line, Region};
+    use disasm6502::banking::BankedAddress;
+    use disasm6502::memory_image::MemoryImage;
+    use disasm6502::segment::Segment;
+
+    // $2000: JSR $3000 / JMP $2000 split across two adjacent segments, $3000: BNE $2FFF (into a gap) / RTS
+    let image = MemoryImage::from(vec![Segment::new(0x2000, vec![0x20, 0x00, 0x30, 0x4C]),
+                                       Segment::new(0x2004, vec![0x00, 0x20, 0xFF]),
+                                       Segment::new(0x3000, vec![0xD0, 0xFD, 0x60])]);
+
+    assert_eq!(image.read_word(0x2004), Some(0x2000));
+    assert_eq!(image.segment_at(0x3001).unwrap().address, 0x3000);
+    assert!(!image.contains(0x2FFF));
+
+    let instructions = disasm6502::from_memory_image(&image).unwrap();
+    assert_eq!(instructions.iter().map(|i| i.address).collect::<Vec<u16>>(), vec![0x2000, 0x2003, 0x2006, 0x3000, 0x3002]);
+
+    let map = analysis::analyze_image(&image, &[0x2000], &[]);
+    assert_eq!(map.region(0x2005), Region::Inferred);
+    assert_eq!(map.region(0x2006), Region::Unknown);
+    assert_eq!(map.region(0x3002), Region::Inferred);
+
+    let lines = analysis::disassemble_image(&image, &map);
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[2], Line::Data(BankedAddress::unbanked(0x2006), 0xFF));
+    assert_eq!(format!("{}", lines[1]), "$2003: 4C 00 20 JMP $2000");
+
+    // a segment loaded over the first one is walked from its own start, and decoded from its own bytes as an overlay
+    let segments = vec![Segment::new(0x2000, vec![0x20, 0x00, 0x30]), Segment::new(0x2001, vec![0xA2, 0x02])];
+    let instructions = disasm6502::from_memory_image(&MemoryImage::from(segments.clone())).unwrap();
+    assert_eq!(instructions.iter().map(|i| i.address).collect::<Vec<u16>>(), vec![0x2000, 0x2001]);
+
+    let image = MemoryImage::with_overlays(segments);
+    let instructions = disasm6502::from_memory_image(&image).unwrap();
+    assert_eq!(instructions[1].as_str(), "LDX #$02");
+    assert_eq!(instructions[1].bank, Some(1));
+}
+
+#[test]
+fn check_banked_analysis() {
+    use disasm6502::analysis::{self, Region};
+    use disasm6502::banking::{BankedAddress, BankLayout};
+    use disasm6502::listing;
+    use disasm6502::memory_image::MemoryImage;
+    use disasm6502::segment::Segment;
+    use disasm6502::symbols::SymbolTable;
+
+    // UxROM-like: banks 0 and 1 switchable at $8000, bank 2 fixed at $C000
+    // 02:$C000: JSR $8000 (bank 1 by annotation) / JSR $8000 (unresolved) / JMP $C000
+    // 00:$8000: RTS, 01:$8000: INX / JSR $C000 / RTS
+    let image = MemoryImage::from(vec![Segment::banked(0, 0x8000, vec![0x60]),
+                                       Segment::banked(1, 0x8000, vec![0xE8, 0x20, 0x00, 0xC0, 0x60]),
+                                       Segment::banked(2, 0xC000, vec![0x20, 0x00, 0x80, 0x20, 0x00, 0x80, 0x4C, 0x00, 0xC0])]);
+    let mut layout = BankLayout::new();
+    layout.add_window(0x8000, 0xBFFF, &[0, 1]);
+    layout.add_window(0xC000, 0xFFFF, &[2]);
+    layout.annotate(BankedAddress::new(2, 0xC000), 1);
+
+    let map = analysis::analyze_banked(&image, &layout, &[BankedAddress::unbanked(0xC000)], &[]);
+    assert_eq!(map.region_at(BankedAddress::new(1, 0x8004)), Region::Inferred);
+    assert_eq!(map.region_at(BankedAddress::new(0, 0x8000)), Region::Unknown);
+    assert!(map.is_instruction(0x8000));
+    assert!(!map.is_instruction_at(BankedAddress::new(0, 0x8000)));
+    assert_eq!(map.references(BankedAddress::new(2, 0xC000)), vec![BankedAddress::new(1, 0x8001), BankedAddress::new(2, 0xC006)]);
+    assert!(map.unresolved().contains(&(BankedAddress::new(2, 0xC003), 0x8000)));
+
+    let mut symbols = SymbolTable::new();
+    symbols.insert_banked(BankedAddress::new(2, 0xC000), "reset");
+    symbols.insert_banked(BankedAddress::new(1, 0x8000), "bank1");
+    let listing = listing::format(&analysis::disassemble_image(&image, &map), &symbols);
+    assert_eq!(listing[0], "00:$8000: 60       .BYTE $60");
+    assert_eq!(listing[1], "bank1:");
+    assert_eq!(listing[3], "01:$8001: 20 00 C0 JSR reset");
+    assert!(listing.contains(&String::from("02:$C000: 20 00 80 JSR bank1")));
+
+    // without windows, a call into another bank's segment goes to the only bank covering the target
+    // 00:$8000: JSR $9000 / JSR $8000 (both banks cover $8000, the caller's wins), 01:$9000: INX / RTS, 02:$8000: RTS
+    let image = MemoryImage::from(vec![Segment::banked(0, 0x8000, vec![0x20, 0x00, 0x90, 0x20, 0x00, 0x80]),
+                                       Segment::banked(1, 0x9000, vec![0xE8, 0x60]),
+                                       Segment::banked(2, 0x8000, vec![0x60])]);
+    let map = analysis::analyze_banked(&image, &BankLayout::new(), &[BankedAddress::new(0, 0x8000)], &[]);
+    assert_eq!(map.region_at(BankedAddress::new(1, 0x9001)), Region::Inferred);
+    assert_eq!(map.references(BankedAddress::new(0, 0x8000)), vec![BankedAddress::new(0, 0x8003)]);
+    assert_eq!(map.region_at(BankedAddress::new(2, 0x8000)), Region::Unknown);
+    assert!(map.unresolved().is_empty());
+}
+
+#[test]
+fn check_platform_symbols() {
+    use disasm6502::platform::{Platform, PLATFORMS};
+
+    for platform in PLATFORMS.iter() {
+        let symbols = platform.symbols();
+        assert!(!symbols.is_empty());
+        assert!(symbols.iter().all(|(&a, _)| symbols.description_banked(a).is_some()));
+        assert_eq!(Platform::parse(platform.name()), Some(*platform));
+    }
+
+    // $C000: LDA $D012 / STA $D020 / JSR $FFD2
+    let bytes = vec![0xAD, 0x12, 0xD0, 0x8D, 0x20, 0xD0, 0x20, 0xD2, 0xFF];
+    let c64 = Platform::C64.symbols();
+    let listing: Vec<String> = disasm6502::from_addr_array(&bytes, 0xC000).unwrap().iter().map(|i| c64.format_instruction(i)).collect();
+    assert_eq!(listing, vec!["LDA VIC_RASTER", "STA VIC_BORDER", "JSR CHROUT"]);
+
+    assert_eq!(Platform::Nes.symbols().get(0x2002), Some("PPUSTATUS"));
+    assert_eq!(Platform::Atari8Bit.symbols().get(0xD40A), Some("WSYNC"));
+    assert_eq!(Platform::Atari2600.symbols().get(0x0002), Some("WSYNC"));
+    assert_eq!(Platform::Atari2600.symbols().get(0x003C), Some("INPT4"));
+    // $0C is REFP1 for stores and INPT4 for loads, $00 is VSYNC for stores and CXM0P for BIT
+    let tia = Platform::Atari2600.symbols();
+    let format = |bytes: &[u8]| tia.format_instruction(&disasm6502::instruction::decode(0xF000, &mut 0, bytes));
+    assert_eq!(format(&[0xA5, 0x0C]), "LDA INPT4");
+    assert_eq!(format(&[0x24, 0x00]), "BIT CXM0P");
+    assert_eq!(format(&[0x85, 0x0C]), "STA REFP1");
+    assert_eq!(format(&[0x86, 0x00]), "STX VSYNC");
+    assert_eq!(format(&[0xC5, 0x3C]), "CMP INPT4");
+    assert_eq!(Platform::Apple2.symbols().get(0xFDED), Some("COUT"));
+    assert_eq!((Platform::Apple2.symbols().get(0xC058), Platform::Apple2.symbols().get(0xC059)), (Some("CLRAN0"), Some("SETAN0")));
+    assert_eq!(Platform::Vic20.symbols().get(0x911F), Some("VIA1_ORA_NH"));
+    assert_eq!(Platform::Bbc.symbols().get(0xFE4E), Some("SYSVIA_IER"));
+    assert_eq!(Platform::Bbc.symbols().description(0xFFEE), Some("write character"));
+}
+
+#[test]
+fn check_symbol_file_import() {
+    use disasm6502::banking::BankedAddress;
+    use disasm6502::symbol_file::{self, SymbolFormat};
+
+    let vice = "al C:0810 .loop\nal 8:0300 .drive\nal 010900 .far\n";
+    let dbg = "version\tmajor=2,minor=0\n\
+               sym\tid=0,name=\"print\",addrsize=absolute,scope=0,def=1,val=0x900,seg=0,type=lab\n\
+               sym\tid=1,name=\"WIDTH\",addrsize=zeropage,scope=0,def=2,val=0x28,type=equ\n\
+               sym\tid=2,name=\"CHROUT\",addrsize=absolute,scope=0,ref=3,type=imp\n";
+    let map = "Modules list:\n-------------\n\nExports list by name:\n---------------------\n\
+               main                      000810 RLA    WIDTH                     000028 REZ    \n\n\
+               Exports list by value:\n";
+    let mesen = "P:4010:reset:power on\nP:0010:bank0\nR:00FB-00FC:ptr\nG:2000:PPUCTRL\nC:0000:tiles\n";
+    let fceux = "$C000#nmi#frame handler\n$8000/10#table#\n";
+    let dasm = "--- Symbol List (sorted by symbol)\nloop                     0810              (R )\nname                     \"TEST\"\n--- End of Symbol List.\n";
+    let acme = "loop\t= $0810\t; ?\nlines\t= 25\n";
+
+    assert_eq!(SymbolFormat::detect(vice), Some(SymbolFormat::Vice));
+    assert_eq!(SymbolFormat::detect(dbg), Some(SymbolFormat::Ld65Debug));
+    assert_eq!(SymbolFormat::detect(map), Some(SymbolFormat::Ld65Map));
+    assert_eq!(SymbolFormat::detect(mesen), Some(SymbolFormat::Mesen { prg_size: 0x8000 }));
+    assert_eq!(SymbolFormat::detect(fceux), Some(SymbolFormat::Fceux { bank: None }));
+    assert_eq!(SymbolFormat::detect(dasm), Some(SymbolFormat::Dasm));
+    assert_eq!(SymbolFormat::detect(acme), Some(SymbolFormat::Acme));
+
+    let symbols = symbol_file::parse_any(vice).unwrap();
+    assert_eq!(symbols.get(0x0810), Some("loop"));
+    assert_eq!(symbols.get(0x0300), None);
+    assert_eq!(symbols.banked_address("far"), Some(BankedAddress::new(1, 0x0900)));
+
+    let mut merged = symbol_file::parse_any(dbg).unwrap();
+    assert_eq!((merged.get(0x0900), merged.len()), (Some("print"), 1));
+    merged.extend(symbol_file::parse_any(map).unwrap());
+    assert_eq!((merged.get(0x0810), merged.get(0x0028)), (Some("main"), None));
+
+    let symbols = symbol_file::parse_any(mesen).unwrap();
+    assert_eq!(symbols.get_banked(BankedAddress::new(1, 0xC010)), Some("reset"));
+    assert_eq!(symbols.description_banked(BankedAddress::new(1, 0xC010)), Some("power on"));
+    assert_eq!(symbols.get_banked(BankedAddress::new(0, 0x8010)), Some("bank0"));
+    assert_eq!((symbols.get(0x00FB), symbols.get(0x2000), symbols.len()), (Some("ptr"), Some("PPUCTRL"), 4));
+
+    let symbols = symbol_file::parse(fceux, SymbolFormat::Fceux { bank: Some(3) }).unwrap();
+    assert_eq!(symbols.get_banked(BankedAddress::new(3, 0xC000)), Some("nmi"));
+    assert_eq!(symbols.description_banked(BankedAddress::new(3, 0xC000)), Some("frame handler"));
+    assert_eq!(symbols.get_banked(BankedAddress::new(3, 0x8000)), Some("table"));
+
+    assert_eq!(symbol_file::parse_any(dasm).unwrap().address("loop"), Some(0x0810));
+    let symbols = symbol_file::parse_any(acme).unwrap();
+    assert_eq!((symbols.address("loop"), symbols.address("lines")), (Some(0x0810), Some(25)));
+
+    assert!(symbol_file::parse("al C:zz .bad", SymbolFormat::Vice).is_err());
+}
+
+#[test]
+fn check_symbol_file_export() {
+    use disasm6502::banking::BankedAddress;
+    use disasm6502::symbol_file::{self, SymbolFormat};
+    use disasm6502::symbols::SymbolTable;
+
+    let mut symbols = SymbolTable::new();
+    symbols.insert(0x00FB, "ptr");
+    symbols.insert_banked(BankedAddress::new(0, 0x8010), "init");
+    symbols.insert_banked(BankedAddress::new(1, 0xC000), "reset");
+    symbols.describe_banked(BankedAddress::new(1, 0xC000), "power on, \"cold\" start");
+    symbols.describe(0x2002, "PPU status");
+
+    let mesen = symbol_file::export(&symbols, SymbolFormat::Mesen { prg_size: 0x8000 }).unwrap();
+    assert_eq!(mesen, "R:00FB:ptr\nG:2002::PPU status\nP:0010:init\nP:4000:reset:power on, \"cold\" start\n");
+    assert_eq!(symbol_file::parse(&mesen, SymbolFormat::Mesen { prg_size: 0x8000 }).unwrap(), symbols);
+
+    let fceux = symbol_file::export(&symbols, SymbolFormat::Fceux { bank: Some(1) }).unwrap();
+    assert_eq!(fceux, "$C000#reset#power on, \"cold\" start\n");
+
+    let csv = symbol_file::export(&symbols, SymbolFormat::Csv).unwrap();
+    assert!(csv.contains("01,C000,reset,\"power on, \"\"cold\"\" start\"\n"));
+    assert_eq!(symbol_file::parse_any(&csv).unwrap(), symbols);
+
+    let vice = symbol_file::export(&symbols, SymbolFormat::Vice).unwrap();
+    assert_eq!(vice, "al C:00FB .ptr\nal C:8010 .init\nal C:C000 .reset\n");
+    assert_eq!(symbol_file::parse_any(&symbol_file::export(&symbols, SymbolFormat::Acme).unwrap()).unwrap().len(), 3);
+    assert!(symbol_file::export(&symbols, SymbolFormat::Ld65Map).is_err());
+}
+
+#[test]
+fn check_project_file() {
+    use disasm6502::banking::BankedAddress;
+    use disasm6502::memory_image::MemoryImage;
+    use disasm6502::operand::OperandFormat;
+    use disasm6502::platform::Platform;
+    use disasm6502::project::{CpuVariant, InputFormat, Project, RegionOverride, RegionType};
+    use disasm6502::segment::Segment;
+
+    // $C000: LDA #$0D / STA $D020 / BEQ $C00A / JMP $C00B, $C00A: 1A (undocumented NOP),
+    // $C00B: 60 60 (data region), $C00D: RTS (code region)
+    let image = MemoryImage::from(vec![Segment::new(0xC000, vec![0xA9, 0x0D, 0x8D, 0x20, 0xD0, 0xF0, 0x03, 0x4C,
+                                                                 0x0B, 0xC0, 0x1A, 0x60, 0x60, 0x60])]);
+    let at = BankedAddress::unbanked;
+
+    let mut project = Project::new("game.bin");
+    project.load_address = Some(at(0xC000));
+    project.cpu = CpuVariant::Documented6502;
+    project.platform = Some(Platform::C64);
+    project.entry_points.push(at(0xC000));
+    project.regions.push(RegionOverride { start: at(0xC00B), end: 0xC00C, region_type: RegionType::Data });
+    project.regions.push(RegionOverride { start: at(0xC00D), end: 0xC00D, region_type: RegionType::Code });
+    project.symbols.insert(0xC000, "start");
+    project.symbols.describe(0xC000, "entry \"point\"");
+    project.annotations.comments.insert(at(0xC002), String::from("border"));
+    project.annotations.block_comments.insert(at(0xC000), String::from("Main program\nsets the border"));
+    project.annotations.operand_formats.insert(at(0xC000), OperandFormat::Decimal);
+
+    let text = project.to_string();
+    assert!(text.contains("[[region]]\nstart = \"$C00B\"\nend = \"$C00C\"\ntype = \"data\"\n"));
+    assert!(text.contains("[operands]\n\"$C000\" = \"decimal\"\n"));
+    assert_eq!(Project::parse(&text).unwrap(), project);
+
+    let path = std::env::temp_dir().join("disasm6502_project_test.toml");
+    let path = path.to_str().unwrap();
+    project.save(path).unwrap();
+    assert_eq!(Project::load(path).unwrap(), project);
+    std::fs::remove_file(path).unwrap();
+
+    // banked raw binary next to the project file, loaded from another working directory
+    let directory = std::env::temp_dir().join("disasm6502_project_dir");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("bank1.bin"), [0xEA, 0x60]).unwrap();
+    let mut banked = Project::new("bank1.bin");
+    banked.format = Some(InputFormat::Binary);
+    banked.load_address = Some(BankedAddress::new(1, 0x8000));
+    let text = banked.to_string();
+    assert!(text.contains("format = \"binary\"\nload_address = \"01:$8000\"\n"));
+    assert_eq!(Project::parse(&text).unwrap(), banked);
+    let loaded = banked.load_image(directory.join("project.toml").to_str().unwrap()).unwrap();
+    assert_eq!(loaded.segments()[0], Segment::banked(1, 0x8000, vec![0xEA, 0x60]));
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    // container formats
+    let mut nes = vec![0x4E, 0x45, 0x53, 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    nes.extend_from_slice(&[0xEA; 0x4000]);
+    let mut container = Project::parse("input = \"game.nes\"\nformat = \"nes\"").unwrap();
+    assert_eq!(container.image(&nes).unwrap().segments()[1].to_string(), "00:$C000-$FFFF (16384 bytes)");
+    container.format = Some(InputFormat::D64);
+    assert!(container.image(&nes).is_err());
+    assert!(Project::parse("format = \"zip\"").is_err());
+
+    let listing = project.listing(&image);
+    assert_eq!(&listing[..4], &["; Main program", "; sets the border", "start:", "$C000: A9 0D    LDA #13"]);
+    assert_eq!(listing[4], "$C002: 8D 20 D0 STA VIC_BORDER ; border");
+    // the undocumented NOP and the data region are not disassembled
+    assert_eq!(listing[7], "$C00A: 1A       .BYTE $1A");
+    assert_eq!(listing[8], "$C00B: 60       .BYTE $60");
+    assert_eq!(listing[9], "$C00C: 60       .BYTE $60");
+    assert_eq!(listing[10], "$C00D: 60       RTS ");
+
+    assert!(Project::parse("cpu = \"z80\"").is_err());
+    assert!(Project::parse("[labels]\n\"$C000\" = start").is_err());
+    // regions need a start, end and type
+    assert!(Project::parse("[[region]]\nstart = \"$C000\"\nend = \"$C0FF\"\ntype = \"code\"").is_ok());
+    assert!(Project::parse("[[region]]\nstart = \"$C000\"\ntype = \"code\"").is_err());
+    assert!(Project::parse("[[region]]\nstart = \"$C000\"\nend = \"$C0FF\"").is_err());
+    assert!(Project::parse("[[region]]\n\n[labels]\n\"$C000\" = \"start\"").is_err());
+}
+
+#[test]
+fn check_operand_formats() {
+    use disasm6502::instruction::decode;
+    use disasm6502::operand::OperandFormat;
+
+    let lda = decode(0x0800, &mut 0, &[0xA9, 0x28]);
+    let sta = decode(0x0802, &mut 0, &[0x9D, 0x00, 0x04]);
+    let format = |text: &str, i| OperandFormat::parse(text).unwrap().format_instruction(i);
+
+    assert_eq!(lda.as_str(), "LDA #$28");
+    assert_eq!(format("decimal", &lda), "LDA #40");
+    assert_eq!(format("binary", &lda), "LDA #%00101000");
+    assert_eq!(format("char", &lda), "LDA #'('");
+    assert_eq!(format("symbol:SCREEN_WIDTH", &lda), "LDA #SCREEN_WIDTH");
+    assert_eq!(format("low:table", &lda), "LDA #<table");
+    assert_eq!(format("high:table+1", &lda), "LDA #>table+1");
+    assert_eq!(format("expr:COLS*2", &lda), "LDA #COLS*2");
+
+    assert_eq!(format("decimal", &sta), "STA 1024,X");
+    assert_eq!(format("binary", &sta), "STA %0000010000000000,X");
+    assert_eq!(format("char", &sta), "STA $0400,X");
+    assert_eq!(format("expr:screen+$100", &sta), "STA screen+$100,X");
+    assert_eq!(OperandFormat::low_byte("table", -1).to_string(), "low:table-1");
+
+    // operand-less instructions are unaffected
+    assert_eq!(format("decimal", &decode(0x0800, &mut 0, &[0x60])), "RTS ");
+}
+
+#[test]
+fn check_pointer_pairs() {
+    use disasm6502::banking::BankedAddress;
+    use disasm6502::memory_image::MemoryImage;
+    use disasm6502::pointers::{self, PointerPair};
+    use disasm6502::project::Project;
+    use disasm6502::segment::Segment;
+    use disasm6502::analysis;
+
+    // $C000: LDA #$C0 / STA $FC / LDA #$1A / STA $FB   (high byte stored first)
+    // $C008: LDA #<text / LDY #>text / JSR $AB1E / LDX #$01 / LDY #$C0 / JSR $FFBD
+    // $C016: LDA #$08 / LDX #$00 / LDY #$C0 / JSR $FFBA  (three registers: ambiguous)
+    // $C01F: LDA #$FF / LDX #$FF / JSR $FFD2 (target outside of the image) / RTS
+    // $C027: "HI"
+    let bytes = vec![0xA9, 0xC0, 0x85, 0xFC, 0xA9, 0x1A, 0x85, 0xFB,
+                     0xA9, 0x27, 0xA0, 0xC0, 0x20, 0x1E, 0xAB, 0xA2, 0x01, 0xA0, 0xC0, 0x20, 0xBD, 0xFF,
+                     0xA9, 0x08, 0xA2, 0x00, 0xA0, 0xC0, 0x20, 0xBA, 0xFF,
+                     0xA9, 0xFF, 0xA2, 0xFF, 0x20, 0xD2, 0xFF, 0x60, 0x48, 0x49];
+    let at = BankedAddress::unbanked;
+    let map = analysis::analyze(&bytes, 0xC000, &[0xC000], &[]);
+    let lines = analysis::disassemble(&bytes, 0xC000, &map);
+
+    assert_eq!(pointers::find_pointer_pairs(&lines), vec![
+        PointerPair { low: at(0xC004), high: at(0xC000), target: at(0xC01A), offset: 0 },
+        PointerPair { low: at(0xC008), high: at(0xC00A), target: at(0xC027), offset: 0 },
+        PointerPair { low: at(0xC00F), high: at(0xC011), target: at(0xC001), offset: 1 }
+    ]);
+
+    let mut project = Project::new("test.bin");
+    project.entry_points.push(at(0xC000));
+    project.symbols.insert(0xC027, "text");
+    project.annotations.operand_formats.insert(at(0xC00F), disasm6502::operand::OperandFormat::Decimal);
+    let listing = project.listing(&MemoryImage::from(vec![Segment::new(0xC000, bytes.clone())]));
+
+    // the target inside the instruction at $C000 is addressed through the label of its line
+    assert_eq!(&listing[0..2], &["LC000:", "$C000: A9 C0    LDA #>LC01A"]);
+    assert_eq!(listing[4], "$C006: 85 FB    STA $FB");
+    assert_eq!(listing[5], "$C008: A9 27    LDA #<text");
+    assert_eq!(listing[6], "$C00A: A0 C0    LDY #>text");
+    assert_eq!(listing[8], "$C00F: A2 01    LDX #1");
+    assert_eq!(listing[9], "$C011: A0 C0    LDY #>LC000+1");
+    assert_eq!(&listing[13..15], &["LC01A:", "$C01A: A0 C0    LDY #$C0"]);
+    assert_eq!(listing[17], "$C021: A2 FF    LDX #$FF");
+
+    // $C000: LDA #$06 / STA $D020 / LDA #$0E / STA $D021 / RTS - colors, not a pointer to $0E06
+    let bytes = vec![0xA9, 0x06, 0x8D, 0x20, 0xD0, 0xA9, 0x0E, 0x8D, 0x21, 0xD0, 0x60];
+    let map = analysis::analyze(&bytes, 0xC000, &[0xC000], &[]);
+    assert!(pointers::find_pointer_pairs(&analysis::disassemble(&bytes, 0xC000, &map)).is_empty());
+
+    // banked: 00:$8000 builds a pointer to its own string at $8010, 01:$800F: LDA $0000 covers $8010 in bank 1
+    let mut bank0 = vec![0xA9, 0x10, 0x85, 0xFB, 0xA9, 0x80, 0x85, 0xFC, 0x60];
+    bank0.resize(0x10, 0xEA);
+    bank0.extend_from_slice(b"HELLO");
+    let mut bank1 = vec![0x60; 0x0F];
+    bank1.extend_from_slice(&[0xAD, 0x00, 0x00, 0x60]);
+    let image = MemoryImage::from(vec![Segment::banked(0, 0x8000, bank0), Segment::banked(1, 0x8000, bank1)]);
+    let map = analysis::analyze_banked(&image, &disasm6502::banking::BankLayout::new(),
+                                       &[BankedAddress::new(0, 0x8000), BankedAddress::new(1, 0x800F)], &[]);
+    let pairs = pointers::find_pointer_pairs(&analysis::disassemble_image(&image, &map));
+    assert_eq!(pairs, vec![PointerPair { low: BankedAddress::new(0, 0x8000), high: BankedAddress::new(0, 0x8004),
+                                         target: BankedAddress::new(0, 0x8010), offset: 0 }]);
+
+    let mut project = Project::new("test.bin");
+    project.entry_points.extend(vec![BankedAddress::new(0, 0x8000), BankedAddress::new(1, 0x800F)]);
+    let listing = project.listing(&image);
+    assert_eq!(listing[0], "00:$8000: A9 10    LDA #<L00_8010");
+    assert_eq!(listing.iter().filter(|l| l.starts_with("L00_8010:")).count(), 1);
+    assert!(!listing.iter().any(|l| l.starts_with("L8010") || l.starts_with("L800F")));
+}
+
+#[test]
+fn check_text_strings() {
+    use disasm6502::analysis::{self, Line};
+    use disasm6502::banking::BankedAddress;
+    use disasm6502::memory_image::MemoryImage;
+    use disasm6502::operand::OperandFormat;
+    use disasm6502::platform::Platform;
+    use disasm6502::project::Project;
+    use disasm6502::segment::Segment;
+    use disasm6502::listing;
+    use disasm6502::symbols::SymbolTable;
+    use disasm6502::text::{self, Encoding, Termination};
+
+    // $C000: LDA #$C1 / RTS
+    // $C003: "READY" with the high bit set on the last character, $FF
+    // $C009: length-prefixed "LOAD", $FF, "Hi there" in lower case PETSCII followed by a zero byte
+    // $C01B: "@@@@@" in screen codes (not text)
+    let bytes = vec![0xA9, 0xC1, 0x60,
+                     0x52, 0x45, 0x41, 0x44, 0xD9, 0xFF,
+                     0x04, 0x4C, 0x4F, 0x41, 0x44, 0xFF, 0xC8, 0x49, 0x20, 0x54, 0x48, 0x45, 0x52, 0x45, 0x00,
+                     0x00, 0x00, 0x00, 0x00, 0x00];
+    let at = BankedAddress::unbanked;
+    let map = analysis::analyze(&bytes, 0xC000, &[0xC000], &[]);
+    let lines = analysis::disassemble(&bytes, 0xC000, &map);
+
+    let found = |encoding| -> Vec<(u16, String, Termination)> {
+        text::find_strings(&lines, encoding, 4).into_iter().map(|s| (s.start.address, s.text, s.termination)).collect()
+    };
+    assert_eq!(found(Encoding::PetsciiUpper), vec![(0xC003, String::from("READY"), Termination::HighBit),
+                                                   (0xC009, String::from("LOAD"), Termination::LengthPrefix),
+                                                   (0xC010, String::from("I THERE"), Termination::Null)]);
+    assert_eq!(found(Encoding::PetsciiLower), vec![(0xC003, String::from("readY"), Termination::Unterminated),
+                                                   (0xC009, String::from("load"), Termination::LengthPrefix),
+                                                   (0xC00F, String::from("Hi there"), Termination::Null)]);
+    assert!(found(Encoding::ScreenCode).is_empty());
+    assert_eq!(Encoding::Atascii.decode(0x60), None);
+
+    let strings = text::find_strings(&lines, Encoding::PetsciiUpper, 4);
+    let merged = text::apply(&lines, &strings);
+    assert_eq!(merged[2], Line::Text(strings[0].clone()));
+    assert_eq!(merged[3], Line::Data(at(0xC008), 0xFF));
+    assert_eq!(merged[4].to_string(), "$C009: 04 4C 4F .PTEXT \"LOAD\"");
+
+    // strings in consecutive banks, the first one ending on the last byte of its bank
+    let image = MemoryImage::from(vec![Segment::banked(0, 0x8000, b"HELLO".to_vec()),
+                                       Segment::banked(1, 0x8000, b"WORLD".to_vec())]);
+    let lines = analysis::disassemble_image(&image, &analysis::analyze_image(&image, &[], &[]));
+    let strings = text::find_strings(&lines, Encoding::Ascii, 4);
+    assert_eq!(strings.len(), 2);
+    let merged: Vec<String> = text::apply(&lines, &strings).iter().map(|l| l.to_string()).collect();
+    assert_eq!(merged, vec!["00:$8000: 48 45 4C .TEXT \"HELLO\"", "01:$8000: 57 4F 52 .TEXT \"WORLD\""]);
+
+    let mut project = Project::new("test.bin");
+    project.platform = Some(Platform::C64);
+    project.encoding = Some(Encoding::PetsciiLower);
+    project.entry_points.push(at(0xC000));
+    project.annotations.operand_formats.insert(at(0xC000), OperandFormat::Char);
+    assert_eq!(Project::parse(&project.to_string()).unwrap().encoding, Some(Encoding::PetsciiLower));
+
+    let listing = project.listing(&MemoryImage::from(vec![Segment::new(0xC000, bytes.clone())]));
+    assert_eq!(listing[0], "$C000: A9 C1    LDA #'A'");
+    assert_eq!(&listing[2..13], &["        .ENC \"petscii-lower\"",
+                                  "        .CDEF \" @\", $20",
+                                  "        .CDEF \"az\", $41",
+                                  "        .CDEF \"[[\", $5B",
+                                  "        .CDEF \"]]\", $5D",
+                                  "        .CDEF \"AZ\", $C1",
+                                  "$C003: 52 45 41 .TEXT \"readY\"",
+                                  "$C008: FF       .BYTE $FF",
+                                  "$C009: 04 4C 4F .PTEXT \"load\"",
+                                  "$C00E: FF       .BYTE $FF",
+                                  "$C00F: C8 49 20 .NULL \"Hi there\""]);
+
+    // ASCII switches back to the built-in encoding; a shifted letter from the $61-$7A copies
+    // would assemble to a different byte, so that string is kept as bytes
+    let bytes = vec![0x48, 0x45, 0x4C, 0xCC, 0xCF, 0x00, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x00, 0x48, 0x45, 0x4C, 0xCC, 0x6F, 0x00];
+    let lines = analysis::disassemble(&bytes, 0xC000, &analysis::analyze(&bytes, 0xC000, &[], &[]));
+    let mut strings = text::find_strings(&lines, Encoding::PetsciiLower, 4);
+    strings[1] = text::find_strings(&lines, Encoding::Ascii, 4).remove(1);
+    let listing = listing::format(&text::apply(&lines, &strings), &SymbolTable::new());
+    assert_eq!(listing[0], "        .ENC \"petscii-lower\"");
+    assert_eq!(listing[6], "$C000: 48 45 4C .NULL \"helLO\"");
+    assert_eq!(&listing[7..], &["        .ENC \"none\"",
+                                "$C006: 68 65 6C .NULL \"hello\"",
+                                "$C00C: 48 45 4C .BYTE $48, $45, $4C, $CC, $6F, $00 ; .NULL \"helLO\""]);
+}